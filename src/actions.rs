@@ -0,0 +1,126 @@
+use crate::summary::Summary;
+use std::{env, fs::OpenOptions, io::Write};
+
+/// Whether anything changed is the `changes` step output, so a run that did something still passes.
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_PARTIAL_FAILURE: i32 = 3;
+
+pub fn enabled() -> bool {
+    env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+}
+
+pub fn exit_code(summary: &Summary) -> i32 {
+    if !summary.errors.is_empty() {
+        EXIT_PARTIAL_FAILURE
+    } else {
+        EXIT_OK
+    }
+}
+
+fn escape(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+pub fn report(summary: &Summary) {
+    for name in &summary.added {
+        println!("::notice title=Added::{}", escape(name));
+    }
+    for name in &summary.removed {
+        println!("::notice title=Archived::{}", escape(name));
+    }
+    for moved in &summary.transferred {
+        println!("::notice title=Moved::{}", escape(moved));
+    }
+    for name in &summary.unavailable {
+        println!("::warning title=Unavailable::{}", escape(name));
+    }
+    for name in &summary.unmatched {
+        println!("::notice title=Unmatched manual row::{}", escape(name));
+    }
+    for repo in &summary.updated {
+        println!(
+            "::notice title=Updated::{}",
+            escape(&format!(
+                "{} release: {:?}, commit: {:?}, push: {:?}, branch: {:?}",
+                repo.name, repo.release, repo.commit, repo.push, repo.branch
+            ))
+        );
+    }
+    for err in &summary.errors {
+        println!(
+            "::error title={} failed::{}",
+            err.operation,
+            escape(&err.to_string())
+        );
+    }
+    if let Ok(path) = env::var("GITHUB_STEP_SUMMARY") {
+        append(&path, &step_summary(summary));
+    }
+    if let Ok(path) = env::var("GITHUB_OUTPUT") {
+        append(&path, &outputs(summary));
+    }
+}
+
+fn append(path: &str, content: &str) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+}
+
+/// Step outputs for later steps to go by, e.g. `if: steps.sync.outputs.changes == 'true'`.
+fn outputs(summary: &Summary) -> String {
+    format!(
+        "changes={}\nadded={}\narchived={}\nupdated={}\nerrors={}\n",
+        summary.has_changes(),
+        summary.added.len(),
+        summary.removed.len(),
+        summary.updated.len(),
+        summary.errors.len()
+    )
+}
+
+fn step_summary(summary: &Summary) -> String {
+    let mut markdown = format!(
+        "## notionstar\n\n{} added, {} archived, {} updated, {} errors\n\n",
+        summary.added.len(),
+        summary.removed.len(),
+        summary.updated.len(),
+        summary.errors.len()
+    );
+    if summary.has_changes() {
+        markdown +=
+            "| Change | Repo | Release | Commit | Push |\n| --- | --- | --- | --- | --- |\n";
+        for name in &summary.added {
+            markdown += &format!("| added | {} | | | |\n", name);
+        }
+        for name in &summary.removed {
+            markdown += &format!("| archived | {} | | | |\n", name);
+        }
+        for repo in &summary.updated {
+            markdown += &format!(
+                "| updated | {} | {} | {} | {} |\n",
+                repo.name,
+                repo.release
+                    .map(|date| date.to_string())
+                    .unwrap_or_default(),
+                repo.commit.map(|date| date.to_string()).unwrap_or_default(),
+                repo.push.map(|date| date.to_string()).unwrap_or_default()
+            );
+        }
+    }
+    if !summary.unmatched.is_empty() {
+        markdown += &format!(
+            "\nUnmatched manual rows (not archived): {}\n",
+            summary.unmatched.join(", ")
+        );
+    }
+    for err in &summary.errors {
+        markdown += &format!("\n> {}\n", err.to_string().replace('\n', " "));
+    }
+    markdown
+}
@@ -0,0 +1,87 @@
+use crate::Notion;
+use notion::{
+    chrono::{SecondsFormat, TimeZone, Utc},
+    models::Page,
+};
+use serde_json::{json, Value};
+use std::{fs::OpenOptions, io::Write, sync::Mutex};
+
+/// The run records are filed under, and the millisecond its id was taken from.
+static RUN: Mutex<Option<(i64, String)>> = Mutex::new(None);
+
+/// Gives the records that follow a new run id, e.g. `20250131-120000.123`. Ids go up, also for
+/// runs started within the same millisecond.
+pub fn start_run() -> String {
+    let mut run = RUN.lock().unwrap();
+    let last = run.as_ref().map_or(i64::MIN, |(millis, _)| *millis);
+    let millis = Utc::now().timestamp_millis().max(last + 1);
+    let id = Utc
+        .timestamp_millis_opt(millis)
+        .unwrap()
+        .format("%Y%m%d-%H%M%S%.3f")
+        .to_string();
+    *run = Some((millis, id.clone()));
+    id
+}
+
+/// The current run's id, one is started for commands that write outside of a sync.
+fn run() -> String {
+    let current = RUN.lock().unwrap().as_ref().map(|(_, id)| id.clone());
+    current.unwrap_or_else(start_run)
+}
+
+/// Appends what a write that went through changed to the `audit_log`, when there's one: a JSON object
+/// per property and line with the time and run, the `operation` behind it, the page, and the value
+/// before and after. `body` is what was sent, `before` the page as it was read when there's one.
+pub fn record(
+    notion: &Notion,
+    operation: &str,
+    page_id: Option<&str>,
+    before: Option<&Page>,
+    body: &Value,
+) {
+    let Some(path) = &notion.config.audit_log else {
+        return;
+    };
+    let time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let run = run();
+    let line = |property: &str, old: Option<Value>, new: &Value| {
+        json!({
+            "time": time,
+            "run": run,
+            "operation": operation,
+            "page_id": page_id,
+            "property": property,
+            "old": old,
+            "new": new,
+        })
+        .to_string()
+            + "\n"
+    };
+    let mut lines = String::new();
+    if let Some(archived) = body.get("archived") {
+        lines += &line(
+            "archived",
+            before.map(|page| json!(page.archived)),
+            archived,
+        );
+    }
+    for (name, new) in body["properties"].as_object().into_iter().flatten() {
+        let old = before
+            .and_then(|page| page.properties.properties.get(name))
+            .map(|old| json!(old));
+        lines += &line(name, old, new);
+    }
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()));
+    if let Err(err) = written {
+        notion.ui.error(format!(
+            "failed to write audit log {}: {}",
+            path.display(),
+            err
+        ));
+    }
+}
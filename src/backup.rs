@@ -0,0 +1,122 @@
+use crate::{audit, Notion, Ui};
+use notion::{chrono::Local, models::Page};
+use serde_json::{json, Map, Value};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+const WRITABLE_TYPES: [&str; 10] = [
+    "title",
+    "rich_text",
+    "number",
+    "select",
+    "multi_select",
+    "date",
+    "checkbox",
+    "url",
+    "email",
+    "phone_number",
+];
+
+/// Exits when the backup can't be written, rather than archive pages with no copy to restore.
+pub fn write_backup(ui: &Ui, pages: &[&Page]) -> PathBuf {
+    let dir = PathBuf::from(env::var("BACKUP_DIR").unwrap_or("backups".to_string()));
+    let fail = |err: std::io::Error| -> ! {
+        ui.error(format!(
+            "can't write a backup to {}: {}",
+            dir.display(),
+            err
+        ));
+        std::process::exit(1);
+    };
+    fs::create_dir_all(&dir).unwrap_or_else(|err| fail(err));
+    let stamp = Local::now().format("%Y%m%d-%H%M%S%.3f");
+    // runs close enough together to share the millisecond get a counter rather than overwrite
+    let (path, mut file) = (0..)
+        .find_map(|n| {
+            let path = match n {
+                0 => dir.join(format!("archive-{}.json", stamp)),
+                n => dir.join(format!("archive-{}-{}.json", stamp, n)),
+            };
+            let file = OpenOptions::new().write(true).create_new(true).open(&path);
+            match file {
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => None,
+                file => Some((path, file.unwrap_or_else(|err| fail(err)))),
+            }
+        })
+        .expect("an unbounded range");
+    let entries = pages
+        .iter()
+        .map(|page| {
+            json!({
+                "id": page.id.to_string(),
+                "title": page.title(),
+                "properties": page.properties.properties,
+            })
+        })
+        .collect::<Vec<Value>>();
+    // properties that came from serde_json always serialize
+    file.write_all(serde_json::to_string_pretty(&entries).unwrap().as_bytes())
+        .unwrap_or_else(|err| fail(err));
+    path
+}
+
+pub async fn restore(notion: &Notion, path: &Path) {
+    let entries = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| serde_json::from_str::<Vec<Value>>(&text).map_err(|err| err.to_string()));
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(err) => {
+            notion
+                .ui
+                .error(format!("can't read backup {}: {}", path.display(), err));
+            std::process::exit(1);
+        }
+    };
+    for entry in entries {
+        let (Some(id), Some(properties)) = (entry["id"].as_str(), entry["properties"].as_object())
+        else {
+            notion
+                .ui
+                .error(format!("skipping a malformed backup entry: {}", entry));
+            continue;
+        };
+        let title = entry["title"].as_str().unwrap_or(id);
+        let body = json!({ "archived": false });
+        match notion.api.update_page(id, body.clone()).await {
+            Ok(_) => {
+                audit::record(notion, "restore", Some(id), None, &body);
+                notion.ui.println(format!("unarchived {}", title));
+                continue;
+            }
+            Err(err) if err.status == 404 || err.code.as_deref() == Some("object_not_found") => {}
+            // anything else (no access, rate limits) would leave a duplicate next to the original
+            Err(err) => {
+                notion
+                    .ui
+                    .error(format!("failed to restore {}: {}", title, err));
+                continue;
+            }
+        }
+        // the page is gone for good (e.g. emptied from trash), recreate it from its properties
+        let properties = properties
+            .iter()
+            .filter(|(_, value)| WRITABLE_TYPES.contains(&value["type"].as_str().unwrap_or("")))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Map<String, Value>>();
+        let body = json!({ "properties": properties });
+        match notion.api.create_page(body.clone()).await {
+            Ok(created) => {
+                audit::record(notion, "recreate", Some(created.id()), None, &body);
+                notion.ui.println(format!("recreated {}", title))
+            }
+            Err(err) => notion
+                .ui
+                .error(format!("failed to restore {}: {}", title, err)),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{sync, ui::Ui, Notion, SyncArgs};
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
+    process,
+    time::{Duration, Instant},
+};
+
+/// How long full syncs over a set of fixtures took.
+pub struct Bench {
+    pub stars: usize,
+    pub pages: usize,
+    pub runs: Vec<Duration>,
+    /// peak resident memory of the process in bytes, where the platform tells
+    pub peak_memory: Option<u64>,
+}
+
+impl Display for Bench {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let total = self.runs.iter().sum::<Duration>();
+        let mean = total / self.runs.len().max(1) as u32;
+        let min = self.runs.iter().min().copied().unwrap_or_default();
+        let max = self.runs.iter().max().copied().unwrap_or_default();
+        let repos = (self.stars + self.pages) * self.runs.len();
+        write!(
+            f,
+            "{} runs over {} stars and {} pages: mean {:.1?}, min {:.1?}, max {:.1?}, {:.0} repos/s",
+            self.runs.len(),
+            self.stars,
+            self.pages,
+            mean,
+            min,
+            max,
+            repos as f64 / total.as_secs_f64().max(f64::EPSILON)
+        )?;
+        match self.peak_memory {
+            Some(bytes) => write!(f, "\npeak memory: {:.1} MiB", bytes as f64 / 1048576.0),
+            None => write!(f, "\npeak memory: unavailable on this platform"),
+        }
+    }
+}
+
+/// `VmHWM` of `/proc/self/status`, Linux only.
+fn peak_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kb * 1024)
+}
+
+/// Runs full syncs `runs` times against fixtures saved with `--record`, to measure the planning
+/// and reconciliation without the network. Writes go nowhere, each run starts from an empty
+/// state, and nothing that would reach outside (notifiers, the webhook, the LLM, the
+/// registries and package indexes behind enrichment) is done.
+pub async fn bench(fixtures: &Path, runs: usize) -> Bench {
+    let scratch = env::temp_dir().join(format!("notionstar-bench-{}", process::id()));
+    env::set_var("STATE_FILE", scratch.join("state.json"));
+    env::set_var("BACKUP_DIR", scratch.join("backups"));
+    for var in ["WEBHOOK_URL", "DASHBOARD_PAGE"] {
+        env::remove_var(var);
+    }
+    // a fresh replay each run, the fixtures' database queries are answered in the recorded order
+    let replay = || {
+        let mut notion = Notion::replay(fixtures, Ui::new(true));
+        notion.config.notifiers.clear();
+        notion.config.owners_page = None;
+        notion.llm = None;
+        notion
+    };
+    let notion = replay();
+    let args = SyncArgs {
+        full: true,
+        skip_enrich: true,
+        ..Default::default()
+    };
+
+    let mut bench = Bench {
+        stars: notion.get_stars().await.len(),
+        pages: notion.get_database().await.len(),
+        runs: Vec::new(),
+        peak_memory: None,
+    };
+    for _ in 0..runs {
+        fs::remove_dir_all(&scratch).ok();
+        fs::create_dir_all(&scratch).unwrap();
+        let notion = replay();
+        let started = Instant::now();
+        sync(&notion, &args).await;
+        bench.runs.push(started.elapsed());
+    }
+    fs::remove_dir_all(&scratch).ok();
+    bench.peak_memory = peak_memory();
+    bench
+}
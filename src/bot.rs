@@ -0,0 +1,132 @@
+use crate::{
+    config::NotifierConfig, error::task_failure, notify, shutdown, state::State, sync, Notion,
+    SyncArgs,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// How long a poll for commands waits for one to come in.
+const POLL_WAIT: Duration = Duration::from_secs(30);
+/// How long to wait before polling again after Telegram couldn't be reached.
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Runs a bot command: `/sync`, `/status`, `/mute owner/name` or `/unmute owner/name`, and
+/// returns the reply.
+pub(crate) async fn answer(notion: &Notion, text: &str) -> String {
+    let mut words = text.split_whitespace();
+    // commands in groups come as `/sync@botname`
+    let command = words.next().unwrap_or_default().split('@').next().unwrap();
+    match (command, words.next()) {
+        ("/sync", _) => {
+            notion.ui.println("sync requested from Telegram");
+            sync(notion, &SyncArgs::default()).await.to_string()
+        }
+        ("/status", _) => {
+            let state = State::load();
+            format!(
+                "{} stars, last full sync {}, {} muted, {} notifications waiting",
+                state.starred.len(),
+                state
+                    .last_full_sync
+                    .map_or("never".to_string(), |date| date.to_rfc3339()),
+                state.muted.len(),
+                state
+                    .digests
+                    .values()
+                    .map(|digest| digest.events.len())
+                    .sum::<usize>()
+            )
+        }
+        ("/mute", Some(repo)) => {
+            let mut state = State::load();
+            state.muted.insert(repo.to_lowercase());
+            state.save();
+            format!("muted {}", repo)
+        }
+        ("/unmute", Some(repo)) => {
+            let mut state = State::load();
+            state.muted.remove(&repo.to_lowercase());
+            state.save();
+            format!("unmuted {}", repo)
+        }
+        _ => "commands: /sync, /status, /mute owner/name, /unmute owner/name".to_string(),
+    }
+}
+
+/// The bots that take commands: `telegram` notifiers with a `secret`. Without one a bot only
+/// sends notifications.
+pub(crate) fn commanded(notion: &Notion) -> impl Iterator<Item = (&String, &NotifierConfig)> {
+    notion.config.notifiers.iter().filter(|(_, config)| {
+        matches!(
+            config,
+            NotifierConfig::Telegram {
+                secret: Some(_),
+                ..
+            }
+        )
+    })
+}
+
+/// Takes a bot's commands for `daemon`, which has no endpoint for Telegram to post them to, by
+/// asking Telegram for them until a shutdown. Only messages from the configured chat count, and
+/// the ones sent while the daemon was down are dropped rather than run late. A command holds
+/// `runs` while it runs, so it never overlaps a scheduled sync.
+pub(crate) async fn poll(notion: Arc<Notion>, name: String, runs: Arc<Mutex<()>>) {
+    let config = &notion.config.notifiers[&name];
+    let NotifierConfig::Telegram { chat, .. } = config else {
+        return;
+    };
+    let bot = notify::telegram(config, &notion.http).unwrap();
+    // -1 is the latest update alone, the ones before it are confirmed by asking past it
+    let mut offset = -1;
+    loop {
+        let wait = if offset == -1 {
+            Duration::ZERO
+        } else {
+            POLL_WAIT
+        };
+        let updates = tokio::select! {
+            _ = shutdown::wait() => return,
+            updates = bot.updates(offset, wait) => updates,
+        };
+        let updates = match updates {
+            Ok(updates) => updates,
+            Err(err) => {
+                notion
+                    .ui
+                    .error(format!("failed to read Telegram commands: {}", err));
+                tokio::select! {
+                    _ = shutdown::wait() => return,
+                    _ = tokio::time::sleep(RETRY_AFTER) => continue,
+                }
+            }
+        };
+        let backlog = offset == -1;
+        offset = offset.max(0);
+        for update in updates {
+            offset = offset.max(update["update_id"].as_i64().unwrap_or_default() + 1);
+            let from = update["message"]["chat"]["id"].to_string();
+            if backlog || from != *chat {
+                continue;
+            }
+            let text = update["message"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            let run = runs.lock().await;
+            // a panic (expired token, rate limit) ends the command, not the polling
+            let reply = tokio::spawn({
+                let notion = notion.clone();
+                async move { answer(&notion, &text).await }
+            })
+            .await
+            .unwrap_or_else(|err| format!("notionstar sync failed: {}", task_failure(err)));
+            drop(run);
+            if let Err(err) = bot.say(reply).await {
+                notion
+                    .ui
+                    .error(format!("failed to answer on Telegram: {}", err));
+            }
+        }
+    }
+}
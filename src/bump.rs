@@ -0,0 +1,28 @@
+use semver::Version;
+
+/// How far apart two release tags are: `major`, `minor`, `patch`, or `unknown` when either isn't a
+/// version or the new one isn't newer. In `0.x` a minor bump counts as major, that's where those
+/// break things.
+pub fn bump(previous: &str, new: &str, prefix: &str) -> &'static str {
+    let (Some(previous), Some(new)) = (version(previous, prefix), version(new, prefix)) else {
+        return "unknown";
+    };
+    if new <= previous {
+        "unknown"
+    } else if new.major != previous.major || (new.major == 0 && new.minor != previous.minor) {
+        "major"
+    } else if new.minor != previous.minor {
+        "minor"
+    } else {
+        "patch"
+    }
+}
+
+/// A tag like `v1.2.3`, `cli-v1.2` or `release-2.0.0-rc.1` as a version.
+pub fn version(tag: &str, prefix: &str) -> Option<Version> {
+    let tag = tag.strip_prefix(prefix).unwrap_or(tag);
+    let tag = tag.trim_start_matches(|c: char| !c.is_ascii_digit());
+    Version::parse(tag)
+        .or_else(|_| Version::parse(&format!("{}.0", tag)))
+        .ok()
+}
@@ -0,0 +1,91 @@
+use crate::state::env_number;
+use notion::chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// Gains smaller than this never count as a jump, however small the repo.
+const MIN_STAR_JUMP: u32 = 50;
+
+/// What a run saw of a repo, compared against by the next one.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub release: Option<NaiveDate>,
+    pub commit: Option<NaiveDate>,
+    pub stars: u32,
+    pub archived: bool,
+}
+
+/// A difference between the last run's snapshot of a repo and this one's.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    Release {
+        repo: String,
+        previous: Option<NaiveDate>,
+        release: NaiveDate,
+    },
+    /// at least `STAR_JUMP_PERCENT` (default 10) percent more stars, and at least 50
+    StarJump {
+        repo: String,
+        previous: u32,
+        stars: u32,
+    },
+    /// the owner archived the repository upstream
+    Archived { repo: String },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Release { repo, release, .. } => {
+                write!(f, "new release of {} on {}", repo, release)
+            }
+            Change::StarJump {
+                repo,
+                previous,
+                stars,
+            } => write!(f, "{} jumped from {} to {} stars", repo, previous, stars),
+            Change::Archived { repo } => write!(f, "{} was archived upstream", repo),
+        }
+    }
+}
+
+/// The changes from `previous` to `current`, keyed by `owner/name`. Repos only one side has are
+/// left out, a new star isn't a change of the repo.
+pub fn diff(
+    previous: &BTreeMap<String, Snapshot>,
+    current: &BTreeMap<String, Snapshot>,
+) -> Vec<Change> {
+    let percent = env_number("STAR_JUMP_PERCENT", 10).max(0) as u64;
+    let mut changes = Vec::new();
+    for (repo, now) in current {
+        let Some(before) = previous.get(repo) else {
+            continue;
+        };
+        if let Some(release) = now
+            .release
+            .filter(|&release| Some(release) > before.release)
+        {
+            changes.push(Change::Release {
+                repo: repo.clone(),
+                previous: before.release,
+                release,
+            });
+        }
+        let gained = now.stars.saturating_sub(before.stars);
+        if gained >= MIN_STAR_JUMP && gained as u64 * 100 >= before.stars as u64 * percent {
+            changes.push(Change::StarJump {
+                repo: repo.clone(),
+                previous: before.stars,
+                stars: now.stars,
+            });
+        }
+        if now.archived && !before.archived {
+            changes.push(Change::Archived { repo: repo.clone() });
+        }
+    }
+    changes
+}
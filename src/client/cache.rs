@@ -0,0 +1,265 @@
+use super::{page_key, Contributors, Gist, GithubClient, Milestone, RateLimit, ReleaseInfo};
+use crate::hash;
+use async_trait::async_trait;
+use notion::chrono::{DateTime, NaiveDate, Utc};
+use octocrab::models::Repository;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// `CACHE_TTL_<NAME>` as a duration such as `30m`, or `default`.
+fn ttl(name: &str, default: Duration) -> Duration {
+    env::var(format!("CACHE_TTL_{}", name))
+        .ok()
+        .and_then(|ttl| humantime::parse_duration(&ttl).ok())
+        .unwrap_or(default)
+}
+
+/// Whether a response found anything. Empty ones are also what a failed request comes back as,
+/// so they're kept no longer than `CACHE_TTL_EMPTY`.
+trait Found {
+    fn found(&self) -> bool;
+}
+
+impl<T> Found for Option<T> {
+    fn found(&self) -> bool {
+        self.is_some()
+    }
+}
+
+impl<T> Found for Vec<T> {
+    fn found(&self) -> bool {
+        !self.is_empty()
+    }
+}
+
+fn branch_key(branch: Option<&str>) -> String {
+    branch
+        .map(|branch| format!("-{}", branch.replace('/', "_")))
+        .unwrap_or_default()
+}
+
+/// Answers GitHub reads from responses saved under `dir` while they're younger than their
+/// category's TTL, so runs repeated in quick succession don't spend the API quota again.
+pub struct Cached<C> {
+    inner: C,
+    dir: PathBuf,
+    /// `CACHE_TTL_STARRED` for starred repos, gists and searches, default 1h
+    starred: Duration,
+    /// `CACHE_TTL_RELEASES`, default 6h
+    releases: Duration,
+    /// `CACHE_TTL_REPO` for commits, contributors, pull requests, discussions, milestones and
+    /// READMEs, default 24h
+    repo: Duration,
+    /// `CACHE_TTL_EMPTY` for responses that found nothing, default 5m, never more than their
+    /// category's
+    empty: Duration,
+}
+
+impl<C> Cached<C> {
+    pub fn new(inner: C, dir: &Path) -> Cached<C> {
+        Cached {
+            inner,
+            dir: dir.to_path_buf(),
+            starred: ttl("STARRED", Duration::from_secs(60 * 60)),
+            releases: ttl("RELEASES", Duration::from_secs(6 * 60 * 60)),
+            repo: ttl("REPO", Duration::from_secs(24 * 60 * 60)),
+            empty: ttl("EMPTY", Duration::from_secs(5 * 60)),
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The saved response, if there is one that hasn't expired. Unreadable entries count as misses.
+    fn load<T: DeserializeOwned + Found>(&self, key: &str, ttl: Duration) -> Option<T> {
+        let path = self.path(key);
+        let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        let value: T = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+        let ttl = if value.found() {
+            ttl
+        } else {
+            ttl.min(self.empty)
+        };
+        (age < ttl).then_some(value)
+    }
+
+    /// Failing to write the cache only costs a request next time, so errors are ignored.
+    fn save<T: Serialize>(&self, key: &str, value: &T) {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        if let Ok(content) = serde_json::to_string(value) {
+            fs::write(&path, content).ok();
+        }
+    }
+}
+
+macro_rules! cached {
+    ($self:ident, $key:expr, $ttl:ident, $fetch:expr) => {{
+        let key = $key;
+        match $self.load(&key, $self.$ttl) {
+            Some(value) => value,
+            None => {
+                let value = $fetch.await;
+                $self.save(&key, &value);
+                value
+            }
+        }
+    }};
+}
+
+#[async_trait]
+impl GithubClient for Cached<Box<dyn GithubClient>> {
+    async fn starred_page(&self, page: u32, per_page: u32) -> Vec<Repository> {
+        cached!(
+            self,
+            format!("starred-{}", page_key(page, per_page)),
+            starred,
+            self.inner.starred_page(page, per_page)
+        )
+    }
+
+    async fn starred_since_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)> {
+        cached!(
+            self,
+            format!("starred-since-{}", page_key(page, per_page)),
+            starred,
+            self.inner.starred_since_page(page, per_page)
+        )
+    }
+
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo> {
+        cached!(
+            self,
+            format!("release-{}-{}", owner, name),
+            releases,
+            self.inner.latest_release(owner, name)
+        )
+    }
+
+    /// Not kept, the feed costs no quota and a saved "no releases" would read back as no feed.
+    async fn feed_release(&self, owner: &str, name: &str) -> Option<Option<ReleaseInfo>> {
+        self.inner.feed_release(owner, name).await
+    }
+
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo> {
+        cached!(
+            self,
+            format!("releases-{}-{}", owner, name),
+            releases,
+            self.inner.releases(owner, name)
+        )
+    }
+
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<NaiveDate> {
+        cached!(
+            self,
+            format!("commit-{}-{}{}", owner, name, branch_key(branch)),
+            repo,
+            self.inner.latest_commit(owner, name, branch)
+        )
+    }
+
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<Contributors> {
+        cached!(
+            self,
+            format!("contributors-{}-{}{}", owner, name, branch_key(branch)),
+            repo,
+            self.inner.contributors(owner, name, branch)
+        )
+    }
+
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64> {
+        cached!(
+            self,
+            format!("open-prs-{}-{}", owner, name),
+            repo,
+            self.inner.open_pull_requests(owner, name)
+        )
+    }
+
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate> {
+        cached!(
+            self,
+            format!("discussion-{}-{}", owner, name),
+            repo,
+            self.inner.latest_discussion(owner, name)
+        )
+    }
+
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone> {
+        cached!(
+            self,
+            format!("milestone-{}-{}", owner, name),
+            repo,
+            self.inner.next_milestone(owner, name)
+        )
+    }
+
+    async fn starred_gists_page(&self, page: u32) -> Result<Vec<Gist>, String> {
+        let key = format!("gists-{}", page);
+        if let Some(gists) = self.load(&key, self.starred) {
+            return Ok(gists);
+        }
+        let gists = self.inner.starred_gists_page(page).await?;
+        self.save(&key, &gists);
+        Ok(gists)
+    }
+
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String> {
+        let key = format!("search-{:016x}", hash::fnv1a(query.as_bytes()));
+        if let Some(repos) = self.load(&key, self.starred) {
+            return Ok(repos);
+        }
+        // a failed search is asked again next time
+        let repos = self.inner.search_repositories(query).await?;
+        self.save(&key, &repos);
+        Ok(repos)
+    }
+
+    async fn readme(&self, owner: &str, name: &str) -> Option<String> {
+        cached!(
+            self,
+            format!("readme-{}-{}", owner, name),
+            repo,
+            self.inner.readme(owner, name)
+        )
+    }
+
+    /// Not cached, a move has to be noticed the run it's looked for.
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository> {
+        self.inner.repository(owner, name).await
+    }
+
+    /// Not cached either, a repo coming back has to be noticed the run it's looked for.
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool> {
+        self.inner.visible(owner, name).await
+    }
+
+    async fn rate_limit(&self) -> Option<RateLimit> {
+        self.inner.rate_limit().await
+    }
+}
@@ -0,0 +1,437 @@
+use super::{
+    page_key, Contributors, Created, Gist, GithubClient, Milestone, NotionClient, RateLimit,
+    ReleaseInfo,
+};
+use crate::{error::NotionError, hash};
+use async_trait::async_trait;
+use notion::chrono::{DateTime, NaiveDate, Utc};
+use octocrab::models::Repository;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// One JSON file per API call, named after the call and its arguments.
+struct Fixtures {
+    dir: PathBuf,
+    queries: Mutex<Queries>,
+}
+
+/// Database queries in the order they were started. A run queries the database more than once
+/// and the rows change in between, so the cursor alone doesn't tell the calls apart.
+#[derive(Default)]
+struct Queries {
+    started: usize,
+    /// the query each cursor handed out so far belongs to
+    cursors: HashMap<String, usize>,
+}
+
+impl Fixtures {
+    fn new(dir: &Path) -> Fixtures {
+        Fixtures {
+            dir: dir.to_path_buf(),
+            queries: Mutex::default(),
+        }
+    }
+
+    /// The query a call with `cursor` is part of, a new one when it starts at the top.
+    fn query(&self, cursor: &Option<String>) -> usize {
+        let mut queries = self.queries.lock().unwrap();
+        match cursor {
+            Some(cursor) => queries.cursors.get(cursor).copied().unwrap_or_default(),
+            None => {
+                queries.started += 1;
+                queries.started
+            }
+        }
+    }
+
+    fn continues(&self, query: usize, next_cursor: &Option<String>) {
+        if let Some(next_cursor) = next_cursor {
+            let mut queries = self.queries.lock().unwrap();
+            queries.cursors.insert(next_cursor.clone(), query);
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn save<T: Serialize>(&self, key: &str, value: &T) {
+        let path = self.path(key);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, serde_json::to_string_pretty(value).unwrap()).unwrap();
+    }
+
+    fn load<T: DeserializeOwned>(&self, key: &str) -> T {
+        let path = self.path(key);
+        let content = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "no fixture at {}, capture one with --record",
+                path.display()
+            )
+        });
+        serde_json::from_str(&content).unwrap()
+    }
+}
+
+fn search_key(query: &str) -> String {
+    format!("github/search-{:016x}", hash::fnv1a(query.as_bytes()))
+}
+
+fn query_key(query: usize, cursor: &Option<String>) -> String {
+    format!(
+        "notion/query-{}-{}",
+        query,
+        cursor.as_deref().unwrap_or("start")
+    )
+}
+
+/// Passes calls through to a real client and saves every read to disk.
+pub struct Recorder<C> {
+    inner: C,
+    fixtures: Fixtures,
+}
+
+impl<C> Recorder<C> {
+    pub fn new(inner: C, dir: &Path) -> Recorder<C> {
+        Recorder {
+            inner,
+            fixtures: Fixtures::new(dir),
+        }
+    }
+}
+
+#[async_trait]
+impl GithubClient for Recorder<Box<dyn GithubClient>> {
+    async fn starred_page(&self, page: u32, per_page: u32) -> Vec<Repository> {
+        let stars = self.inner.starred_page(page, per_page).await;
+        let key = format!("github/starred-{}", page_key(page, per_page));
+        self.fixtures.save(&key, &stars);
+        stars
+    }
+
+    async fn starred_since_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)> {
+        let stars = self.inner.starred_since_page(page, per_page).await;
+        let key = format!("github/starred-since-{}", page_key(page, per_page));
+        self.fixtures.save(&key, &stars);
+        stars
+    }
+
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo> {
+        let release = self.inner.latest_release(owner, name).await;
+        self.fixtures
+            .save(&format!("github/release-{}-{}", owner, name), &release);
+        release
+    }
+
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo> {
+        let releases = self.inner.releases(owner, name).await;
+        self.fixtures
+            .save(&format!("github/releases-{}-{}", owner, name), &releases);
+        releases
+    }
+
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<NaiveDate> {
+        let commit = self.inner.latest_commit(owner, name, branch).await;
+        self.fixtures
+            .save(&format!("github/commit-{}-{}", owner, name), &commit);
+        commit
+    }
+
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<Contributors> {
+        let contributors = self.inner.contributors(owner, name, branch).await;
+        self.fixtures.save(
+            &format!("github/contributors-{}-{}", owner, name),
+            &contributors,
+        );
+        contributors
+    }
+
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64> {
+        let count = self.inner.open_pull_requests(owner, name).await;
+        self.fixtures
+            .save(&format!("github/open-prs-{}-{}", owner, name), &count);
+        count
+    }
+
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate> {
+        let date = self.inner.latest_discussion(owner, name).await;
+        self.fixtures
+            .save(&format!("github/discussion-{}-{}", owner, name), &date);
+        date
+    }
+
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone> {
+        let milestone = self.inner.next_milestone(owner, name).await;
+        self.fixtures
+            .save(&format!("github/milestone-{}-{}", owner, name), &milestone);
+        milestone
+    }
+
+    async fn starred_gists_page(&self, page: u32) -> Result<Vec<Gist>, String> {
+        let gists = self.inner.starred_gists_page(page).await?;
+        self.fixtures
+            .save(&format!("github/gists-{}", page), &gists);
+        Ok(gists)
+    }
+
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String> {
+        let repos = self.inner.search_repositories(query).await?;
+        self.fixtures.save(&search_key(query), &repos);
+        Ok(repos)
+    }
+
+    async fn readme(&self, owner: &str, name: &str) -> Option<String> {
+        let readme = self.inner.readme(owner, name).await;
+        self.fixtures
+            .save(&format!("github/readme-{}-{}", owner, name), &readme);
+        readme
+    }
+
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository> {
+        let repo = self.inner.repository(owner, name).await;
+        self.fixtures
+            .save(&format!("github/repository-{}-{}", owner, name), &repo);
+        repo
+    }
+
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool> {
+        let visible = self.inner.visible(owner, name).await;
+        self.fixtures
+            .save(&format!("github/visible-{}-{}", owner, name), &visible);
+        visible
+    }
+
+    async fn rate_limit(&self) -> Option<RateLimit> {
+        self.inner.rate_limit().await
+    }
+}
+
+#[async_trait]
+impl NotionClient for Recorder<Box<dyn NotionClient>> {
+    async fn query_database(
+        &self,
+        cursor: Option<String>,
+        sorts: Option<Value>,
+    ) -> Result<(Vec<Value>, Option<String>), NotionError> {
+        let query = self.fixtures.query(&cursor);
+        let key = query_key(query, &cursor);
+        let result = self.inner.query_database(cursor, sorts).await?;
+        self.fixtures.continues(query, &result.1);
+        self.fixtures.save(&key, &result);
+        Ok(result)
+    }
+
+    async fn database_properties(&self) -> Result<HashMap<String, Value>, NotionError> {
+        let properties = self.inner.database_properties().await?;
+        self.fixtures.save("notion/database", &properties);
+        Ok(properties)
+    }
+
+    async fn update_database(&self, properties: Value) -> Result<(), NotionError> {
+        self.inner.update_database(properties).await
+    }
+
+    async fn block_children(&self, block_id: &str) -> Result<Vec<Value>, NotionError> {
+        let blocks = self.inner.block_children(block_id).await?;
+        self.fixtures
+            .save(&format!("notion/blocks-{}", block_id), &blocks);
+        Ok(blocks)
+    }
+
+    async fn append_blocks(&self, block_id: &str, children: Vec<Value>) -> Result<(), NotionError> {
+        self.inner.append_blocks(block_id, children).await
+    }
+
+    async fn delete_block(&self, block_id: &str) -> Result<(), NotionError> {
+        self.inner.delete_block(block_id).await
+    }
+
+    async fn create_page(&self, body: Value) -> Result<Created, NotionError> {
+        self.inner.create_page(body).await
+    }
+
+    /// Only asked after a failed create, which a replay never has.
+    async fn find_page(&self, property: &str, url: &str) -> Result<Option<Value>, NotionError> {
+        self.inner.find_page(property, url).await
+    }
+
+    async fn update_page(&self, page_id: &str, body: Value) -> Result<(), NotionError> {
+        self.inner.update_page(page_id, body).await
+    }
+}
+
+/// Answers reads from what a `Recorder` saved; writes succeed without going anywhere.
+pub struct Replayer {
+    fixtures: Fixtures,
+}
+
+impl Replayer {
+    pub fn new(dir: &Path) -> Replayer {
+        Replayer {
+            fixtures: Fixtures::new(dir),
+        }
+    }
+}
+
+#[async_trait]
+impl GithubClient for Replayer {
+    async fn starred_page(&self, page: u32, per_page: u32) -> Vec<Repository> {
+        self.fixtures
+            .load(&format!("github/starred-{}", page_key(page, per_page)))
+    }
+
+    async fn starred_since_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)> {
+        self.fixtures.load(&format!(
+            "github/starred-since-{}",
+            page_key(page, per_page)
+        ))
+    }
+
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo> {
+        self.fixtures
+            .load(&format!("github/release-{}-{}", owner, name))
+    }
+
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo> {
+        self.fixtures
+            .load(&format!("github/releases-{}-{}", owner, name))
+    }
+
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        _branch: Option<&str>,
+    ) -> Option<NaiveDate> {
+        self.fixtures
+            .load(&format!("github/commit-{}-{}", owner, name))
+    }
+
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        _branch: Option<&str>,
+    ) -> Option<Contributors> {
+        self.fixtures
+            .load(&format!("github/contributors-{}-{}", owner, name))
+    }
+
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64> {
+        self.fixtures
+            .load(&format!("github/open-prs-{}-{}", owner, name))
+    }
+
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate> {
+        self.fixtures
+            .load(&format!("github/discussion-{}-{}", owner, name))
+    }
+
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone> {
+        self.fixtures
+            .load(&format!("github/milestone-{}-{}", owner, name))
+    }
+
+    async fn starred_gists_page(&self, page: u32) -> Result<Vec<Gist>, String> {
+        Ok(self.fixtures.load(&format!("github/gists-{}", page)))
+    }
+
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String> {
+        Ok(self.fixtures.load(&search_key(query)))
+    }
+
+    async fn readme(&self, owner: &str, name: &str) -> Option<String> {
+        self.fixtures
+            .load(&format!("github/readme-{}-{}", owner, name))
+    }
+
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository> {
+        self.fixtures
+            .load(&format!("github/repository-{}-{}", owner, name))
+    }
+
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool> {
+        self.fixtures
+            .load(&format!("github/visible-{}-{}", owner, name))
+    }
+
+    /// Nothing was spent.
+    async fn rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+}
+
+#[async_trait]
+impl NotionClient for Replayer {
+    async fn query_database(
+        &self,
+        cursor: Option<String>,
+        _sorts: Option<Value>,
+    ) -> Result<(Vec<Value>, Option<String>), NotionError> {
+        let query = self.fixtures.query(&cursor);
+        let result: (Vec<Value>, Option<String>) = self.fixtures.load(&query_key(query, &cursor));
+        self.fixtures.continues(query, &result.1);
+        Ok(result)
+    }
+
+    async fn database_properties(&self) -> Result<HashMap<String, Value>, NotionError> {
+        Ok(self.fixtures.load("notion/database"))
+    }
+
+    async fn update_database(&self, _properties: Value) -> Result<(), NotionError> {
+        Ok(())
+    }
+
+    async fn block_children(&self, block_id: &str) -> Result<Vec<Value>, NotionError> {
+        Ok(self.fixtures.load(&format!("notion/blocks-{}", block_id)))
+    }
+
+    async fn append_blocks(
+        &self,
+        _block_id: &str,
+        _children: Vec<Value>,
+    ) -> Result<(), NotionError> {
+        Ok(())
+    }
+
+    async fn delete_block(&self, _block_id: &str) -> Result<(), NotionError> {
+        Ok(())
+    }
+
+    async fn create_page(&self, _body: Value) -> Result<Created, NotionError> {
+        // nothing is written, there's no page to point at
+        Ok(Created::New(String::new()))
+    }
+
+    async fn find_page(&self, _property: &str, _url: &str) -> Result<Option<Value>, NotionError> {
+        Ok(None)
+    }
+
+    async fn update_page(&self, _page_id: &str, _body: Value) -> Result<(), NotionError> {
+        Ok(())
+    }
+}
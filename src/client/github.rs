@@ -0,0 +1,476 @@
+use super::{Contributors, Gist, GithubClient, Milestone, RateLimit, ReleaseInfo};
+use async_trait::async_trait;
+use notion::chrono::{DateTime, NaiveDate, Utc};
+use octocrab::{models::Repository, Octocrab};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Pages of 100 contributors to count before giving up, the count is a lower bound past that.
+const CONTRIBUTOR_PAGES: u32 = 5;
+
+#[derive(Deserialize)]
+struct StarredRepo {
+    starred_at: DateTime<Utc>,
+    repo: Repository,
+}
+
+pub struct HttpGithubClient {
+    base_url: String,
+    http: reqwest::Client,
+    octocrab: Octocrab,
+    token: String,
+    /// site to read `releases.atom` feeds from before asking the API
+    feed_url: Option<String>,
+}
+
+impl HttpGithubClient {
+    pub fn new(token: String, base_url: &str, timeout: Duration) -> HttpGithubClient {
+        let base_url = base_url.trim_end_matches('/').to_string();
+        HttpGithubClient {
+            octocrab: Octocrab::builder()
+                .base_uri(base_url.as_str())
+                .unwrap()
+                .personal_token(token.clone())
+                .set_connect_timeout(Some(timeout))
+                .set_read_timeout(Some(timeout))
+                .build()
+                .unwrap(),
+            http: reqwest::Client::builder().timeout(timeout).build().unwrap(),
+            base_url,
+            token,
+            feed_url: None,
+        }
+    }
+
+    /// Reads the latest release from the public `releases.atom` feed on `web_url` (such as
+    /// `https://github.com`), which needs no token and has no rate limit, and only falls back to
+    /// the API when the feed can't be read.
+    pub fn with_release_feed(self, web_url: &str) -> HttpGithubClient {
+        HttpGithubClient {
+            feed_url: Some(web_url.trim_end_matches('/').to_string()),
+            ..self
+        }
+    }
+
+    fn request(&self, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .get(format!("{}/{}", self.base_url, path))
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("User-Agent", "notionstar")
+    }
+}
+
+#[async_trait]
+impl GithubClient for HttpGithubClient {
+    async fn starred_page(&self, page: u32, per_page: u32) -> Vec<Repository> {
+        // asked for directly, octocrab's builder only goes up to page 255
+        self.request(&format!("user/starred?per_page={}&page={}", per_page, page))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    async fn starred_since_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)> {
+        self.request(&format!(
+            "user/starred?sort=created&direction=desc&per_page={}&page={}",
+            per_page, page
+        ))
+        .header("Accept", "application/vnd.github.star+json")
+        .send()
+        .await
+        .unwrap()
+        .json::<Vec<StarredRepo>>()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|star| (star.starred_at, star.repo))
+        .collect()
+    }
+
+    /// `None` when the feed is unavailable (not asked for, private repo, network error, not a
+    /// feed), as opposed to `Some(None)` for a repo without releases.
+    async fn feed_release(&self, owner: &str, name: &str) -> Option<Option<ReleaseInfo>> {
+        let feed_url = self.feed_url.as_ref()?;
+        let resp = self
+            .http
+            .get(format!("{}/{}/{}/releases.atom", feed_url, owner, name))
+            .header("User-Agent", "notionstar")
+            .send()
+            .await
+            .ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        parse_feed(&resp.text().await.ok()?)
+    }
+
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo> {
+        let release = self
+            .octocrab
+            .repos(owner, name)
+            .releases()
+            .get_latest()
+            .await
+            .ok()?;
+        Some(ReleaseInfo {
+            tag_name: release.tag_name,
+            published_at: release.published_at,
+            body: release.body,
+            prerelease: release.prerelease,
+        })
+    }
+
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo> {
+        let releases = match self
+            .octocrab
+            .repos(owner, name)
+            .releases()
+            .list()
+            .per_page(100)
+            .send()
+            .await
+        {
+            Ok(releases) => releases.items,
+            Err(_) => return Vec::new(),
+        };
+        releases
+            .into_iter()
+            .filter(|release| !release.draft)
+            .map(|release| ReleaseInfo {
+                tag_name: release.tag_name,
+                published_at: release.published_at,
+                body: release.body,
+                prerelease: release.prerelease,
+            })
+            .collect()
+    }
+
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<NaiveDate> {
+        let repo = self.octocrab.repos(owner, name);
+        let mut request = repo.list_commits().per_page(1);
+        if let Some(branch) = branch {
+            request = request.sha(branch);
+        }
+        let commits = request.send().await.ok()?;
+        let date = commits.items.first()?.commit.committer.as_ref()?.date?;
+        Some(date.naive_utc().date())
+    }
+
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<Contributors> {
+        let mut count = 0;
+        for page in 1..=CONTRIBUTOR_PAGES {
+            let resp = self
+                .request(&format!(
+                    "repos/{}/{}/contributors?anon=false&per_page=100&page={}",
+                    owner, name, page
+                ))
+                .send()
+                .await
+                .ok()?;
+            // 204 for an empty repo, 403 when the history is too large to list
+            if !resp.status().is_success() {
+                return None;
+            }
+            let contributors = resp.json::<Vec<Value>>().await.unwrap_or_default();
+            count += contributors.len() as u64;
+            if contributors.len() < 100 {
+                break;
+            }
+        }
+        let mut path = format!("repos/{}/{}/commits?per_page=1", owner, name);
+        if let Some(branch) = branch {
+            path += &format!("&sha={}", branch);
+        }
+        let commits = self
+            .request(&path)
+            .send()
+            .await
+            .ok()?
+            .json::<Vec<Value>>()
+            .await
+            .ok()?;
+        let latest = commits
+            .first()
+            .and_then(|commit| commit["author"]["login"].as_str())
+            .map(str::to_string);
+        Some(Contributors { count, latest })
+    }
+
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64> {
+        let resp = self
+            .request(&format!(
+                "search/issues?q=repo:{}/{}+type:pr+state:open&per_page=1",
+                owner, name
+            ))
+            .send()
+            .await
+            .ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        resp.json::<Value>().await.ok()?["total_count"].as_u64()
+    }
+
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate> {
+        let query = r#"query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    discussions(first: 1, orderBy: { field: CREATED_AT, direction: DESC }) {
+      nodes { createdAt }
+    }
+  }
+}"#;
+        // GitHub Enterprise serves REST under `/api/v3` and GraphQL at `/api/graphql`
+        let url = match self.base_url.strip_suffix("/v3") {
+            Some(api) => format!("{}/graphql", api),
+            None => format!("{}/graphql", self.base_url),
+        };
+        let resp = self
+            .http
+            .post(url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("User-Agent", "notionstar")
+            .json(&json!({ "query": query, "variables": { "owner": owner, "name": name } }))
+            .send()
+            .await
+            .ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        let body = resp.json::<Value>().await.ok()?;
+        let created =
+            body["data"]["repository"]["discussions"]["nodes"][0]["createdAt"].as_str()?;
+        let created = created.parse::<DateTime<Utc>>().ok()?;
+        Some(created.date_naive())
+    }
+
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone> {
+        let resp = self
+            .request(&format!(
+                "repos/{}/{}/milestones?state=open&per_page=100",
+                owner, name
+            ))
+            .send()
+            .await
+            .ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        let milestones = resp
+            .json::<Vec<Value>>()
+            .await
+            .ok()?
+            .into_iter()
+            .map(|milestone| Milestone {
+                title: milestone["title"].as_str().unwrap_or_default().to_string(),
+                due_on: milestone["due_on"]
+                    .as_str()
+                    .and_then(|due| due.parse::<DateTime<Utc>>().ok())
+                    .map(|due| due.date_naive()),
+            })
+            .collect::<Vec<Milestone>>();
+        milestones
+            .iter()
+            .filter(|milestone| milestone.due_on.is_some())
+            .min_by_key(|milestone| milestone.due_on)
+            .or(milestones.first())
+            .cloned()
+    }
+
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String> {
+        let resp = self
+            .http
+            .get(format!("{}/search/repositories", self.base_url))
+            .query(&[("q", query), ("per_page", "100")])
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("User-Agent", "notionstar")
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("GitHub returned {}", resp.status()));
+        }
+        let mut results = resp.json::<Value>().await.map_err(|err| err.to_string())?;
+        serde_json::from_value(results["items"].take()).map_err(|err| err.to_string())
+    }
+
+    async fn readme(&self, owner: &str, name: &str) -> Option<String> {
+        let resp = self
+            .request(&format!("repos/{}/{}/readme", owner, name))
+            .header("Accept", "application/vnd.github.raw")
+            .send()
+            .await
+            .ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        resp.text().await.ok()
+    }
+
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository> {
+        // the 301 GitHub answers for a moved repo is followed to where it lives now
+        let resp = self
+            .request(&format!("repos/{}/{}", owner, name))
+            .send()
+            .await
+            .ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        resp.json().await.ok()
+    }
+
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool> {
+        let resp = self
+            .request(&format!("repos/{}/{}", owner, name))
+            .send()
+            .await
+            .ok()?;
+        // a 403 with the budget spent is the rate limit, not the repo
+        let limited = resp
+            .headers()
+            .get("x-ratelimit-remaining")
+            .is_some_and(|remaining| remaining == "0");
+        match resp.status().as_u16() {
+            200..=299 => Some(true),
+            404 | 451 => Some(false),
+            403 if !limited => Some(false),
+            _ => None,
+        }
+    }
+
+    async fn rate_limit(&self) -> Option<RateLimit> {
+        let resp = self.request("rate_limit").send().await.ok()?;
+        if !resp.status().is_success() {
+            return None;
+        }
+        let core = &resp.json::<Value>().await.ok()?["resources"]["core"];
+        Some(RateLimit {
+            remaining: core["remaining"].as_u64()?,
+            limit: core["limit"].as_u64()?,
+            reset: DateTime::from_timestamp(core["reset"].as_i64()?, 0)?,
+        })
+    }
+
+    async fn starred_gists_page(&self, page: u32) -> Result<Vec<Gist>, String> {
+        let resp = self
+            .request(&format!("gists/starred?per_page=100&page={}", page))
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("GitHub returned {}", resp.status()));
+        }
+        let gists = resp
+            .json::<Vec<Value>>()
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(gists
+            .into_iter()
+            .map(|gist| {
+                // files are keyed by name, the first one by name stands for the gist
+                let file = gist["files"]
+                    .as_object()
+                    .and_then(|files| files.values().next().cloned())
+                    .unwrap_or_default();
+                Gist {
+                    html_url: gist["html_url"].as_str().unwrap_or_default().to_string(),
+                    description: gist["description"]
+                        .as_str()
+                        .filter(|description| !description.is_empty())
+                        .map(str::to_string),
+                    filename: file["filename"].as_str().unwrap_or_default().to_string(),
+                    language: file["language"].as_str().map(str::to_string),
+                    owner: gist["owner"]["login"].as_str().map(str::to_string),
+                    updated_at: gist["updated_at"]
+                        .as_str()
+                        .and_then(|date| date.parse().ok())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect())
+    }
+}
+
+/// The newest entry of a releases feed that isn't tagged like a prerelease, the feed doesn't say.
+/// Entry ids look like `tag:github.com,2008:Repository/<id>/<tag>`. There's no publish date,
+/// `updated` is when the release was last edited. `None` when it isn't a feed, or when every
+/// entry in it is a prerelease and the latest release is further back.
+pub fn parse_feed(xml: &str) -> Option<Option<ReleaseInfo>> {
+    if !xml.contains("<feed") {
+        return None;
+    }
+    let entries = Regex::new(r"(?s)<entry>(.*?)</entry>").unwrap();
+    let prerelease =
+        Regex::new(r"(?i)\d[-.]?(alpha|beta|rc|pre|preview|dev|snapshot|nightly)").unwrap();
+    let mut any = false;
+    for entry in entries.captures_iter(xml) {
+        any = true;
+        let entry = &entry[1];
+        let field = |name: &str| {
+            Regex::new(&format!(r#"(?s)<{0}(?:\s[^>]*)?>(.*?)</{0}>"#, name))
+                .unwrap()
+                .captures(entry)
+                .map(|field| field[1].trim().to_string())
+        };
+        let id = field("id")?;
+        let tag_name = id
+            .split_once("Repository/")?
+            .1
+            .split_once('/')?
+            .1
+            .to_string();
+        if prerelease.is_match(&tag_name) {
+            continue;
+        }
+        return Some(Some(ReleaseInfo {
+            tag_name,
+            published_at: None,
+            body: field("content").map(|content| feed_text(&content)),
+            prerelease: false,
+        }));
+    }
+    match any {
+        true => None,
+        false => Some(None),
+    }
+}
+
+/// The release notes in an entry's `content`, escaped HTML, as plain text a line per block.
+fn feed_text(content: &str) -> String {
+    let unescape = |text: &str| {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&")
+    };
+    let html = unescape(content);
+    let blocks = Regex::new(r"(?i)<br\s*/?>|</(p|li|h[1-6]|pre|div)>").unwrap();
+    let html = blocks.replace_all(&html, "\n");
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    let text = unescape(&tags.replace_all(&html, ""));
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
@@ -0,0 +1,193 @@
+use super::{
+    Contributors, Created, Gist, GithubClient, Milestone, NotionClient, RateLimit, ReleaseInfo,
+};
+use crate::error::NotionError;
+use async_trait::async_trait;
+use notion::chrono::{DateTime, NaiveDate, Utc};
+use octocrab::models::Repository;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+/// Requests made so far, by endpoint category such as `github releases` or `notion page patch`.
+#[derive(Default)]
+pub struct ApiCalls(Mutex<BTreeMap<&'static str, u64>>);
+
+impl ApiCalls {
+    fn count(&self, category: &'static str) {
+        *self.0.lock().unwrap().entry(category).or_default() += 1;
+    }
+
+    /// The counts so far, starting over from zero, so a daemon reports each run's own calls.
+    pub fn take(&self) -> BTreeMap<String, u64> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+            .into_iter()
+            .map(|(category, count)| (category.to_string(), count))
+            .collect()
+    }
+}
+
+/// Passes calls through to another client and counts them in `ApiCalls`. It goes right around
+/// the client doing the requests, so calls a cache answers aren't counted.
+pub struct Metered<C> {
+    inner: C,
+    calls: Arc<ApiCalls>,
+}
+
+impl<C> Metered<C> {
+    pub fn new(inner: C, calls: Arc<ApiCalls>) -> Metered<C> {
+        Metered { inner, calls }
+    }
+}
+
+#[async_trait]
+impl GithubClient for Metered<Box<dyn GithubClient>> {
+    async fn starred_page(&self, page: u32, per_page: u32) -> Vec<Repository> {
+        self.calls.count("github stars");
+        self.inner.starred_page(page, per_page).await
+    }
+
+    async fn starred_since_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)> {
+        self.calls.count("github stars");
+        self.inner.starred_since_page(page, per_page).await
+    }
+
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo> {
+        self.calls.count("github releases");
+        self.inner.latest_release(owner, name).await
+    }
+
+    async fn feed_release(&self, owner: &str, name: &str) -> Option<Option<ReleaseInfo>> {
+        // the public releases.atom, which isn't an API call and has no rate limit
+        self.calls.count("github feed");
+        self.inner.feed_release(owner, name).await
+    }
+
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo> {
+        self.calls.count("github releases");
+        self.inner.releases(owner, name).await
+    }
+
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<NaiveDate> {
+        self.calls.count("github commits");
+        self.inner.latest_commit(owner, name, branch).await
+    }
+
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<Contributors> {
+        self.calls.count("github contributors");
+        self.inner.contributors(owner, name, branch).await
+    }
+
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64> {
+        self.calls.count("github search");
+        self.inner.open_pull_requests(owner, name).await
+    }
+
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate> {
+        self.calls.count("github graphql");
+        self.inner.latest_discussion(owner, name).await
+    }
+
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone> {
+        self.calls.count("github milestones");
+        self.inner.next_milestone(owner, name).await
+    }
+
+    async fn starred_gists_page(&self, page: u32) -> Result<Vec<Gist>, String> {
+        self.calls.count("github gists");
+        self.inner.starred_gists_page(page).await
+    }
+
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String> {
+        self.calls.count("github search");
+        self.inner.search_repositories(query).await
+    }
+
+    async fn readme(&self, owner: &str, name: &str) -> Option<String> {
+        self.calls.count("github readme");
+        self.inner.readme(owner, name).await
+    }
+
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository> {
+        self.calls.count("github repos");
+        self.inner.repository(owner, name).await
+    }
+
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool> {
+        self.calls.count("github repos");
+        self.inner.visible(owner, name).await
+    }
+
+    /// Free, so not counted.
+    async fn rate_limit(&self) -> Option<RateLimit> {
+        self.inner.rate_limit().await
+    }
+}
+
+#[async_trait]
+impl NotionClient for Metered<Box<dyn NotionClient>> {
+    async fn query_database(
+        &self,
+        cursor: Option<String>,
+        sorts: Option<Value>,
+    ) -> Result<(Vec<Value>, Option<String>), NotionError> {
+        self.calls.count("notion query");
+        self.inner.query_database(cursor, sorts).await
+    }
+
+    async fn database_properties(&self) -> Result<HashMap<String, Value>, NotionError> {
+        self.calls.count("notion database");
+        self.inner.database_properties().await
+    }
+
+    async fn update_database(&self, properties: Value) -> Result<(), NotionError> {
+        self.calls.count("notion database");
+        self.inner.update_database(properties).await
+    }
+
+    async fn block_children(&self, block_id: &str) -> Result<Vec<Value>, NotionError> {
+        self.calls.count("notion blocks");
+        self.inner.block_children(block_id).await
+    }
+
+    async fn append_blocks(&self, block_id: &str, children: Vec<Value>) -> Result<(), NotionError> {
+        self.calls.count("notion blocks");
+        self.inner.append_blocks(block_id, children).await
+    }
+
+    async fn delete_block(&self, block_id: &str) -> Result<(), NotionError> {
+        self.calls.count("notion blocks");
+        self.inner.delete_block(block_id).await
+    }
+
+    async fn create_page(&self, body: Value) -> Result<Created, NotionError> {
+        self.calls.count("notion page create");
+        self.inner.create_page(body).await
+    }
+
+    async fn find_page(&self, property: &str, url: &str) -> Result<Option<Value>, NotionError> {
+        self.calls.count("notion query");
+        self.inner.find_page(property, url).await
+    }
+
+    async fn update_page(&self, page_id: &str, body: Value) -> Result<(), NotionError> {
+        self.calls.count("notion page patch");
+        self.inner.update_page(page_id, body).await
+    }
+}
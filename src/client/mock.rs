@@ -0,0 +1,487 @@
+use super::{
+    Contributors, Created, Gist, GithubClient, Milestone, NotionClient, RateLimit, ReleaseInfo,
+};
+use crate::error::NotionError;
+use async_trait::async_trait;
+use notion::{
+    chrono::{DateTime, NaiveDate, Utc},
+    models::Page,
+};
+use octocrab::models::Repository;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Ids of appended blocks, unique across mocks.
+static NEXT_BLOCK: AtomicUsize = AtomicUsize::new(1);
+
+/// In-memory GitHub, filled in up front. Activity is keyed by `owner/name`.
+#[derive(Default)]
+pub struct MockGithubClient {
+    pub stars: Vec<(DateTime<Utc>, Repository)>,
+    pub releases: HashMap<String, ReleaseInfo>,
+    /// what the releases feed shows, for repos that have one
+    pub feed: HashMap<String, Option<ReleaseInfo>>,
+    /// every release, newest first; falls back to the one in `releases`
+    pub release_history: HashMap<String, Vec<ReleaseInfo>>,
+    pub commits: HashMap<String, NaiveDate>,
+    pub contributors: HashMap<String, Contributors>,
+    pub open_pull_requests: HashMap<String, u64>,
+    pub discussions: HashMap<String, NaiveDate>,
+    pub milestones: HashMap<String, Milestone>,
+    pub gists: Vec<Gist>,
+    /// results by query
+    pub searches: HashMap<String, Vec<Repository>>,
+    pub readmes: HashMap<String, String>,
+    /// where repos that moved live now, by their old `owner/name`
+    pub moved: HashMap<String, Repository>,
+    /// `owner/name` of repos that went private or were deleted
+    pub gone: HashSet<String>,
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl MockGithubClient {
+    fn page_of<T: Clone>(items: &[T], page: u32, per_page: u32) -> Vec<T> {
+        items
+            .chunks(per_page as usize)
+            .nth(page as usize - 1)
+            .map(<[T]>::to_vec)
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl GithubClient for MockGithubClient {
+    async fn starred_page(&self, page: u32, per_page: u32) -> Vec<Repository> {
+        let repos = self
+            .stars
+            .iter()
+            .map(|(_, repo)| repo.clone())
+            .collect::<Vec<Repository>>();
+        Self::page_of(&repos, page, per_page)
+    }
+
+    async fn starred_since_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)> {
+        let mut stars = self.stars.clone();
+        stars.sort_by_key(|(starred_at, _)| std::cmp::Reverse(*starred_at));
+        Self::page_of(&stars, page, per_page)
+    }
+
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo> {
+        self.releases.get(&format!("{}/{}", owner, name)).cloned()
+    }
+
+    async fn feed_release(&self, owner: &str, name: &str) -> Option<Option<ReleaseInfo>> {
+        self.feed.get(&format!("{}/{}", owner, name)).cloned()
+    }
+
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo> {
+        let repo = format!("{}/{}", owner, name);
+        match self.release_history.get(&repo) {
+            Some(releases) => releases.clone(),
+            None => self.releases.get(&repo).cloned().into_iter().collect(),
+        }
+    }
+
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        _branch: Option<&str>,
+    ) -> Option<NaiveDate> {
+        self.commits.get(&format!("{}/{}", owner, name)).copied()
+    }
+
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        _branch: Option<&str>,
+    ) -> Option<Contributors> {
+        self.contributors
+            .get(&format!("{}/{}", owner, name))
+            .cloned()
+    }
+
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64> {
+        self.open_pull_requests
+            .get(&format!("{}/{}", owner, name))
+            .copied()
+    }
+
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate> {
+        self.discussions
+            .get(&format!("{}/{}", owner, name))
+            .copied()
+    }
+
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone> {
+        self.milestones.get(&format!("{}/{}", owner, name)).cloned()
+    }
+
+    async fn starred_gists_page(&self, page: u32) -> Result<Vec<Gist>, String> {
+        Ok(Self::page_of(&self.gists, page, 100))
+    }
+
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String> {
+        Ok(self.searches.get(query).cloned().unwrap_or_default())
+    }
+
+    async fn readme(&self, owner: &str, name: &str) -> Option<String> {
+        self.readmes.get(&format!("{}/{}", owner, name)).cloned()
+    }
+
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository> {
+        let full_name = format!("{}/{}", owner, name);
+        self.moved.get(&full_name).cloned().or_else(|| {
+            self.stars
+                .iter()
+                .map(|(_, repo)| repo)
+                .find(|repo| repo.full_name.as_deref() == Some(&full_name))
+                .cloned()
+        })
+    }
+
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool> {
+        Some(!self.gone.contains(&format!("{}/{}", owner, name)))
+    }
+
+    async fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.clone()
+    }
+}
+
+/// In-memory Notion database. Clones share the same pages, so a test can keep one to inspect.
+#[derive(Clone, Default)]
+pub struct MockNotionClient {
+    pages: Arc<Mutex<Vec<Value>>>,
+    schema: Arc<Mutex<HashMap<String, Value>>>,
+    blocks: Arc<Mutex<HashMap<String, Vec<Value>>>>,
+    /// titles whose create is saved but answered with a gateway timeout
+    lost_responses: Arc<Mutex<HashSet<String>>>,
+    /// writes to columns the schema doesn't have fail, as they do with the API
+    strict: bool,
+}
+
+/// Orders by a date property or a timestamp like the API, empty values last either way.
+fn sort_order(a: &Value, b: &Value, sort: &Value) -> std::cmp::Ordering {
+    let value = |page: &Value| match sort["property"].as_str() {
+        Some(property) => page["properties"][property]["date"]["start"]
+            .as_str()
+            .map(str::to_string),
+        None => page[sort["timestamp"].as_str().unwrap()]
+            .as_str()
+            .map(str::to_string),
+    };
+    match (value(a), value(b)) {
+        (Some(a), Some(b)) if sort["direction"] == "descending" => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+/// The page's title as plain text.
+fn title(page: &Value) -> String {
+    page["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(_, value)| value.get("title")?.as_array())
+        .flatten()
+        .filter_map(|text| {
+            text["plain_text"]
+                .as_str()
+                .or(text["text"]["content"].as_str())
+        })
+        .collect()
+}
+
+impl MockNotionClient {
+    /// Declares a column, only needed for types that matter to the sync such as read-only ones.
+    pub fn with_property(self, name: &str, kind: &str) -> MockNotionClient {
+        self.schema
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), json!({ "name": name, "type": kind }));
+        self
+    }
+
+    /// Gives a page (say a template living outside the database) some content.
+    pub fn with_blocks(self, page_id: &str, blocks: Vec<Value>) -> MockNotionClient {
+        self.blocks
+            .lock()
+            .unwrap()
+            .insert(page_id.to_string(), blocks);
+        self
+    }
+
+    /// Creates the page titled `title` like any other but fails the call, the way Notion sometimes
+    /// times out on a create that went through.
+    pub fn losing_response_to(self, title: &str) -> MockNotionClient {
+        self.lost_responses
+            .lock()
+            .unwrap()
+            .insert(title.to_string());
+        self
+    }
+
+    /// Fails writes to columns that weren't declared, the way Notion answers them.
+    pub fn strict(mut self) -> MockNotionClient {
+        self.strict = true;
+        self
+    }
+
+    /// The validation error Notion gives a write naming a column it doesn't know, when `strict`.
+    fn check_columns(&self, body: &Value) -> Result<(), NotionError> {
+        if !self.strict {
+            return Ok(());
+        }
+        let schema = self.schema.lock().unwrap();
+        let unknown = body["properties"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, _)| name)
+            .find(|name| !schema.contains_key(*name));
+        match unknown {
+            Some(name) => Err(NotionError {
+                status: 400,
+                code: Some("validation_error".to_string()),
+                message: format!("{} is not a property that exists.", name),
+                request_id: None,
+                retry_after: None,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// A column as it stands after any `update_database` calls.
+    pub fn property(&self, name: &str) -> Option<Value> {
+        self.schema.lock().unwrap().get(name).cloned()
+    }
+
+    /// The raw JSON of every page, for fields the notion crate doesn't model such as `icon`.
+    pub fn raw_pages(&self) -> Vec<Value> {
+        self.pages.lock().unwrap().clone()
+    }
+
+    /// Every page ever created, archived ones included.
+    pub fn pages(&self) -> Vec<Page> {
+        self.pages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|page| serde_json::from_value(page.clone()).unwrap())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl NotionClient for MockNotionClient {
+    async fn query_database(
+        &self,
+        cursor: Option<String>,
+        sorts: Option<Value>,
+    ) -> Result<(Vec<Value>, Option<String>), NotionError> {
+        let mut pages = self
+            .raw_pages()
+            .into_iter()
+            .filter(|page| page["archived"] == false)
+            .collect::<Vec<Value>>();
+        for sort in sorts
+            .iter()
+            .flat_map(|sorts| sorts.as_array().unwrap())
+            .rev()
+        {
+            pages.sort_by(|a, b| sort_order(a, b, sort));
+        }
+        // pages of 100 like the API, the cursor is where the next one starts in the order as it is
+        // then, so rows that move meanwhile shift others past it the way they can with Notion
+        let start = cursor.map_or(0, |cursor| cursor.parse().unwrap());
+        let end = (start + 100).min(pages.len());
+        let next = (end < pages.len()).then(|| end.to_string());
+        Ok((pages[start..end].to_vec(), next))
+    }
+
+    async fn database_properties(&self) -> Result<HashMap<String, Value>, NotionError> {
+        Ok(self.schema.lock().unwrap().clone())
+    }
+
+    async fn update_database(&self, properties: Value) -> Result<(), NotionError> {
+        let mut schema = self.schema.lock().unwrap();
+        for (name, update) in properties.as_object().unwrap() {
+            let property = schema
+                .entry(name.clone())
+                .or_insert_with(|| json!({ "name": name }));
+            for (key, value) in update.as_object().unwrap() {
+                property[key] = value.clone();
+                if key != "name" {
+                    property["type"] = json!(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn block_children(&self, block_id: &str) -> Result<Vec<Value>, NotionError> {
+        if let Some(blocks) = self.blocks.lock().unwrap().get(block_id) {
+            return Ok(blocks.clone());
+        }
+        let pages = self.pages.lock().unwrap();
+        match pages.iter().find(|page| page["id"] == block_id) {
+            Some(page) => Ok(page["children"].as_array().cloned().unwrap_or_default()),
+            None => Err(NotionError {
+                status: 404,
+                code: Some("object_not_found".to_string()),
+                message: format!("Could not find block with ID: {}", block_id),
+                request_id: None,
+                retry_after: None,
+            }),
+        }
+    }
+
+    async fn append_blocks(&self, block_id: &str, children: Vec<Value>) -> Result<(), NotionError> {
+        let children = children.into_iter().map(|mut block| {
+            let id = NEXT_BLOCK.fetch_add(1, Ordering::Relaxed);
+            block["id"] = json!(format!("block-{}", id));
+            block
+        });
+        self.blocks
+            .lock()
+            .unwrap()
+            .entry(block_id.to_string())
+            .or_default()
+            .extend(children);
+        Ok(())
+    }
+
+    async fn delete_block(&self, block_id: &str) -> Result<(), NotionError> {
+        for children in self.blocks.lock().unwrap().values_mut() {
+            children.retain(|block| block["id"] != block_id);
+        }
+        Ok(())
+    }
+
+    async fn create_page(&self, body: Value) -> Result<Created, NotionError> {
+        self.check_columns(&body)?;
+        let mut pages = self.pages.lock().unwrap();
+        let id = format!("00000000-0000-0000-0000-{:012}", pages.len() + 1);
+        let now = Utc::now().to_rfc3339();
+        pages.push(json!({
+            "object": "page",
+            "id": id.clone(),
+            "created_time": now,
+            "last_edited_time": now,
+            "parent": { "type": "database_id", "database_id": "mock" },
+            "archived": false,
+            "icon": body["icon"],
+            "children": body["children"],
+            "properties": normalize(&body["properties"]),
+            "url": format!("https://www.notion.so/{}", id.replace('-', "")),
+        }));
+        if self.lost_responses.lock().unwrap().contains(&title(&body)) {
+            return Err(NotionError {
+                status: 504,
+                code: None,
+                message: "Gateway Timeout".to_string(),
+                request_id: None,
+                retry_after: None,
+            });
+        }
+        Ok(Created::New(id))
+    }
+
+    async fn find_page(&self, property: &str, url: &str) -> Result<Option<Value>, NotionError> {
+        Ok(self
+            .raw_pages()
+            .into_iter()
+            .find(|page| page["archived"] == false && page["properties"][property]["url"] == url))
+    }
+
+    async fn update_page(&self, page_id: &str, body: Value) -> Result<(), NotionError> {
+        self.check_columns(&body)?;
+        let mut pages = self.pages.lock().unwrap();
+        let page = match pages.iter_mut().find(|page| page["id"] == page_id) {
+            Some(page) => page,
+            None => {
+                return Err(NotionError {
+                    status: 404,
+                    code: Some("object_not_found".to_string()),
+                    message: format!("Could not find page with ID: {}", page_id),
+                    request_id: None,
+                    retry_after: None,
+                })
+            }
+        };
+        if let Some(archived) = body.get("archived") {
+            page["archived"] = archived.clone();
+        }
+        if let Value::Object(properties) = normalize(&body["properties"]) {
+            for (name, value) in properties {
+                page["properties"][name] = value;
+            }
+        }
+        page["last_edited_time"] = json!(Utc::now().to_rfc3339());
+        Ok(())
+    }
+}
+
+/// Turns request-style property values into what the API hands back: `type` and `id` filled in,
+/// rich text carrying its `plain_text` and options their `color`.
+fn normalize(properties: &Value) -> Value {
+    let properties = match properties.as_object() {
+        Some(properties) => properties,
+        None => return Value::Null,
+    };
+    properties
+        .iter()
+        .map(|(name, value)| {
+            let mut value = value.clone();
+            let kind = match value["type"].as_str() {
+                Some(kind) => kind.to_string(),
+                None => value
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .find(|key| *key != "id")
+                    .unwrap()
+                    .clone(),
+            };
+            value["type"] = json!(kind);
+            if value.get("id").is_none() {
+                value["id"] = json!(name);
+            }
+            if kind == "select" || kind == "status" {
+                if let Some(option) = value[&kind].as_object_mut() {
+                    option.entry("color").or_insert(json!("default"));
+                }
+            }
+            if kind == "multi_select" {
+                for option in value[&kind].as_array_mut().into_iter().flatten() {
+                    option["color"] = option.get("color").cloned().unwrap_or(json!("default"));
+                }
+            }
+            if let Some(Value::Array(texts)) = value.get_mut(&kind) {
+                for text in texts {
+                    if text.get("plain_text").is_none() {
+                        text["plain_text"] = text["text"]["content"].clone();
+                    }
+                    if text.get("type").is_none() {
+                        text["type"] = json!("text");
+                    }
+                }
+            }
+            (name.clone(), value)
+        })
+        .collect::<Map<String, Value>>()
+        .into()
+}
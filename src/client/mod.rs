@@ -0,0 +1,187 @@
+mod cache;
+mod fixture;
+mod github;
+mod metered;
+mod mock;
+mod notion_api;
+mod traced;
+
+pub use cache::Cached;
+pub use fixture::{Recorder, Replayer};
+pub use github::{parse_feed, HttpGithubClient};
+pub use metered::{ApiCalls, Metered};
+pub use mock::{MockGithubClient, MockNotionClient};
+pub use notion_api::{
+    database_id, parse_page, HttpNotionClient, DEFAULT_VERSION, SUPPORTED_VERSIONS,
+};
+pub use traced::Traced;
+
+use crate::error::NotionError;
+use async_trait::async_trait;
+use notion::chrono::{DateTime, NaiveDate, Utc};
+use octocrab::models::Repository;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The parts of a release the sync looks at.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+    pub tag_name: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub body: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Contributors {
+    /// capped at `CONTRIBUTOR_PAGES` pages of 100 for very large projects
+    pub count: u64,
+    /// author of the latest commit on the branch
+    pub latest: Option<String>,
+}
+
+/// The parts of a gist the `gists` command mirrors.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Gist {
+    pub html_url: String,
+    pub description: Option<String>,
+    /// of the first file
+    pub filename: String,
+    pub language: Option<String>,
+    pub owner: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What is left of the REST API's hourly budget.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub remaining: u64,
+    pub limit: u64,
+    pub reset: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Milestone {
+    pub title: String,
+    pub due_on: Option<NaiveDate>,
+}
+
+/// How the star listing goes through its pages, from the config file's `[stars]`.
+#[derive(Clone, Copy)]
+pub struct StarPaging {
+    pub per_page: u32,
+    pub delay: std::time::Duration,
+    pub max_pages: Option<u32>,
+}
+
+/// What a page of a listing is saved under: its number, and the page size unless it's 100.
+fn page_key(page: u32, per_page: u32) -> String {
+    match per_page {
+        100 => page.to_string(),
+        _ => format!("{}-of-{}", page, per_page),
+    }
+}
+
+#[async_trait]
+pub trait GithubClient: Send + Sync {
+    /// One page (1-based, `per_page` up to 100) of the authenticated user's stars.
+    async fn starred_page(&self, page: u32, per_page: u32) -> Vec<Repository>;
+    /// Like `starred_page` but newest first, with the time each repo was starred.
+    async fn starred_since_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)>;
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo>;
+    /// The latest release as the public releases feed has it, which costs no rate limit but has
+    /// no publish date. `None` when there's no feed to read, `Some(None)` for a repo without
+    /// releases.
+    async fn feed_release(&self, _owner: &str, _name: &str) -> Option<Option<ReleaseInfo>> {
+        None
+    }
+    /// The most recent published releases, newest first, prereleases included.
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo>;
+    /// Date of the latest commit on `branch`, or on the default branch when it is `None`.
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<NaiveDate>;
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<Contributors>;
+    /// Open pull requests, from the search API (which has its own, lower rate limit).
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64>;
+    /// Date the newest discussion was posted, from the GraphQL API. `None` when the repo has
+    /// discussions turned off or none yet.
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate>;
+    /// The open milestone due soonest, or one without a due date when none has one.
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone>;
+    /// One page (1-based, 100 per page) of the authenticated user's starred gists, or why GitHub
+    /// couldn't list it.
+    async fn starred_gists_page(&self, page: u32) -> Result<Vec<Gist>, String>;
+    /// The best 100 matches of a repository search such as `topic:egui language:rust`, or why
+    /// GitHub couldn't say, e.g. for a query it doesn't take.
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String>;
+    /// The README's raw text, `None` for repos without one.
+    async fn readme(&self, owner: &str, name: &str) -> Option<String>;
+    /// The repo at `owner/name`, under its new name when it was transferred or renamed since.
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository>;
+    /// Whether the repo can still be seen: `Some(false)` for the 404 of a deleted repo and the
+    /// 403/451 of one gone private or blocked, `None` when GitHub couldn't be asked.
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool>;
+    /// The core rate limit as it stands, asking for it doesn't count against it.
+    async fn rate_limit(&self) -> Option<RateLimit>;
+}
+
+/// How a create went through, with the id of the page.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Created {
+    New(String),
+    /// a failed attempt had created the page after all, it was found instead of made again
+    Recovered(String),
+}
+
+impl Created {
+    pub fn id(&self) -> &str {
+        match self {
+            Created::New(id) | Created::Recovered(id) => id,
+        }
+    }
+}
+
+#[async_trait]
+pub trait NotionClient: Send + Sync {
+    /// One page of the synced database, as raw objects for `parse_page`, and the cursor for the
+    /// next one. Parsing is left to the caller so it can overlap with fetching the next page.
+    /// `sorts` is the query's, `None` for the database's own order.
+    async fn query_database(
+        &self,
+        cursor: Option<String>,
+        sorts: Option<Value>,
+    ) -> Result<(Vec<Value>, Option<String>), NotionError>;
+    /// The synced database's columns by name, as the API's property objects.
+    async fn database_properties(&self) -> Result<HashMap<String, Value>, NotionError>;
+    /// PATCHes the database's `properties`, e.g. to add select options.
+    async fn update_database(&self, properties: Value) -> Result<(), NotionError>;
+    /// Every child block of a page or block, as raw API objects.
+    async fn block_children(&self, block_id: &str) -> Result<Vec<Value>, NotionError>;
+    /// Adds blocks, at most 100, after the existing children of a page or block.
+    async fn append_blocks(&self, block_id: &str, children: Vec<Value>) -> Result<(), NotionError>;
+    /// Moves a block to the trash.
+    async fn delete_block(&self, block_id: &str) -> Result<(), NotionError>;
+    /// Creates a row in the synced database from a raw body, e.g. `{"properties": ..., "icon": ...}`;
+    /// the parent is filled in.
+    async fn create_page(&self, body: Value) -> Result<Created, NotionError>;
+    /// The live row whose URL column `property` reads exactly `url`, if there is one. Titles
+    /// aren't enough, repos of different owners can share a name.
+    async fn find_page(&self, property: &str, url: &str) -> Result<Option<Value>, NotionError>;
+    /// PATCHes a page with a raw body, e.g. `{"properties": ...}` or `{"archived": true}`.
+    async fn update_page(&self, page_id: &str, body: Value) -> Result<(), NotionError>;
+}
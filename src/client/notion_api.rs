@@ -0,0 +1,365 @@
+use super::{Created, NotionClient};
+use crate::error::NotionError;
+use async_trait::async_trait;
+use notion::models::Page;
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::HashMap, time::Duration};
+
+pub const DEFAULT_VERSION: &str = "2022-06-28";
+/// `Notion-Version`s whose page shapes we can read (with `shim` papering over newer property types).
+pub const SUPPORTED_VERSIONS: [&str; 2] = ["2022-02-22", DEFAULT_VERSION];
+
+/// Property types the notion crate can deserialize.
+const KNOWN_TYPES: [&str; 20] = [
+    "title",
+    "rich_text",
+    "number",
+    "select",
+    "status",
+    "multi_select",
+    "date",
+    "formula",
+    "relation",
+    "rollup",
+    "people",
+    "files",
+    "checkbox",
+    "url",
+    "email",
+    "phone_number",
+    "created_time",
+    "created_by",
+    "last_edited_time",
+    "last_edited_by",
+];
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    results: Vec<Value>,
+    next_cursor: Option<String>,
+}
+
+/// Rewrites property types added after the notion crate was written so the page still parses:
+/// `unique_id` becomes the `PREFIX-123` text it displays as, anything else unknown is dropped.
+fn shim(mut page: Value) -> Value {
+    if let Some(properties) = page["properties"].as_object_mut() {
+        properties.retain(|_, value| {
+            let kind = value["type"].as_str().unwrap_or_default();
+            if kind == "unique_id" {
+                let id = &value["unique_id"];
+                let display = match id["prefix"].as_str() {
+                    Some(prefix) => format!("{}-{}", prefix, id["number"]),
+                    None => id["number"].to_string(),
+                };
+                *value = json!({
+                    "id": value["id"],
+                    "type": "rich_text",
+                    "rich_text": [{
+                        "type": "text",
+                        "text": { "content": display, "link": null },
+                        "plain_text": display,
+                        "href": null,
+                    }],
+                });
+                return true;
+            }
+            KNOWN_TYPES.contains(&kind)
+        });
+    }
+    page
+}
+
+/// The id in a database id, with or without dashes, or in a link to the database such as
+/// `https://www.notion.so/workspace/Stars-0123…cdef?v=…`.
+pub fn database_id(input: &str) -> Option<String> {
+    let input = input.trim();
+    let end = input.find(['?', '#']).unwrap_or(input.len());
+    let segment = input[..end].trim_end_matches('/').rsplit('/').next()?;
+    let compact = segment.replace('-', "");
+    let id = compact.get(compact.len().checked_sub(32)?..)?;
+    id.chars()
+        .all(|c| c.is_ascii_hexdigit())
+        .then(|| id.to_lowercase())
+}
+
+pub fn parse_page(page: Value) -> Page {
+    serde_json::from_value(shim(page)).unwrap()
+}
+
+/// Raw REST client; the notion crate can't be pointed at another host and lacks half the endpoints.
+pub struct HttpNotionClient {
+    base_url: String,
+    database_id: String,
+    http: reqwest::Client,
+    token: String,
+    version: String,
+    /// how many times a failed call is tried again
+    retries: u32,
+}
+
+/// Wait before retry `attempt` (from 0) when Notion didn't say how long.
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_millis(500 << attempt.min(6))
+}
+
+impl HttpNotionClient {
+    pub fn new(
+        token: String,
+        database_id: String,
+        base_url: &str,
+        version: String,
+        timeout: Duration,
+    ) -> HttpNotionClient {
+        HttpNotionClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            database_id,
+            http: reqwest::Client::builder().timeout(timeout).build().unwrap(),
+            token,
+            version,
+            retries: 0,
+        }
+    }
+
+    /// Retries throttled and failed calls up to `retries` times. Reads and updates are simply
+    /// sent again; a create is only repeated once the page it would make turns out not to exist.
+    pub fn with_retries(self, retries: u32) -> HttpNotionClient {
+        HttpNotionClient { retries, ..self }
+    }
+
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, format!("{}/{}", self.base_url, path))
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Notion-Version", &self.version)
+    }
+
+    async fn send_once(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, NotionError> {
+        let resp = request.send().await.map_err(NotionError::transport)?;
+        if !resp.status().is_success() {
+            return Err(NotionError::from_response(resp).await);
+        }
+        Ok(resp)
+    }
+
+    /// Sends a request that does the same however often it's repeated.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, NotionError> {
+        let mut attempt = 0;
+        loop {
+            // JSON bodies can always be cloned
+            match self.send_once(request.try_clone().unwrap()).await {
+                Err(err) if err.retryable() && attempt < self.retries => {
+                    tokio::time::sleep(err.retry_after.unwrap_or(backoff(attempt))).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// The id of the database's live page with the URL the page `body` creates has, if there is
+    /// one. A title alone could be another owner's repo of the same name.
+    async fn existing(&self, body: &Value) -> Result<Option<String>, NotionError> {
+        let Some((name, url)) = body["properties"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .find_map(|(name, value)| Some((name, value.get("url")?.as_str()?)))
+        else {
+            return Ok(None);
+        };
+        let page = self.find_page(name, url).await?;
+        Ok(page.and_then(|page| Some(page["id"].as_str()?.to_string())))
+    }
+}
+
+impl HttpNotionClient {
+    /// The databases shared with the integration, as ids and titles.
+    pub async fn search_databases(&self) -> Result<Vec<(String, String)>, NotionError> {
+        let mut databases = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut body = json!({
+                "filter": { "property": "object", "value": "database" },
+                "page_size": 100,
+            });
+            if let Some(cursor) = &cursor {
+                body["start_cursor"] = json!(cursor);
+            }
+            let list = self
+                .send(self.request(Method::POST, "search").json(&body))
+                .await?
+                .json::<QueryResponse>()
+                .await
+                .map_err(NotionError::transport)?;
+            databases.extend(list.results.iter().map(|database| {
+                let title = database["title"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|text| text["plain_text"].as_str())
+                    .collect::<String>();
+                (database["id"].as_str().unwrap().to_string(), title)
+            }));
+            match list.next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => return Ok(databases),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl NotionClient for HttpNotionClient {
+    async fn query_database(
+        &self,
+        cursor: Option<String>,
+        sorts: Option<Value>,
+    ) -> Result<(Vec<Value>, Option<String>), NotionError> {
+        let mut body = match cursor {
+            Some(cursor) => json!({ "start_cursor": cursor, "page_size": 100 }),
+            None => json!({ "page_size": 100 }),
+        };
+        if let Some(sorts) = sorts {
+            body["sorts"] = sorts;
+        }
+        let resp = self
+            .send(
+                self.request(
+                    Method::POST,
+                    &format!("databases/{}/query", self.database_id),
+                )
+                .json(&body),
+            )
+            .await?;
+        let list = resp.json::<QueryResponse>().await.unwrap();
+        Ok((list.results, list.next_cursor))
+    }
+
+    async fn database_properties(&self) -> Result<HashMap<String, Value>, NotionError> {
+        let resp = self
+            .send(self.request(Method::GET, &format!("databases/{}", self.database_id)))
+            .await?;
+        let database = resp.json::<Value>().await.unwrap();
+        Ok(database["properties"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, property)| (name.clone(), property.clone()))
+            .collect())
+    }
+
+    async fn update_database(&self, properties: Value) -> Result<(), NotionError> {
+        self.send(
+            self.request(Method::PATCH, &format!("databases/{}", self.database_id))
+                .json(&json!({ "properties": properties })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn block_children(&self, block_id: &str) -> Result<Vec<Value>, NotionError> {
+        let mut blocks = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut request = self
+                .request(Method::GET, &format!("blocks/{}/children", block_id))
+                .query(&[("page_size", "100")]);
+            if let Some(cursor) = &cursor {
+                request = request.query(&[("start_cursor", cursor)]);
+            }
+            let list = self
+                .send(request)
+                .await?
+                .json::<QueryResponse>()
+                .await
+                .unwrap();
+            blocks.extend(list.results);
+            match list.next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => return Ok(blocks),
+            }
+        }
+    }
+
+    async fn append_blocks(&self, block_id: &str, children: Vec<Value>) -> Result<(), NotionError> {
+        self.send(
+            self.request(Method::PATCH, &format!("blocks/{}/children", block_id))
+                .json(&json!({ "children": children })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_block(&self, block_id: &str) -> Result<(), NotionError> {
+        self.send(self.request(Method::DELETE, &format!("blocks/{}", block_id)))
+            .await?;
+        Ok(())
+    }
+
+    async fn create_page(&self, mut body: Value) -> Result<Created, NotionError> {
+        body["parent"] = json!({ "database_id": self.database_id });
+        let mut attempt = 0;
+        loop {
+            let err = match self
+                .send_once(self.request(Method::POST, "pages").json(&body))
+                .await
+            {
+                Ok(resp) => {
+                    let page = resp.json::<Value>().await.map_err(NotionError::transport)?;
+                    let id = page["id"].as_str().unwrap_or_default().to_string();
+                    return Ok(Created::New(id));
+                }
+                Err(err) if err.retryable() && attempt < self.retries => err,
+                Err(err) => return Err(err),
+            };
+            tokio::time::sleep(err.retry_after.unwrap_or(backoff(attempt))).await;
+            attempt += 1;
+            // a throttled create did nothing, any other may have made the page before failing,
+            // e.g. when only the response timed out; when that can't be told, sending it again
+            // could duplicate the page
+            if err.status == 429 {
+                continue;
+            }
+            if let Some(id) = self.existing(&body).await.map_err(|_| err)? {
+                return Ok(Created::Recovered(id));
+            }
+        }
+    }
+
+    async fn find_page(&self, property: &str, url: &str) -> Result<Option<Value>, NotionError> {
+        let query = json!({
+            "filter": { "property": property, "url": { "equals": url } },
+            "page_size": 1,
+        });
+        let list = self
+            .send(
+                self.request(
+                    Method::POST,
+                    &format!("databases/{}/query", self.database_id),
+                )
+                .json(&query),
+            )
+            .await?
+            .json::<QueryResponse>()
+            .await
+            .map_err(NotionError::transport)?;
+        Ok(list.results.into_iter().next())
+    }
+
+    async fn update_page(&self, page_id: &str, body: Value) -> Result<(), NotionError> {
+        self.send(
+            self.request(Method::PATCH, &format!("pages/{}", page_id))
+                .json(&body),
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use super::{
+    Contributors, Created, Gist, GithubClient, Milestone, NotionClient, RateLimit, ReleaseInfo,
+};
+use crate::{error::NotionError, telemetry};
+use async_trait::async_trait;
+use notion::chrono::{DateTime, NaiveDate, Utc};
+use octocrab::models::Repository;
+use opentelemetry::KeyValue;
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn repo(owner: &str, name: &str) -> Vec<KeyValue> {
+    vec![KeyValue::new("repo", format!("{}/{}", owner, name))]
+}
+
+fn page(page: u32) -> Vec<KeyValue> {
+    vec![KeyValue::new("page", i64::from(page))]
+}
+
+/// Runs a Notion call in a span, with the status it failed with.
+async fn notion<T>(
+    name: &'static str,
+    attributes: Vec<KeyValue>,
+    call: impl std::future::Future<Output = Result<T, NotionError>>,
+) -> Result<T, NotionError> {
+    telemetry::span(name, attributes, async {
+        let result = call.await;
+        if let Err(err) = &result {
+            telemetry::error(err);
+        }
+        result
+    })
+    .await
+}
+
+/// Passes calls through to another client, each in an OpenTelemetry span named after the call.
+pub struct Traced<C> {
+    inner: C,
+}
+
+impl<C> Traced<C> {
+    pub fn new(inner: C) -> Traced<C> {
+        Traced { inner }
+    }
+}
+
+#[async_trait]
+impl GithubClient for Traced<Box<dyn GithubClient>> {
+    async fn starred_page(&self, number: u32, per_page: u32) -> Vec<Repository> {
+        let call = self.inner.starred_page(number, per_page);
+        telemetry::span("github.starred_page", page(number), call).await
+    }
+
+    async fn starred_since_page(
+        &self,
+        number: u32,
+        per_page: u32,
+    ) -> Vec<(DateTime<Utc>, Repository)> {
+        let call = self.inner.starred_since_page(number, per_page);
+        telemetry::span("github.starred_since_page", page(number), call).await
+    }
+
+    async fn latest_release(&self, owner: &str, name: &str) -> Option<ReleaseInfo> {
+        let call = self.inner.latest_release(owner, name);
+        telemetry::span("github.latest_release", repo(owner, name), call).await
+    }
+
+    async fn feed_release(&self, owner: &str, name: &str) -> Option<Option<ReleaseInfo>> {
+        let call = self.inner.feed_release(owner, name);
+        telemetry::span("github.feed_release", repo(owner, name), call).await
+    }
+
+    async fn releases(&self, owner: &str, name: &str) -> Vec<ReleaseInfo> {
+        let call = self.inner.releases(owner, name);
+        telemetry::span("github.releases", repo(owner, name), call).await
+    }
+
+    async fn latest_commit(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<NaiveDate> {
+        let call = self.inner.latest_commit(owner, name, branch);
+        telemetry::span("github.latest_commit", repo(owner, name), call).await
+    }
+
+    async fn contributors(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: Option<&str>,
+    ) -> Option<Contributors> {
+        let call = self.inner.contributors(owner, name, branch);
+        telemetry::span("github.contributors", repo(owner, name), call).await
+    }
+
+    async fn open_pull_requests(&self, owner: &str, name: &str) -> Option<u64> {
+        let call = self.inner.open_pull_requests(owner, name);
+        telemetry::span("github.open_pull_requests", repo(owner, name), call).await
+    }
+
+    async fn latest_discussion(&self, owner: &str, name: &str) -> Option<NaiveDate> {
+        let call = self.inner.latest_discussion(owner, name);
+        telemetry::span("github.latest_discussion", repo(owner, name), call).await
+    }
+
+    async fn next_milestone(&self, owner: &str, name: &str) -> Option<Milestone> {
+        let call = self.inner.next_milestone(owner, name);
+        telemetry::span("github.next_milestone", repo(owner, name), call).await
+    }
+
+    async fn starred_gists_page(&self, number: u32) -> Result<Vec<Gist>, String> {
+        let call = self.inner.starred_gists_page(number);
+        telemetry::span("github.starred_gists_page", page(number), call).await
+    }
+
+    async fn search_repositories(&self, query: &str) -> Result<Vec<Repository>, String> {
+        let call = self.inner.search_repositories(query);
+        let attributes = vec![KeyValue::new("query", query.to_string())];
+        telemetry::span("github.search_repositories", attributes, call).await
+    }
+
+    async fn readme(&self, owner: &str, name: &str) -> Option<String> {
+        let call = self.inner.readme(owner, name);
+        telemetry::span("github.readme", repo(owner, name), call).await
+    }
+
+    async fn repository(&self, owner: &str, name: &str) -> Option<Repository> {
+        let call = self.inner.repository(owner, name);
+        telemetry::span("github.repository", repo(owner, name), call).await
+    }
+
+    async fn visible(&self, owner: &str, name: &str) -> Option<bool> {
+        let call = self.inner.visible(owner, name);
+        telemetry::span("github.visible", repo(owner, name), call).await
+    }
+
+    async fn rate_limit(&self) -> Option<RateLimit> {
+        telemetry::span("github.rate_limit", vec![], self.inner.rate_limit()).await
+    }
+}
+
+#[async_trait]
+impl NotionClient for Traced<Box<dyn NotionClient>> {
+    async fn query_database(
+        &self,
+        cursor: Option<String>,
+        sorts: Option<Value>,
+    ) -> Result<(Vec<Value>, Option<String>), NotionError> {
+        let call = self.inner.query_database(cursor, sorts);
+        notion("notion.query_database", vec![], call).await
+    }
+
+    async fn database_properties(&self) -> Result<HashMap<String, Value>, NotionError> {
+        let call = self.inner.database_properties();
+        notion("notion.database_properties", vec![], call).await
+    }
+
+    async fn update_database(&self, properties: Value) -> Result<(), NotionError> {
+        let call = self.inner.update_database(properties);
+        notion("notion.update_database", vec![], call).await
+    }
+
+    async fn block_children(&self, block_id: &str) -> Result<Vec<Value>, NotionError> {
+        let attributes = vec![KeyValue::new("block_id", block_id.to_string())];
+        let call = self.inner.block_children(block_id);
+        notion("notion.block_children", attributes, call).await
+    }
+
+    async fn append_blocks(&self, block_id: &str, children: Vec<Value>) -> Result<(), NotionError> {
+        let attributes = vec![KeyValue::new("block_id", block_id.to_string())];
+        let call = self.inner.append_blocks(block_id, children);
+        notion("notion.append_blocks", attributes, call).await
+    }
+
+    async fn delete_block(&self, block_id: &str) -> Result<(), NotionError> {
+        let attributes = vec![KeyValue::new("block_id", block_id.to_string())];
+        let call = self.inner.delete_block(block_id);
+        notion("notion.delete_block", attributes, call).await
+    }
+
+    async fn create_page(&self, body: Value) -> Result<Created, NotionError> {
+        let call = self.inner.create_page(body);
+        notion("notion.create_page", vec![], call).await
+    }
+
+    async fn find_page(&self, property: &str, url: &str) -> Result<Option<Value>, NotionError> {
+        let attributes = vec![KeyValue::new("url", url.to_string())];
+        let call = self.inner.find_page(property, url);
+        notion("notion.find_page", attributes, call).await
+    }
+
+    async fn update_page(&self, page_id: &str, body: Value) -> Result<(), NotionError> {
+        let attributes = vec![KeyValue::new("page_id", page_id.to_string())];
+        let call = self.inner.update_page(page_id, body);
+        notion("notion.update_page", attributes, call).await
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    checkbox_property, date_property, date_value, multi_select_property, multi_select_value,
+    number_property, select_property, select_value, text, text_property, url_property,
+};
+use notion::{chrono::NaiveDate, models::Page};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// What an optional column holds, which says how its values are read and written.
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Checkbox,
+    Date,
+    MultiSelect,
+    Number,
+    Select,
+    Text,
+    Url,
+}
+
+/// A column the sync fills in when the database has it and leaves alone otherwise.
+#[derive(Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    kind: Kind,
+}
+
+/// A value for an optional column.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell {
+    Checkbox(bool),
+    /// `None` clears the date
+    Date(Option<NaiveDate>),
+    MultiSelect(Vec<String>),
+    Number(u64),
+    Select(String),
+    Text(String),
+    /// `None` clears the URL
+    Url(Option<String>),
+}
+
+/// Optional date of the latest push to any branch, from the star listing.
+pub const LAST_PUSH: Column = Column::new("Last push", Kind::Date);
+/// Optional text with the repo's default branch, renamed along with it.
+pub const DEFAULT_BRANCH: Column = Column::new("Default branch", Kind::Text);
+/// Optional select with the repo's main language.
+pub const LANGUAGE: Column = Column::new("Language", Kind::Select);
+/// Optional multi-select with the repo's topics.
+pub const TOPICS: Column = Column::new("Topics", Kind::MultiSelect);
+/// Optional select with the repo's license, its SPDX id where there's one.
+pub const LICENSE: Column = Column::new("License", Kind::Select);
+/// Optional number of contributors, checked every `CONTRIBUTORS_CHECK_DAYS`.
+pub const CONTRIBUTORS: Column = Column::new("Contributors", Kind::Number);
+/// Optional text with the login of whoever joined the contributors last.
+pub const LATEST_CONTRIBUTOR: Column = Column::new("Latest contributor", Kind::Text);
+/// Optional number of open pull requests, with `TRACK_OPEN_PRS`.
+pub const OPEN_PRS: Column = Column::new("Open PRs", Kind::Number);
+/// Optional number of open issues without the pull requests GitHub counts among them.
+pub const OPEN_ISSUES: Column = Column::new("Open issues", Kind::Number);
+/// Optional checkbox ticked while the latest release looks like a security fix.
+pub const SECURITY_RELEASE: Column = Column::new("Security release", Kind::Checkbox);
+/// Optional text column with the start of the latest release's notes.
+pub const CHANGELOG: Column = Column::new("Changelog", Kind::Text);
+/// Optional select with how far the last release moved the version: major, minor, patch or unknown.
+pub const BREAKING: Column = Column::new("Breaking?", Kind::Select);
+/// Optional checkbox ticked for repos that show up in the lockfiles listed under `dependencies`.
+pub const DEPENDENCY: Column = Column::new("Dependency", Kind::Checkbox);
+/// Optional checkbox ticked for template repos.
+pub const TEMPLATE: Column = Column::new("Template", Kind::Checkbox);
+/// Optional URL of the upstream a mirror is kept in sync with, empty for other repos.
+pub const MIRROR: Column = Column::new("Mirror", Kind::Url);
+/// Optional date of the newest GitHub Discussions post, for projects that announce there.
+pub const DISCUSSION: Column = Column::new("Last discussion", Kind::Date);
+/// Optional text with the title and due date of the open milestone due soonest.
+pub const NEXT_MILESTONE: Column = Column::new("Next milestone", Kind::Text);
+/// Optional date that milestone is due.
+pub const MILESTONE_DUE: Column = Column::new("Milestone due", Kind::Date);
+/// Optional date the newest tag of the repo's configured `image` was pushed.
+pub const LATEST_IMAGE: Column = Column::new("Latest image", Kind::Date);
+/// Optional text with the version of the Homebrew formula or cask named like the repo.
+pub const HOMEBREW: Column = Column::new("Homebrew", Kind::Text);
+/// Optional text with the version of the AUR package named like the repo.
+pub const AUR: Column = Column::new("AUR", Kind::Text);
+/// Optional select saying whether distros ship the latest release, per Repology.
+pub const DISTROS: Column = Column::new("Distros", Kind::Select);
+
+/// Every optional column a page update can hold.
+pub const OPTIONAL: [Column; 22] = [
+    LAST_PUSH,
+    DEFAULT_BRANCH,
+    LANGUAGE,
+    TOPICS,
+    LICENSE,
+    CONTRIBUTORS,
+    LATEST_CONTRIBUTOR,
+    OPEN_PRS,
+    OPEN_ISSUES,
+    SECURITY_RELEASE,
+    CHANGELOG,
+    BREAKING,
+    DEPENDENCY,
+    TEMPLATE,
+    MIRROR,
+    DISCUSSION,
+    NEXT_MILESTONE,
+    MILESTONE_DUE,
+    LATEST_IMAGE,
+    HOMEBREW,
+    AUR,
+    DISTROS,
+];
+
+impl Column {
+    const fn new(name: &'static str, kind: Kind) -> Column {
+        Column { name, kind }
+    }
+
+    pub fn exists(self, schema: &HashMap<String, Value>) -> bool {
+        schema.contains_key(self.name)
+    }
+
+    /// What the page has in the column, in the form the sync writes it. A date or URL the page
+    /// doesn't have reads as a cleared one.
+    pub fn read(self, page: &Page) -> Option<Cell> {
+        let name = self.name;
+        match self.kind {
+            Kind::Checkbox => checkbox_property(page, name).map(Cell::Checkbox),
+            Kind::Date => Some(Cell::Date(date_property(page, name))),
+            Kind::MultiSelect => multi_select_property(page, name).map(Cell::MultiSelect),
+            Kind::Number => number_property(page, name).map(Cell::Number),
+            Kind::Select => select_property(page, name).map(Cell::Select),
+            Kind::Text => text_property(page, name).map(|text| Cell::Text(text.trim().to_string())),
+            Kind::Url => Some(Cell::Url(url_property(page, name))),
+        }
+    }
+}
+
+impl Cell {
+    /// Whether the page's `current` cell already says this.
+    pub fn matches(&self, current: Option<&Cell>) -> bool {
+        match (self, current) {
+            (Cell::Text(text), Some(Cell::Text(current))) => text.trim() == current,
+            (cell, current) => Some(cell) == current,
+        }
+    }
+
+    /// Nothing in it, what an unset cell and a cleared one have in common.
+    pub fn is_blank(&self) -> bool {
+        match self {
+            Cell::Date(date) => date.is_none(),
+            Cell::MultiSelect(names) => names.is_empty(),
+            Cell::Text(text) => text.trim().is_empty(),
+            Cell::Url(url) => url.is_none(),
+            _ => false,
+        }
+    }
+
+    /// The property value to send.
+    pub fn value(&self) -> Value {
+        match self {
+            Cell::Checkbox(checked) => json!({ "checkbox": checked }),
+            Cell::Date(Some(date)) => date_value(*date),
+            Cell::Date(None) => json!({ "date": null }),
+            Cell::MultiSelect(names) => multi_select_value(names),
+            Cell::Number(number) => json!({ "number": number }),
+            Cell::Select(name) => select_value(name),
+            Cell::Text(content) => json!({ "rich_text": text(content.clone()) }),
+            Cell::Url(url) => json!({ "url": url }),
+        }
+    }
+}
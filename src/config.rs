@@ -0,0 +1,427 @@
+use crate::{
+    client::{database_id, StarPaging},
+    notify::{Event, EventKind},
+};
+use notion::chrono::{Duration, NaiveDate};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, env, fs, path::PathBuf, str::FromStr};
+
+const DEFAULT_SECURITY_PATTERN: &str = r"(?i)\bCVE-\d{4}-\d+|\bGHSA-|\bsecurity\b";
+
+/// Settings from `CONFIG_FILE` (default `notionstar.toml`), which is optional.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// the database stars are synced into when `DATABASE` isn't set, written by the picker
+    pub database: Option<String>,
+    /// constant column values for every page, written only when it's created
+    #[serde(default)]
+    pub properties: BTreeMap<String, toml::Value>,
+    /// applies to every repo unless its own section says otherwise
+    #[serde(default)]
+    pub defaults: RepoConfig,
+    /// keyed by `owner/name`
+    #[serde(default)]
+    pub repos: BTreeMap<String, RepoConfig>,
+    /// channels events can be sent to, keyed by a name routes refer to
+    #[serde(default)]
+    pub notifiers: BTreeMap<String, NotifierConfig>,
+    /// each event goes to the first route it matches, or nowhere
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    /// regex for tags and release notes of security releases
+    pub security_pattern: Option<String>,
+    /// GitHub repository searches whose results are synced like stars, marked as discovered
+    #[serde(default)]
+    pub searches: Vec<String>,
+    /// paths of `Cargo.lock`, `package-lock.json` or `requirements.txt` files of my projects
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// `owner/name`s always given every lookup and refreshed even when dormant; once any repo is
+    /// pinned the others only get their dates refreshed
+    #[serde(default)]
+    pub pinned: Vec<String>,
+    pub llm: Option<LlmConfig>,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub stars: StarsConfig,
+    /// GitHub and Repology responses are kept here between runs, nothing is cached without it
+    pub cache_dir: Option<PathBuf>,
+    /// JSON lines file every write is appended to, which `undo` goes by
+    pub audit_log: Option<PathBuf>,
+    /// page id or link whose content is rewritten with the rollup of owners
+    pub owners_page: Option<String>,
+}
+
+/// How the star listing goes through its pages.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StarsConfig {
+    /// 1 to 100, the default
+    pub per_page: Option<u32>,
+    /// wait between requests, e.g. `500ms`, none by default
+    pub page_delay: Option<String>,
+    /// the listing stops after this many pages, a full sync cut short archives nothing
+    pub max_pages: Option<u32>,
+}
+
+impl StarsConfig {
+    pub fn paging(&self) -> Result<StarPaging, String> {
+        let per_page = self.per_page.unwrap_or(100);
+        if !(1..=100).contains(&per_page) {
+            return Err(format!("stars per_page {} isn't 1 to 100", per_page));
+        }
+        if self.max_pages == Some(0) {
+            return Err("stars max_pages can't be 0".to_string());
+        }
+        let delay = match &self.page_delay {
+            Some(delay) => humantime::parse_duration(delay)
+                .map_err(|err| format!("invalid stars page_delay {:?}: {}", delay, err))?,
+            None => std::time::Duration::ZERO,
+        };
+        Ok(StarPaging {
+            per_page,
+            delay,
+            max_pages: self.max_pages,
+        })
+    }
+}
+
+/// The runs of `notionstar daemon`.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DaemonConfig {
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+    /// each run starts up to this much later than scheduled, e.g. `10m`, so daemons everywhere
+    /// don't hit GitHub on the hour together
+    pub jitter: Option<String>,
+}
+
+impl DaemonConfig {
+    pub fn jitter(&self) -> Result<std::time::Duration, String> {
+        match &self.jitter {
+            Some(jitter) => humantime::parse_duration(jitter)
+                .map_err(|err| format!("invalid daemon jitter {:?}: {}", jitter, err)),
+            None => Ok(std::time::Duration::ZERO),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    /// cron expression in UTC, five fields or six with seconds first
+    pub cron: String,
+    /// what the run does, everything when empty
+    #[serde(default)]
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Add,
+    Archive,
+    Update,
+    /// the extra lookups during `update`
+    Enrich,
+}
+
+impl Schedule {
+    pub fn schedule(&self) -> Result<cron::Schedule, String> {
+        let expression = match self.cron.split_whitespace().count() {
+            5 => format!("0 {}", self.cron),
+            _ => self.cron.clone(),
+        };
+        cron::Schedule::from_str(&expression)
+            .map_err(|err| format!("invalid cron {:?}: {}", self.cron, err))
+    }
+}
+
+/// A chat completions API, OpenAI's by default. Ollama serves the same one under `/v1`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LlmConfig {
+    #[serde(default = "default_llm_url")]
+    pub url: String,
+    pub model: String,
+    /// new repos are filed under one of these in the `Category` select
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// language of the one-sentence summaries written to `摘要`, none are written without it
+    pub summary_language: Option<String>,
+    /// language the `Changelog` excerpt is translated into, it's left as is without one
+    pub changelog_language: Option<String>,
+}
+
+fn default_llm_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NotifierConfig {
+    Stdout,
+    Desktop,
+    Discord {
+        url: String,
+    },
+    Slack {
+        url: String,
+    },
+    Webhook {
+        url: String,
+    },
+    Matrix {
+        homeserver: String,
+        token: String,
+        room: String,
+    },
+    Email {
+        to: String,
+    },
+    Ntfy {
+        topic: String,
+        server: Option<String>,
+        token: Option<String>,
+    },
+    Telegram {
+        token: String,
+        /// chat id, quoted
+        chat: String,
+        /// checked against the `X-Telegram-Bot-Api-Secret-Token` header of bot commands, which
+        /// are only taken when it's set
+        secret: Option<String>,
+        api_url: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    /// event kinds taken, any when empty
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// the event's repo needs one of these tags, any repo when empty
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `owner/name`s, any repo when empty
+    #[serde(default)]
+    pub repos: Vec<String>,
+    pub notifier: String,
+    /// collect events into one message sent this often, e.g. `7d`, instead of sending each
+    pub every: Option<String>,
+    /// send the run's events in one message at its end, grouped by kind, instead of each
+    #[serde(default)]
+    pub batch: bool,
+    /// batches with more events than this are sent as counts per kind and `link` instead
+    pub batch_limit: Option<usize>,
+    /// where the counts point to for the rest, e.g. the database
+    pub link: Option<String>,
+}
+
+impl Route {
+    pub fn matches(&self, event: &Event) -> bool {
+        (self.events.is_empty() || self.events.contains(&event.kind))
+            && (self.tags.is_empty()
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| event.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))))
+            && (self.repos.is_empty()
+                || self
+                    .repos
+                    .iter()
+                    .any(|repo| repo.eq_ignore_ascii_case(&event.repo)))
+    }
+
+    pub fn every(&self) -> Option<Duration> {
+        let every = humantime::parse_duration(self.every.as_ref()?).ok()?;
+        Duration::from_std(every).ok()
+    }
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    /// branch to read the latest commit from instead of the default one
+    pub branch: Option<String>,
+    /// only releases whose tag starts with this count, for monorepos tagging several packages
+    pub tag_prefix: Option<String>,
+    pub include_prereleases: Option<bool>,
+    pub skip_commit_tracking: Option<bool>,
+    /// container image the project publishes, e.g. `ghcr.io/owner/name`
+    pub image: Option<String>,
+    /// nothing is sent about the repo, its page is still updated
+    pub mute: Option<bool>,
+    /// muted until this day, e.g. `"2025-01-31"`, without needing `mute`
+    pub mute_until: Option<NaiveDate>,
+    /// extra column values written on creation and whenever the column is empty
+    #[serde(default)]
+    pub properties: BTreeMap<String, toml::Value>,
+}
+
+impl Config {
+    pub fn path() -> String {
+        env::var("CONFIG_FILE").unwrap_or("notionstar.toml".to_string())
+    }
+
+    pub fn load() -> Result<Config, String> {
+        let path = Config::path();
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let config: Config =
+                    toml::from_str(&content).map_err(|err| format!("{}: {}", path, err))?;
+                config.check().map_err(|err| format!("{}: {}", path, err))?;
+                Ok(config)
+            }
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    pub fn security_pattern(&self) -> Regex {
+        Regex::new(
+            self.security_pattern
+                .as_deref()
+                .unwrap_or(DEFAULT_SECURITY_PATTERN),
+        )
+        .unwrap()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if let Some(pattern) = &self.security_pattern {
+            Regex::new(pattern).map_err(|err| format!("invalid security_pattern: {}", err))?;
+        }
+        self.daemon.jitter()?;
+        self.stars.paging()?;
+        if let Some(page) = &self.owners_page {
+            if database_id(page).is_none() {
+                return Err(format!("owners_page {:?} is not a page id or link", page));
+            }
+        }
+        for schedule in &self.daemon.schedules {
+            schedule.schedule()?;
+            if schedule.phases.contains(&Phase::Enrich) && !schedule.phases.contains(&Phase::Update)
+            {
+                return Err(format!(
+                    "schedule {:?}: enrich only happens during update",
+                    schedule.cron
+                ));
+            }
+        }
+        for route in &self.routes {
+            if !self.notifiers.contains_key(&route.notifier) {
+                return Err(format!("route to unknown notifier {}", route.notifier));
+            }
+            if route.batch && route.every.is_some() {
+                return Err(format!(
+                    "a route to {} can't both batch and wait with every",
+                    route.notifier
+                ));
+            }
+            if !route.batch && (route.batch_limit.is_some() || route.link.is_some()) {
+                return Err(format!(
+                    "batch_limit and link on a route to {} need batch = true",
+                    route.notifier
+                ));
+            }
+            if route.every.is_some() && route.every().is_none() {
+                return Err(format!(
+                    "invalid every {:?} on a route to {}",
+                    route.every.as_deref().unwrap(),
+                    route.notifier
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_pinned(&self, full_name: &str) -> bool {
+        self.pinned
+            .iter()
+            .any(|pinned| pinned.eq_ignore_ascii_case(full_name))
+    }
+
+    /// The repo's section merged over `defaults`.
+    pub fn repo(&self, full_name: &str) -> RepoConfig {
+        let repo = self
+            .repos
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(full_name))
+            .map(|(_, repo)| repo.clone())
+            .unwrap_or_default();
+        let mut properties = self.defaults.properties.clone();
+        properties.extend(repo.properties);
+        RepoConfig {
+            branch: repo.branch.or(self.defaults.branch.clone()),
+            tag_prefix: repo.tag_prefix.or(self.defaults.tag_prefix.clone()),
+            include_prereleases: repo
+                .include_prereleases
+                .or(self.defaults.include_prereleases),
+            skip_commit_tracking: repo
+                .skip_commit_tracking
+                .or(self.defaults.skip_commit_tracking),
+            image: repo.image.or(self.defaults.image.clone()),
+            mute: repo.mute.or(self.defaults.mute),
+            mute_until: repo.mute_until.or(self.defaults.mute_until),
+            properties,
+        }
+    }
+}
+
+impl RepoConfig {
+    pub fn muted(&self, today: NaiveDate) -> bool {
+        match self.mute_until {
+            Some(until) => today < until,
+            None => self.mute == Some(true),
+        }
+    }
+}
+
+/// A configured value in API form for a column of type `kind`. Columns the database doesn't have
+/// yet get a type from the value: text for strings, number, checkbox, or multi-select for lists.
+pub fn property_value(kind: Option<&str>, value: &toml::Value) -> Option<Value> {
+    let text = |content: &str| json!([{ "type": "text", "text": { "content": content } }]);
+    let names = |value: &toml::Value| -> Vec<Value> {
+        match value {
+            toml::Value::Array(items) => items
+                .iter()
+                .filter_map(toml::Value::as_str)
+                .map(|name| json!({ "name": name }))
+                .collect(),
+            value => value
+                .as_str()
+                .map(|name| vec![json!({ "name": name })])
+                .unwrap_or_default(),
+        }
+    };
+    let kind = kind.unwrap_or(match value {
+        toml::Value::Integer(_) | toml::Value::Float(_) => "number",
+        toml::Value::Boolean(_) => "checkbox",
+        toml::Value::Array(_) => "multi_select",
+        _ => "rich_text",
+    });
+    Some(match kind {
+        "rich_text" | "title" => json!({ kind: text(value.as_str()?) }),
+        "select" => json!({ "select": { "name": value.as_str()? } }),
+        "status" => json!({ "status": { "name": value.as_str()? } }),
+        "multi_select" => json!({ "multi_select": names(value) }),
+        "number" => match value {
+            toml::Value::Integer(number) => json!({ "number": number }),
+            toml::Value::Float(number) => json!({ "number": number }),
+            _ => return None,
+        },
+        "checkbox" => json!({ "checkbox": value.as_bool()? }),
+        "url" | "email" | "phone_number" => json!({ kind: value.as_str()? }),
+        "date" => match value {
+            toml::Value::String(date) => json!({ "date": { "start": date } }),
+            toml::Value::Datetime(date) => json!({ "date": { "start": date.to_string() } }),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
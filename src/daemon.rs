@@ -0,0 +1,107 @@
+use crate::{bot, config::Phase, error::task_failure, shutdown, sync, Notion, SyncArgs};
+use notion::chrono::{DateTime, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// Runs syncs on the config file's `[daemon]` schedules until Ctrl-C/SIGTERM. Schedules due at
+/// the same minute share one run doing all their phases. Bots that take commands are polled for
+/// them meanwhile, a command waits for a scheduled run to finish and the other way round.
+pub async fn daemon(notion: Arc<Notion>) {
+    let schedules = notion
+        .config
+        .daemon
+        .schedules
+        .iter()
+        // checked when the config was loaded
+        .map(|schedule| (schedule.schedule().unwrap(), schedule.phases.clone()))
+        .collect::<Vec<_>>();
+    let jitter = notion.config.daemon.jitter().unwrap();
+    if schedules.is_empty() {
+        notion
+            .ui
+            .error("no [[daemon.schedules]] in the config file");
+        std::process::exit(1);
+    }
+    shutdown::listen();
+    let runs = Arc::new(Mutex::new(()));
+    for (name, _) in bot::commanded(&notion) {
+        tokio::spawn(bot::poll(notion.clone(), name.clone(), runs.clone()));
+    }
+    loop {
+        let now = Utc::now();
+        let next = schedules
+            .iter()
+            .filter_map(|(schedule, phases)| Some((schedule.after(&now).next()?, phases)))
+            .collect::<Vec<_>>();
+        let Some(at) = next.iter().map(|(at, _)| *at).min() else {
+            notion.ui.error("no schedule fires again");
+            return;
+        };
+        let mut phases = Vec::new();
+        for (_, run) in next.iter().filter(|(next, _)| *next == at) {
+            match run.is_empty() {
+                true => phases.extend([Phase::Add, Phase::Archive, Phase::Update, Phase::Enrich]),
+                false => phases.extend(run.iter()),
+            }
+        }
+        // drawn anew for every run, so runs of one daemon don't settle on one offset either
+        let delay = jitter.mul_f64(random());
+        let start = at + notion::chrono::Duration::from_std(delay).unwrap_or_default();
+        notion.ui.println(format!(
+            "next run at {}",
+            start.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        if !sleep_until(start).await {
+            return;
+        }
+        let args = SyncArgs {
+            // unstars only show in the full listing
+            full: phases.contains(&Phase::Archive),
+            skip_add: !phases.contains(&Phase::Add),
+            skip_archive: !phases.contains(&Phase::Archive),
+            skip_update: !phases.contains(&Phase::Update),
+            skip_enrich: !phases.contains(&Phase::Enrich),
+            ..Default::default()
+        };
+        let running = runs.lock().await;
+        // a panic (expired token, rate limit) ends the run, not the daemon
+        let run = tokio::spawn({
+            let notion = notion.clone();
+            async move { sync(&notion, &args).await }
+        })
+        .await;
+        drop(running);
+        if let Err(err) = run {
+            notion
+                .report_failure(format!("notionstar sync failed: {}", task_failure(err)))
+                .await;
+        }
+        if shutdown::requested() {
+            return;
+        }
+    }
+}
+
+/// Uniform in [0, 1). 0 when the system has no randomness to give, a run on time rather than none.
+fn random() -> f64 {
+    let mut bytes = [0u8; 8];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return 0.0;
+    }
+    (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// `false` when a shutdown was asked for while waiting.
+async fn sleep_until(at: DateTime<Utc>) -> bool {
+    loop {
+        if shutdown::requested() {
+            return false;
+        }
+        let left = (at - Utc::now()).to_std().unwrap_or_default();
+        if left.is_zero() {
+            return true;
+        }
+        tokio::time::sleep(left.min(Duration::from_secs(1))).await;
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{
+    client, date_property, error::NotionError, hash, page_full_name, report::last_active,
+    select_property, state::State, Notion,
+};
+use notion::{
+    chrono::{Datelike, NaiveDate},
+    models::Page,
+};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, env};
+
+/// How many of the least active repos the dashboard lists.
+const STALEST: usize = 10;
+
+fn rich_text(content: &str) -> Value {
+    json!([{ "type": "text", "text": { "content": content } }])
+}
+
+fn heading(level: u8, content: &str) -> Value {
+    let kind = format!("heading_{}", level);
+    json!({ "object": "block", "type": kind, kind.clone(): { "rich_text": rich_text(content) } })
+}
+
+fn callout(emoji: &str, content: &str) -> Value {
+    json!({
+        "object": "block",
+        "type": "callout",
+        "callout": {
+            "rich_text": rich_text(content),
+            "icon": { "type": "emoji", "emoji": emoji },
+        },
+    })
+}
+
+fn table(header: [&str; 2], rows: Vec<[String; 2]>) -> Value {
+    let row = |cells: [&str; 2]| {
+        json!({
+            "object": "block",
+            "type": "table_row",
+            "table_row": { "cells": cells.map(rich_text) },
+        })
+    };
+    let mut children = vec![row(header)];
+    children.extend(rows.iter().map(|[a, b]| row([a, b])));
+    json!({
+        "object": "block",
+        "type": "table",
+        "table": {
+            "table_width": 2,
+            "has_column_header": true,
+            "has_row_header": false,
+            "children": children,
+        },
+    })
+}
+
+/// The dashboard section: repos per language, the repos released this month and the stalest
+/// repos.
+fn blocks(pages: &[Page], today: NaiveDate) -> Vec<Value> {
+    let mut languages = BTreeMap::<String, usize>::new();
+    let mut released = Vec::new();
+    let mut stale = Vec::new();
+    for page in pages {
+        let repo = page_full_name(page);
+        *languages
+            .entry(select_property(page, "Language").unwrap_or("Unknown".to_string()))
+            .or_default() += 1;
+        if date_property(page, "上次release")
+            .is_some_and(|date| (date.year(), date.month()) == (today.year(), today.month()))
+        {
+            released.push(repo.clone());
+        }
+        stale.push((last_active(page), repo));
+    }
+    let mut languages = languages.into_iter().collect::<Vec<_>>();
+    languages.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    released.sort();
+    // never active first, then the longest inactive
+    stale.sort();
+
+    let released = match released.len() {
+        0 => "No repo released this month.".to_string(),
+        count => format!(
+            "{} repo{} released this month: {}",
+            count,
+            if count == 1 { "" } else { "s" },
+            released.join(", ")
+        ),
+    };
+    vec![
+        heading(2, "Stars dashboard"),
+        callout("⭐", &format!("{} starred repos", pages.len())),
+        heading(3, "Languages"),
+        table(
+            ["Language", "Repos"],
+            languages
+                .into_iter()
+                .map(|(language, count)| [language, count.to_string()])
+                .collect(),
+        ),
+        heading(3, "Releases"),
+        callout("🚀", &released),
+        heading(3, "Stalest repos"),
+        table(
+            ["Repo", "Last active"],
+            stale
+                .into_iter()
+                .take(STALEST)
+                .map(|(date, repo)| [repo, date.map_or("never".to_string(), |d| d.to_string())])
+                .collect(),
+        ),
+    ]
+}
+
+/// Keeps a section of the `DASHBOARD_PAGE` up to date with statistics over the database. Only
+/// the blocks written by the last run are replaced, the rest of the page is the user's.
+pub async fn update(notion: &Notion, state: &mut State, today: NaiveDate) {
+    let Some(page) = env::var("DASHBOARD_PAGE")
+        .ok()
+        .and_then(|page| client::database_id(&page))
+    else {
+        return;
+    };
+    let blocks = blocks(&notion.get_database().await, today);
+    let digest = format!(
+        "{:016x}",
+        hash::fnv1a(serde_json::to_string(&blocks).unwrap().as_bytes())
+    );
+    if state.dashboard_hash.as_ref() == Some(&digest) {
+        return;
+    }
+    let result = async {
+        for block in &state.dashboard_blocks {
+            match notion.api.delete_block(block).await {
+                // removed by hand
+                Err(err) if err.status == 404 => {}
+                result => result?,
+            }
+        }
+        state.dashboard_blocks.clear();
+        notion.api.append_blocks(&page, blocks.clone()).await?;
+        let children = notion.api.block_children(&page).await?;
+        state.dashboard_blocks = children[children.len().saturating_sub(blocks.len())..]
+            .iter()
+            .filter_map(|block| block["id"].as_str().map(str::to_string))
+            .collect();
+        Ok::<(), NotionError>(())
+    }
+    .await;
+    match result {
+        Ok(()) => state.dashboard_hash = Some(digest),
+        Err(err) => notion
+            .ui
+            .error(format!("failed to update the dashboard: {}", err)),
+    }
+}
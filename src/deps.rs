@@ -0,0 +1,126 @@
+use crate::{full_name, repo::RepoRef, ui::Ui};
+use regex::Regex;
+use serde_json::Value;
+use std::{collections::HashSet, fs};
+
+/// Packages named in the lockfiles listed under `dependencies` in the config file.
+#[derive(Default)]
+pub struct Dependencies {
+    /// normalized package names
+    names: HashSet<String>,
+    /// lowercase `owner/name`s of packages pulled straight from GitHub
+    repos: HashSet<String>,
+}
+
+/// Lowercase with `_` and `.` as `-`, the way crates.io, npm and PyPI names compare.
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+impl Dependencies {
+    /// Reads `Cargo.lock`, `package-lock.json` and `requirements*.txt` files. Files that can't be
+    /// read are reported and skipped, the others still count.
+    pub fn load(paths: &[String], ui: &Ui) -> Dependencies {
+        let mut dependencies = Dependencies::default();
+        for path in paths {
+            let parsed = fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|content| dependencies.parse(path, &content));
+            if let Err(err) = parsed {
+                ui.error(format!(
+                    "failed to read dependencies from {}: {}",
+                    path, err
+                ));
+            }
+        }
+        dependencies
+    }
+
+    fn parse(&mut self, path: &str, content: &str) -> Result<(), String> {
+        if path.ends_with("Cargo.lock") {
+            let lock = content
+                .parse::<toml::Table>()
+                .map_err(|err| err.to_string())?;
+            let packages = lock
+                .get("package")
+                .and_then(toml::Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            for package in packages {
+                if let Some(name) = package.get("name").and_then(toml::Value::as_str) {
+                    self.names.insert(normalize(name));
+                }
+                // registry sources are on GitHub too, only git ones name the package's repo
+                match package.get("source").and_then(toml::Value::as_str) {
+                    Some(source) if source.starts_with("git+") => self.url(source),
+                    _ => {}
+                }
+            }
+        } else if path.ends_with("package-lock.json") {
+            let lock = serde_json::from_str::<Value>(content).map_err(|err| err.to_string())?;
+            // lockfile v2 and v3 list `node_modules/...` paths, v1 nests `dependencies`
+            if let Some(packages) = lock["packages"].as_object() {
+                for (path, package) in packages {
+                    if let Some((_, name)) = path.rsplit_once("node_modules/") {
+                        self.npm(name, package);
+                    }
+                }
+            }
+            self.npm_tree(&lock["dependencies"]);
+        } else if path.ends_with(".txt") {
+            for line in content.lines() {
+                let line = line.split(" #").next().unwrap_or_default().trim();
+                if line.starts_with('#') || line.is_empty() {
+                    continue;
+                }
+                if line.contains("github.com") {
+                    self.url(line);
+                }
+                let line = line.trim_start_matches("-e").trim();
+                let end = line
+                    .find(|c: char| !(c.is_alphanumeric() || "-_.".contains(c)))
+                    .unwrap_or(line.len());
+                if end > 0 && !line.starts_with("git+") && !line.starts_with('-') {
+                    self.names.insert(normalize(&line[..end]));
+                }
+            }
+        } else {
+            return Err("not a Cargo.lock, package-lock.json or requirements file".to_string());
+        }
+        Ok(())
+    }
+
+    fn npm(&mut self, name: &str, package: &Value) {
+        self.names.insert(normalize(name));
+        // `@scope/name` is usually published from a repo called `name`
+        if let Some((_, unscoped)) = name.split_once('/') {
+            self.names.insert(normalize(unscoped));
+        }
+        if let Some(resolved) = package["resolved"].as_str() {
+            self.url(resolved);
+        }
+    }
+
+    fn npm_tree(&mut self, dependencies: &Value) {
+        for (name, package) in dependencies.as_object().into_iter().flatten() {
+            self.npm(name, package);
+            self.npm_tree(&package["dependencies"]);
+        }
+    }
+
+    /// Records the repo a GitHub URL points at.
+    fn url(&mut self, url: &str) {
+        let github =
+            Regex::new(r"github\.com[/:]([\w.-]+)/([\w.-]+?)(?:\.git)?(?:[/#?@]|$)").unwrap();
+        if let Some(captures) = github.captures(url) {
+            self.repos
+                .insert(format!("{}/{}", &captures[1], &captures[2]).to_lowercase());
+        }
+    }
+
+    /// Whether a package is named like the repo or was fetched from it.
+    pub fn contains(&self, repo: &RepoRef) -> bool {
+        self.names.contains(&normalize(&repo.name))
+            || self.repos.contains(&full_name(repo).to_lowercase())
+    }
+}
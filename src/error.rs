@@ -0,0 +1,125 @@
+use crate::redact::redact;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// A failed Notion API call, with the `request_id` Notion support asks for.
+#[derive(Debug, Serialize)]
+pub struct NotionError {
+    pub status: u16,
+    pub code: Option<String>,
+    pub message: String,
+    pub request_id: Option<String>,
+    /// how long a 429 said to wait
+    #[serde(skip)]
+    pub retry_after: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    code: Option<String>,
+    message: Option<String>,
+    request_id: Option<String>,
+}
+
+impl NotionError {
+    pub async fn from_response(resp: reqwest::Response) -> NotionError {
+        let status = resp.status().as_u16();
+        let header_request_id = resp
+            .headers()
+            .get("x-notion-request-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let retry_after = resp
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs);
+        let text = redact(&resp.text().await.unwrap_or_default());
+        match serde_json::from_str::<ErrorBody>(&text) {
+            Ok(body) => NotionError {
+                status,
+                code: body.code,
+                message: body.message.unwrap_or(text),
+                request_id: body.request_id.or(header_request_id),
+                retry_after,
+            },
+            Err(_) => NotionError {
+                status,
+                code: None,
+                message: text,
+                request_id: header_request_id,
+                retry_after,
+            },
+        }
+    }
+
+    /// A request that never got a response, status 0.
+    pub fn transport(err: reqwest::Error) -> NotionError {
+        NotionError {
+            status: 0,
+            code: None,
+            message: redact(&err.to_string()),
+            request_id: None,
+            retry_after: None,
+        }
+    }
+
+    /// Worth trying again: throttled, a conflicting save, Notion having trouble, or no answer.
+    pub fn retryable(&self) -> bool {
+        matches!(self.status, 0 | 409 | 429 | 500 | 502 | 503 | 504)
+    }
+}
+
+impl Display for NotionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.status == 0 {
+            write!(f, "{}", self.message)?;
+        } else {
+            write!(f, "{}", self.status)?;
+            if let Some(code) = &self.code {
+                write!(f, " {}", code)?;
+            }
+            write!(f, ": {}", self.message)?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request_id {})", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// A per-repo failure as it shows up in logs, the webhook summary and Actions annotations.
+#[derive(Debug, Serialize)]
+pub struct SyncError {
+    pub operation: &'static str,
+    pub repo: String,
+    pub page_id: Option<String>,
+    pub error: NotionError,
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed for {}", self.operation, self.repo)?;
+        if let Some(page_id) = &self.page_id {
+            write!(f, " (page {})", page_id)?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+/// What a sync task died of, its panic message when it panicked.
+pub fn task_failure(err: tokio::task::JoinError) -> String {
+    match err.try_into_panic() {
+        Ok(panic) => redact(
+            &panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or("failed".to_string()),
+        ),
+        Err(err) => err.to_string(),
+    }
+}
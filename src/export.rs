@@ -0,0 +1,109 @@
+use clap::ValueEnum;
+use notion::models::{
+    properties::{DateOrDateTime, PropertyValue},
+    Page,
+};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeSet, fs::File, path::Path};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+pub fn export(pages: &[Page], format: ExportFormat, path: &Path) {
+    match format {
+        ExportFormat::Csv => export_csv(pages, path),
+        ExportFormat::Json => export_json(pages, path),
+    }
+}
+
+fn export_json(pages: &[Page], path: &Path) {
+    let rows = pages
+        .iter()
+        .map(|page| {
+            let properties = page
+                .properties
+                .properties
+                .iter()
+                .map(|(name, value)| (name.clone(), property_value(value)))
+                .collect::<Map<String, Value>>();
+            json!({
+                "id": page.id.to_string(),
+                "last_edited_time": page.last_edited_time.to_rfc3339(),
+                "properties": properties,
+            })
+        })
+        .collect::<Vec<Value>>();
+    serde_json::to_writer_pretty(File::create(path).unwrap(), &rows).unwrap();
+}
+
+fn export_csv(pages: &[Page], path: &Path) {
+    let columns = pages
+        .iter()
+        .flat_map(|page| page.properties.properties.keys().cloned())
+        .collect::<BTreeSet<String>>();
+    let mut writer = csv::Writer::from_path(path).unwrap();
+    writer
+        .write_record(std::iter::once(&"id".to_string()).chain(columns.iter()))
+        .unwrap();
+    for page in pages {
+        let mut record = vec![page.id.to_string()];
+        for column in &columns {
+            record.push(match page.properties.properties.get(column) {
+                Some(value) => plain(&property_value(value)),
+                None => String::new(),
+            });
+        }
+        writer.write_record(&record).unwrap();
+    }
+    writer.flush().unwrap();
+}
+
+fn plain(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(plain).collect::<Vec<String>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn date_string(date: &DateOrDateTime) -> String {
+    match date {
+        DateOrDateTime::Date(date) => date.to_string(),
+        DateOrDateTime::DateTime(date) => date.to_rfc3339(),
+    }
+}
+
+pub fn property_value(value: &PropertyValue) -> Value {
+    match value {
+        PropertyValue::Title { title: text, .. }
+        | PropertyValue::Text {
+            rich_text: text, ..
+        } => Value::String(text.iter().map(|t| t.plain_text()).collect()),
+        PropertyValue::Number { number, .. } => match number {
+            Some(number) => Value::Number(number.clone()),
+            None => Value::Null,
+        },
+        PropertyValue::Select { select, .. } => match select {
+            Some(select) => json!(select.name),
+            None => Value::Null,
+        },
+        PropertyValue::MultiSelect { multi_select, .. } => match multi_select {
+            Some(options) => json!(options.iter().map(|o| o.name.clone()).collect::<Vec<_>>()),
+            None => Value::Null,
+        },
+        PropertyValue::Date { date, .. } => match date {
+            Some(date) => match &date.end {
+                Some(end) => json!(format!("{}/{}", date_string(&date.start), date_string(end))),
+                None => json!(date_string(&date.start)),
+            },
+            None => Value::Null,
+        },
+        PropertyValue::Checkbox { checkbox, .. } => Value::Bool(*checkbox),
+        PropertyValue::Url { url, .. } => json!(url),
+        other => serde_json::to_value(other).unwrap_or(Value::Null),
+    }
+}
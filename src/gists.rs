@@ -0,0 +1,131 @@
+use crate::{
+    audit, canonical_url, client::Gist, date_property, date_value, kept, same_url, select_value,
+    sync_off, text, url_property, Notion,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+/// The column a gist's page is matched on.
+const URL: &str = "URL";
+
+fn properties(title: &str, gist: &Gist) -> Value {
+    let mut properties = Map::new();
+    let name = gist.description.clone().unwrap_or(gist.filename.clone());
+    properties.insert(title.to_string(), json!({ "title": text(name) }));
+    properties.insert(
+        URL.to_string(),
+        json!({ "url": canonical_url(&gist.html_url) }),
+    );
+    properties.insert(
+        "Updated".to_string(),
+        date_value(gist.updated_at.date_naive()),
+    );
+    if let Some(language) = &gist.language {
+        properties.insert("Language".to_string(), select_value(language));
+    }
+    if let Some(owner) = &gist.owner {
+        properties.insert(
+            "Owner".to_string(),
+            json!({ "rich_text": text(owner.clone()) }),
+        );
+    }
+    Value::Object(properties)
+}
+
+/// Mirrors starred gists into the database `notion` was opened on: new ones get a page, pages
+/// whose gist changed since are rewritten, and pages of unstarred gists are archived with a
+/// backup. Pages are matched on their `URL`. Pages with `Sync` off are left alone and kept ones
+/// aren't archived, as for stars; the rest of a star sync (limits, deadlines, notifications,
+/// state) doesn't apply, gists are few enough to go through in one go.
+pub async fn sync(notion: &Notion) {
+    let pb = notion.ui.counter("gists");
+    let mut gists = Vec::new();
+    for page in 1.. {
+        let gist_page = match notion.github.starred_gists_page(page).await {
+            Ok(gist_page) => gist_page,
+            // archiving against part of the list would archive the rest
+            Err(err) => {
+                pb.abandon_with_message("failed");
+                notion
+                    .ui
+                    .error(format!("failed to list starred gists: {}", err));
+                std::process::exit(1);
+            }
+        };
+        if gist_page.is_empty() {
+            break;
+        }
+        gists.extend(gist_page);
+        pb.set_position(gists.len() as u64);
+    }
+    pb.finish_with_message("done");
+    let title = notion
+        .schema()
+        .await
+        .iter()
+        .find(|(_, property)| property["type"] == "title")
+        .map_or("Name".to_string(), |(name, _)| name.clone());
+    let database = notion.get_database().await;
+
+    let mut added = 0;
+    let mut updated = 0;
+    for gist in &gists {
+        let page = database
+            .iter()
+            .find(|page| url_property(page, URL).is_some_and(|url| same_url(&url, &gist.html_url)));
+        let body = json!({ "properties": notion.writable(properties(&title, gist)).await });
+        let result = match page {
+            None => notion.api.create_page(body.clone()).await.map(|created| {
+                audit::record(notion, "gist create", Some(created.id()), None, &body);
+                added += 1
+            }),
+            Some(page) if sync_off(page) => Ok(()),
+            Some(page) if date_property(page, "Updated") != Some(gist.updated_at.date_naive()) => {
+                let page_id = page.id.to_string();
+                notion
+                    .api
+                    .update_page(&page_id, body.clone())
+                    .await
+                    .map(|()| {
+                        audit::record(notion, "gist update", Some(&page_id), Some(page), &body);
+                        updated += 1
+                    })
+            }
+            Some(_) => Ok(()),
+        };
+        if let Err(err) = result {
+            notion
+                .ui
+                .error(format!("failed to write gist {}: {}", gist.html_url, err));
+        }
+    }
+
+    let starred = gists
+        .iter()
+        .map(|gist| canonical_url(&gist.html_url).to_lowercase())
+        .collect::<HashSet<String>>();
+    let unstarred = database
+        .iter()
+        .filter(|page| {
+            url_property(page, URL)
+                .is_some_and(|url| !starred.contains(&canonical_url(&url).to_lowercase()))
+        })
+        .filter(|page| !sync_off(page))
+        .filter(|page| {
+            let keep = kept(page);
+            if keep {
+                notion
+                    .ui
+                    .println(format!("kept despite unstar: {}", page.title().unwrap()));
+            }
+            !keep
+        })
+        .collect();
+    let (archived, _) = notion.archive_repo(unstarred, None).await;
+    notion.ui.println(format!(
+        "gists: {} added, {} updated, {} archived",
+        added,
+        updated,
+        archived.len()
+    ));
+}
@@ -0,0 +1,7 @@
+/// FNV-1a, used where a hash is stored or shown and so must not change between builds the way
+/// the std hasher's output may.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
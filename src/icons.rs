@@ -0,0 +1,42 @@
+use std::env;
+
+const DEFAULTS: [(&str, &str); 24] = [
+    ("Rust", "🦀"),
+    ("Python", "🐍"),
+    ("Go", "🐹"),
+    ("JavaScript", "🟨"),
+    ("TypeScript", "🔷"),
+    ("Java", "☕"),
+    ("Kotlin", "🟪"),
+    ("Swift", "🐦"),
+    ("C", "🔧"),
+    ("C++", "⚙️"),
+    ("C#", "🎯"),
+    ("Ruby", "💎"),
+    ("PHP", "🐘"),
+    ("Shell", "🐚"),
+    ("Lua", "🌙"),
+    ("Elixir", "💧"),
+    ("Haskell", "🎓"),
+    ("Scala", "🔺"),
+    ("Zig", "⚡"),
+    ("Nix", "❄️"),
+    ("Vue", "💚"),
+    ("HTML", "🌐"),
+    ("CSS", "🎨"),
+    ("Jupyter Notebook", "📓"),
+];
+
+/// Page icon for a repo's primary language. Off unless `LANGUAGE_ICONS` is set; its value is a
+/// comma separated list of overrides such as `Go=🐿️,Dockerfile=🐳` on top of the defaults.
+pub fn icon(language: Option<&str>) -> Option<String> {
+    let overrides = env::var("LANGUAGE_ICONS").ok()?;
+    let language = language?;
+    let (_, icon) = overrides
+        .split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(language, icon)| (language.trim(), icon.trim()))
+        .chain(DEFAULTS)
+        .find(|(name, _)| name.eq_ignore_ascii_case(language))?;
+    Some(icon.to_string()).filter(|icon| !icon.is_empty())
+}
@@ -0,0 +1,100 @@
+use crate::telemetry;
+use notion::chrono::{DateTime, NaiveDate, Utc};
+use opentelemetry::KeyValue;
+use serde_json::Value;
+use std::env;
+
+/// When the newest tag of a container image was pushed, for `ghcr.io/...` images from the GitHub
+/// packages API (the token needs `read:packages`) and for Docker Hub ones (`docker.io/...` or no
+/// registry at all) from the Hub's API. Other registries don't say when a tag was pushed.
+pub async fn latest_push(http: &reqwest::Client, image: &str) -> Result<NaiveDate, String> {
+    // a tag or digest in the reference doesn't matter, every tag is looked at
+    let image = image.split(['@', ':']).next().unwrap_or_default();
+    let (registry, path) = match image.split_once('/') {
+        Some((registry, path)) if registry.contains('.') => (registry, path),
+        _ => ("docker.io", image),
+    };
+    let lookup = async {
+        match registry {
+            "ghcr.io" => ghcr(http, path).await,
+            "docker.io" | "index.docker.io" | "registry-1.docker.io" => {
+                docker_hub(http, path).await
+            }
+            _ => Err(format!(
+                "can't tell when images on {} were pushed",
+                registry
+            )),
+        }
+    };
+    telemetry::span(
+        "registry",
+        vec![KeyValue::new("image", image.to_string())],
+        lookup,
+    )
+    .await
+}
+
+async fn ghcr(http: &reqwest::Client, path: &str) -> Result<NaiveDate, String> {
+    let (owner, package) = path
+        .split_once('/')
+        .ok_or(format!("no package name in ghcr.io/{}", path))?;
+    let api = env::var("GITHUB_API_URL").unwrap_or("https://api.github.com".to_string());
+    let token = env::var("GITHUB_API").unwrap_or_default();
+    // the owner may be a user or an organization, and only the matching route answers
+    for kind in ["users", "orgs"] {
+        let resp = http
+            .get(format!(
+                "{}/{}/{}/packages/container/{}/versions?per_page=1",
+                api.trim_end_matches('/'),
+                kind,
+                owner,
+                package.replace('/', "%2F")
+            ))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("User-Agent", "notionstar")
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            continue;
+        }
+        if !resp.status().is_success() {
+            return Err(format!("GitHub returned {}", resp.status()));
+        }
+        let versions = resp.json::<Value>().await.map_err(|err| err.to_string())?;
+        return date(&versions[0]["updated_at"]);
+    }
+    Err(format!("no package ghcr.io/{}", path))
+}
+
+async fn docker_hub(http: &reqwest::Client, path: &str) -> Result<NaiveDate, String> {
+    // official images live under `library`
+    let path = match path.contains('/') {
+        true => path.to_string(),
+        false => format!("library/{}", path),
+    };
+    let hub = env::var("DOCKER_HUB_URL").unwrap_or("https://hub.docker.com".to_string());
+    let resp = http
+        .get(format!(
+            "{}/v2/repositories/{}/tags?ordering=last_updated&page_size=1",
+            hub.trim_end_matches('/'),
+            path
+        ))
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Docker Hub returned {}", resp.status()));
+    }
+    let tags = resp.json::<Value>().await.map_err(|err| err.to_string())?;
+    date(&tags["results"][0]["tag_last_pushed"])
+        .or_else(|_| date(&tags["results"][0]["last_updated"]))
+}
+
+fn date(value: &Value) -> Result<NaiveDate, String> {
+    value
+        .as_str()
+        .and_then(|date| date.parse::<DateTime<Utc>>().ok())
+        .map(|date| date.date_naive())
+        .ok_or("no tags".to_string())
+}
@@ -0,0 +1,41 @@
+use crate::notify::{Event, EventKind};
+use notion::chrono::NaiveDate;
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+/// Appends a `## <date>` section listing the run's new stars, unstars and new releases to a
+/// Markdown file, a plain-text journal of star activity. Runs that saw none of them add nothing.
+pub fn append(path: &Path, date: NaiveDate, events: &[Event]) -> std::io::Result<()> {
+    let list = |kind: EventKind, line: fn(&Event) -> String| {
+        events
+            .iter()
+            .filter(|event| event.kind == kind)
+            .map(|event| format!("- {}\n", line(event)))
+            .collect::<String>()
+    };
+    let sections = [
+        (
+            "Starred",
+            list(EventKind::Added, |event| event.repo.clone()),
+        ),
+        (
+            "Unstarred",
+            list(EventKind::Archived, |event| event.repo.clone()),
+        ),
+        (
+            "New releases",
+            list(EventKind::Release, |event| event.message.clone()),
+        ),
+    ];
+    if sections.iter().all(|(_, lines)| lines.is_empty()) {
+        return Ok(());
+    }
+    let mut section = format!("\n## {}\n", date);
+    for (title, lines) in sections.iter().filter(|(_, lines)| !lines.is_empty()) {
+        section.push_str(&format!("\n### {}\n\n{}", title, lines));
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(section.as_bytes())
+}
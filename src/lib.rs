@@ -0,0 +1,1690 @@
+pub mod actions;
+mod audit;
+pub mod backup;
+pub mod bench;
+mod bot;
+pub mod bump;
+pub mod changes;
+pub mod client;
+mod column;
+pub mod config;
+pub mod daemon;
+mod dashboard;
+mod deps;
+pub mod error;
+pub mod export;
+pub mod gists;
+mod hash;
+mod icons;
+mod images;
+pub mod journal;
+mod llm;
+mod notes;
+mod notify;
+pub mod oauth;
+mod options;
+pub mod owners;
+mod packages;
+pub mod picker;
+pub mod redact;
+pub mod repair;
+mod repo;
+mod repology;
+pub mod report;
+mod run;
+pub mod secrets;
+pub mod selfupdate;
+pub mod serve;
+pub mod service;
+pub mod shutdown;
+pub mod sqlite;
+mod state;
+mod status;
+pub mod summary;
+mod target;
+pub mod telemetry;
+mod template;
+mod transfer;
+pub mod tui;
+pub mod ui;
+pub mod undo;
+pub mod verify;
+
+use clap::Args;
+use client::{
+    ApiCalls, Cached, Created, GithubClient, HttpGithubClient, HttpNotionClient, Metered,
+    NotionClient, Recorder, ReleaseInfo, Replayer, Traced,
+};
+use column::{
+    Cell, Column, CHANGELOG, DEFAULT_BRANCH, DEPENDENCY, DISCUSSION, LANGUAGE, LAST_PUSH, LICENSE,
+    MIRROR, SECURITY_RELEASE, TEMPLATE, TOPICS,
+};
+use config::Config;
+use deps::Dependencies;
+use error::{NotionError, SyncError};
+use llm::Llm;
+use notion::{
+    chrono::{DateTime, NaiveDate, NaiveTime, SecondsFormat, Utc},
+    ids::PropertyId,
+    models::{
+        properties::{DateOrDateTime, DateValue, PropertyValue},
+        text::{Annotations, RichText, RichTextCommon, Text},
+        Page,
+    },
+};
+use regex::Regex;
+use repo::RepoRef;
+use serde_json::{json, Map, Value};
+use state::State;
+use std::time::{Duration, Instant};
+use std::{
+    collections::HashSet,
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    str::FromStr,
+};
+use summary::Summary;
+use tokio::sync::{mpsc, OnceCell};
+use ui::Ui;
+
+#[derive(Args, Default)]
+pub struct SyncArgs {
+    /// Also mirror the synced data to a local target, e.g. `--target markdown vault/stars`
+    #[arg(long, num_args = 2, value_names = ["KIND", "PATH"])]
+    pub target: Vec<String>,
+    /// Only add and refresh the first N repos, handy for trying out changes cheaply
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Stop cleanly (saving progress for the next run) once this much time has passed, e.g. `25m`
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub deadline: Option<Duration>,
+    /// List every star instead of only the ones added since the last run
+    #[arg(long)]
+    pub full: bool,
+    /// Force a refresh of just these repos and skip add/archive, e.g. `--only owner/name,owner/other`
+    #[arg(long, value_delimiter = ',')]
+    pub only: Vec<String>,
+    /// Don't create pages for new stars
+    #[arg(long)]
+    pub skip_add: bool,
+    /// Don't archive pages of unstarred repos
+    #[arg(long)]
+    pub skip_archive: bool,
+    /// Don't refresh existing pages, e.g. for a cheap nightly membership-only run
+    #[arg(long)]
+    pub skip_update: bool,
+    /// Refresh dates only, without the extra lookups: discussions, milestones, images, packages,
+    /// Repology, open PRs, contributors and summaries
+    #[arg(long)]
+    pub skip_enrich: bool,
+    /// Print the summary, changes since the last run included, as JSON instead of text
+    #[arg(long)]
+    pub json: bool,
+    /// Append a dated section with this run's new stars, unstars and new releases to a Markdown file
+    #[arg(long, value_name = "FILE")]
+    pub changelog: Option<PathBuf>,
+    /// After applying, work out what a run right after this one would do and fail unless it's
+    /// nothing, to catch values that never compare equal to what was written
+    #[arg(long)]
+    pub assert_empty_plan: bool,
+    /// `owner/name`s left alone entirely, neither added, archived nor refreshed
+    #[arg(skip)]
+    pub exclude: Vec<String>,
+}
+
+impl SyncArgs {
+    fn wants(&self, repo: &RepoRef) -> bool {
+        (self.only.is_empty()
+            || self
+                .only
+                .iter()
+                .any(|only| only.eq_ignore_ascii_case(&full_name(repo))))
+            && !self.excludes(&full_name(repo))
+    }
+    fn excludes(&self, full_name: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|exclude| exclude.eq_ignore_ascii_case(full_name))
+    }
+}
+
+pub async fn sync(notion: &Notion, args: &SyncArgs) -> Summary {
+    telemetry::span("sync", vec![], run::run_sync(notion, args)).await
+}
+
+/// How many chunks of 100 database rows may wait for the sync to get to them.
+const CHUNKS_AHEAD: usize = 2;
+/// Optional hidden column holding `PageUpdate::hash` of what was last synced.
+const SYNC_HASH: &str = "Sync hash";
+/// Optional checkbox for repos that always get every lookup, like the config file's `pinned`.
+const PINNED: &str = "Pinned";
+/// Optional checkbox silencing the repo's notifications, its page is still updated.
+const MUTE: &str = "Mute";
+/// Optional date the repo is muted until, ticked `Mute` box or not.
+const MUTE_UNTIL: &str = "Mute until";
+/// Optional date the repo was created, written with the page and never after.
+const CREATED: &str = "Created";
+/// Optional select the LLM files new repos under, one of the configured `categories`.
+const CATEGORY: &str = "Category";
+/// Optional text with a one-sentence summary of the README in the configured `summary_language`.
+const SUMMARY: &str = "摘要";
+
+/// Values to write to a page, `None` leaves the property as it is.
+#[derive(Clone, Default)]
+struct PageUpdate {
+    release: Option<NaiveDate>,
+    commit: Option<NaiveDate>,
+    status: Option<&'static str>,
+    /// values of the optional columns the database has, keyed by column name
+    columns: BTreeMap<&'static str, Cell>,
+    /// values from the config file, already in API form
+    configured: Map<String, Value>,
+    sync_hash: Option<String>,
+}
+
+impl PageUpdate {
+    fn is_empty(&self) -> bool {
+        self.properties().is_empty()
+    }
+
+    /// Sets `column` to `cell`, unless the database has no such column. Writing to a missing
+    /// column fails the whole request, and select values would create it.
+    fn set(&mut self, schema: &HashMap<String, Value>, column: Column, cell: Option<Cell>) {
+        if let Some(cell) = cell.filter(|_| column.exists(schema)) {
+            self.columns.insert(column.name, cell);
+        }
+    }
+
+    fn date(&self, column: Column) -> Option<NaiveDate> {
+        match self.columns.get(column.name) {
+            Some(Cell::Date(date)) => *date,
+            _ => None,
+        }
+    }
+
+    fn text(&self, column: Column) -> Option<String> {
+        match self.columns.get(column.name) {
+            Some(Cell::Text(text)) => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    /// Only the values that differ from what the page has.
+    fn changed_from(&self, page: &Page) -> PageUpdate {
+        PageUpdate {
+            release: self
+                .release
+                .filter(|release| date_property(page, "上次release") != Some(*release)),
+            commit: self
+                .commit
+                .filter(|commit| date_property(page, "上次commit") != Some(*commit)),
+            status: self
+                .status
+                .filter(|status| status_property(page, "Status").as_deref() != Some(status)),
+            columns: column::OPTIONAL
+                .iter()
+                .filter_map(|column| {
+                    let cell = self.columns.get(column.name)?;
+                    let current = column.read(page);
+                    (!cell.matches(current.as_ref())).then(|| (column.name, cell.clone()))
+                })
+                .collect(),
+            configured: self.configured.clone(),
+            sync_hash: self.sync_hash.clone(),
+        }
+    }
+
+    /// Fingerprint of the values and of the columns there are to write them to, keys are sorted
+    /// so it's stable between runs.
+    fn hash(&self, columns: &Value) -> String {
+        let body = json!({ "values": self.properties(), "columns": columns }).to_string();
+        format!("{:016x}", hash::fnv1a(body.as_bytes()))
+    }
+
+    /// The property values to send, keyed by column name.
+    fn properties(&self) -> Map<String, Value> {
+        let mut body = self.configured.clone();
+        if let Some(release) = self.release {
+            body.insert(
+                "上次release".to_string(),
+                json!(PropertyValue::Date {
+                    id: PropertyId::from_str("pkvi").unwrap(),
+                    date: Some(DateValue {
+                        start: DateOrDateTime::Date(release),
+                        end: None,
+                        time_zone: None,
+                    }),
+                }),
+            );
+        }
+        if let Some(commit) = self.commit {
+            body.insert(
+                "上次Commit".to_string(),
+                json!(PropertyValue::Date {
+                    id: PropertyId::from_str("%7B%3Ddw").unwrap(),
+                    date: Some(DateValue {
+                        start: DateOrDateTime::Date(commit),
+                        end: None,
+                        time_zone: None,
+                    }),
+                }),
+            );
+        }
+        if let Some(status) = self.status {
+            body.insert("Status".to_string(), status::property(status));
+        }
+        for (name, cell) in &self.columns {
+            body.insert(name.to_string(), cell.value());
+        }
+        if let Some(hash) = &self.sync_hash {
+            body.insert(
+                SYNC_HASH.to_string(),
+                json!({ "rich_text": text(hash.clone()) }),
+            );
+        }
+        body
+    }
+}
+
+/// Property types whose values Notion computes, writing them is an error.
+const READ_ONLY_TYPES: [&str; 9] = [
+    "formula",
+    "rollup",
+    "unique_id",
+    "created_time",
+    "created_by",
+    "last_edited_time",
+    "last_edited_by",
+    "button",
+    "verification",
+];
+
+pub struct Notion {
+    api: Box<dyn NotionClient>,
+    github: Box<dyn GithubClient>,
+    http: reqwest::Client,
+    /// the database's columns, read on the first write
+    schema: OnceCell<HashMap<String, Value>>,
+    /// select options per column, the schema's plus the ones added since
+    options: Mutex<HashMap<String, Vec<Value>>>,
+    config: Config,
+    /// matches release tags and notes of security fixes
+    security: Regex,
+    /// packages in the configured lockfiles
+    dependencies: Dependencies,
+    /// the configured `[llm]`
+    llm: Option<Llm>,
+    /// content of the `TEMPLATE_PAGE`, read on the first page creation
+    template: OnceCell<Vec<Value>>,
+    /// requests made to the APIs this run
+    calls: Arc<ApiCalls>,
+    pub ui: Ui,
+}
+impl Notion {
+    /// On `DATABASE`, or the config file's `database`, or one picked from those shared with the
+    /// integration.
+    pub async fn new(ui: Ui) -> Notion {
+        let database = match env::var("DATABASE") {
+            Ok(database) => database,
+            // a broken config file is reported once it's loaded for real
+            Err(_) => match Config::load().ok().and_then(|config| config.database) {
+                Some(database) => database,
+                None => picker::pick_database(&ui).await,
+            },
+        };
+        Notion::with_database(ui, database).await
+    }
+    /// Like `new`, for another database such as the `GISTS_DATABASE`.
+    pub async fn with_database(ui: Ui, database: String) -> Notion {
+        let Some(database) = client::database_id(&database) else {
+            ui.error(format!(
+                "{:?} is not a database id: copy the 32 characters after the last / of the database's link, or the whole link",
+                database
+            ));
+            std::process::exit(1);
+        };
+        let timeout = request_timeout(&ui);
+        let version = env::var("NOTION_VERSION").unwrap_or(client::DEFAULT_VERSION.to_string());
+        if !client::SUPPORTED_VERSIONS.contains(&version.as_str()) {
+            ui.error(format!(
+                "unsupported NOTION_VERSION {}, supported versions: {}",
+                version,
+                client::SUPPORTED_VERSIONS.join(", ")
+            ));
+            std::process::exit(1);
+        }
+        let token = |name: &str, hint: &str| match env::var(name) {
+            Ok(token) => token,
+            Err(_) => {
+                ui.error(format!("{} is not set, {}", name, hint));
+                std::process::exit(1);
+            }
+        };
+        let github_token = token(
+            "GITHUB_API",
+            "create a token at https://github.com/settings/tokens",
+        );
+        let notion_token = token(
+            "NOTION_API",
+            "copy the integration's secret from https://www.notion.so/my-integrations or run `notionstar login`",
+        );
+        let mut github = HttpGithubClient::new(
+            github_token,
+            &env::var("GITHUB_API_URL").unwrap_or("https://api.github.com".to_string()),
+            timeout,
+        );
+        if env::var("RELEASE_FEED").is_ok() {
+            github = github.with_release_feed(
+                &env::var("GITHUB_WEB_URL").unwrap_or("https://github.com".to_string()),
+            );
+        }
+        let notion = Notion::with_clients(
+            Box::new(github),
+            Box::new(
+                HttpNotionClient::new(
+                    notion_token,
+                    database.clone(),
+                    &env::var("NOTION_API_URL").unwrap_or("https://api.notion.com/v1".to_string()),
+                    version,
+                    timeout,
+                )
+                .with_retries(state::env_number("NOTION_RETRIES", 3).max(0) as u32),
+            ),
+            ui,
+        );
+        // outside the counting, so answers from the cache aren't counted as requests
+        let notion = match notion.config.cache_dir.clone() {
+            Some(dir) => Notion {
+                github: Box::new(Cached::new(notion.github, &dir)),
+                ..notion
+            },
+            None => notion,
+        };
+        if let Err(err) = notion.preflight(&database).await {
+            notion.ui.error(err);
+            std::process::exit(1);
+        }
+        notion
+    }
+    /// Reads the database once before anything else, so a wrong token or database fails here
+    /// with what to do about it instead of deep inside a query. The schema is kept for the run.
+    pub async fn preflight(&self, database: &str) -> Result<(), String> {
+        let err = match self.api.database_properties().await {
+            Ok(schema) => {
+                self.schema.set(schema).ok();
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+        Err(match (err.status, err.code.as_deref()) {
+            (401, _) | (_, Some("unauthorized")) => format!(
+                "NOTION_API was rejected, the token is wrong or was revoked: copy the integration's secret again from https://www.notion.so/my-integrations or run `notionstar login` ({})",
+                err
+            ),
+            (404, _) | (_, Some("object_not_found")) => format!(
+                "database {} isn't shared with the integration (or doesn't exist): open it in Notion and add the integration under ⋯ > Connections ({})",
+                database, err
+            ),
+            (400, _) | (_, Some("validation_error")) => format!(
+                "{} isn't a database the integration can sync into, check that DATABASE is a database and not a page or view ({})",
+                database, err
+            ),
+            _ => format!("failed to read database {}: {}", database, err),
+        })
+    }
+    /// Serves every read from fixtures saved by `record`, no credentials or network needed.
+    pub fn replay(dir: &Path, ui: Ui) -> Notion {
+        Notion::with_clients(
+            Box::new(Replayer::new(dir)),
+            Box::new(Replayer::new(dir)),
+            ui,
+        )
+    }
+    /// Puts every API call in an OpenTelemetry span, see `telemetry`.
+    pub fn traced(self) -> Notion {
+        Notion {
+            api: Box::new(Traced::new(self.api)),
+            github: Box::new(Traced::new(self.github)),
+            ..self
+        }
+    }
+    /// Saves every API response under `dir` for `replay`.
+    pub fn record(self, dir: &Path) -> Notion {
+        Notion {
+            api: Box::new(Recorder::new(self.api, dir)),
+            github: Box::new(Recorder::new(self.github, dir)),
+            ..self
+        }
+    }
+    pub fn with_clients(
+        github: Box<dyn GithubClient>,
+        api: Box<dyn NotionClient>,
+        ui: Ui,
+    ) -> Notion {
+        let config = Config::load().unwrap_or_else(|err| {
+            ui.error(format!("invalid config file {}", err));
+            std::process::exit(1);
+        });
+        let http = reqwest::Client::builder()
+            .timeout(request_timeout(&ui))
+            .build()
+            .unwrap();
+        let calls = Arc::new(ApiCalls::default());
+        Notion {
+            api: Box::new(Metered::new(api, calls.clone())),
+            github: Box::new(Metered::new(github, calls.clone())),
+            calls,
+            llm: config.llm.as_ref().map(|llm| Llm::new(llm, &http)),
+            http,
+            schema: OnceCell::new(),
+            options: Mutex::new(HashMap::new()),
+            security: config.security_pattern(),
+            dependencies: Dependencies::load(&config.dependencies, &ui),
+            config,
+            template: OnceCell::new(),
+            ui,
+        }
+    }
+    /// Tells the configured notifiers the run died, e.g. on an expired token.
+    pub async fn report_failure(&self, message: String) {
+        let mut state = State::load();
+        let failure = vec![notify::failure(message)];
+        notify::dispatch(self, &mut state, failure, &HashSet::new(), Utc::now()).await;
+        // digests that came due went out with it
+        state.save();
+    }
+    /// Results of the config file's `searches` that aren't among `stars`.
+    async fn discover(&self, stars: &[RepoRef]) -> Vec<RepoRef> {
+        let mut discovered: Vec<RepoRef> = Vec::new();
+        for query in &self.config.searches {
+            let repos = match self.github.search_repositories(query).await {
+                Ok(repos) => repos,
+                Err(err) => {
+                    self.ui.error(format!("search {:?} failed: {}", query, err));
+                    continue;
+                }
+            };
+            for repo in repos {
+                let known = stars
+                    .iter()
+                    .chain(&discovered)
+                    .any(|star| star.id == repo.id.0);
+                if !known {
+                    discovered.push(repo.into());
+                }
+            }
+        }
+        if !discovered.is_empty() {
+            self.ui
+                .println(format!("{} repos discovered by searches", discovered.len()));
+        }
+        discovered
+    }
+    async fn get_stars(&self) -> Vec<RepoRef> {
+        self.star_listing().await.0
+    }
+    /// All stars, and whether the listing got to the end of them rather than stopping at the
+    /// configured `max_pages`.
+    async fn star_listing(&self) -> (Vec<RepoRef>, bool) {
+        let (pages, mut received) = mpsc::channel(CHUNKS_AHEAD);
+        let mut stars = Vec::new();
+        let collecting = async {
+            while let Some(page) = received.recv().await {
+                stars.extend(page);
+            }
+        };
+        let (complete, ()) = tokio::join!(self.star_pages(pages), collecting);
+        (stars, complete)
+    }
+    /// Sends the stars to `pages` a page at a time, paced by the config's `[stars]`, as
+    /// `RepoRef`s: GitHub's whole repo objects are dropped as soon as a page is read. Returns
+    /// whether the listing got to the end, like `star_listing`.
+    async fn star_pages(&self, pages: mpsc::Sender<Vec<RepoRef>>) -> bool {
+        // checked when the config was loaded
+        let paging = self.config.stars.paging().unwrap();
+        let pb = self.ui.counter("stars");
+        let mut listed = 0;
+        let mut page = 1;
+        let mut last_full = true;
+        let complete = loop {
+            if paging.max_pages.is_some_and(|max_pages| page > max_pages) {
+                // a short last page was the end anyway
+                break !last_full;
+            }
+            if page > 1 {
+                tokio::time::sleep(paging.delay).await;
+            }
+            let star_page = self.github.starred_page(page, paging.per_page).await;
+            if star_page.is_empty() {
+                break true;
+            }
+            last_full = star_page.len() >= paging.per_page as usize;
+            listed += star_page.len();
+            let refs = star_page.into_iter().map(RepoRef::from).collect();
+            if pages.send(refs).await.is_err() {
+                break false;
+            }
+            page += 1;
+            pb.set_position(listed as u64);
+        };
+        pb.finish_with_message("done");
+        complete
+    }
+    /// Newest-first star listing that stops at the first star not newer than `since`.
+    async fn get_stars_since(&self, since: DateTime<Utc>) -> Vec<(DateTime<Utc>, RepoRef)> {
+        let paging = self.config.stars.paging().unwrap();
+        let pb = self.ui.counter("new stars");
+        let mut stars = Vec::new();
+        let mut page = 1;
+        loop {
+            if paging.max_pages.is_some_and(|max_pages| page > max_pages) {
+                pb.finish_with_message("done");
+                return stars;
+            }
+            if page > 1 {
+                tokio::time::sleep(paging.delay).await;
+            }
+            let star_page = self.github.starred_since_page(page, paging.per_page).await;
+            let exhausted = star_page.len() < paging.per_page as usize;
+            for (starred_at, repo) in star_page {
+                if starred_at <= since {
+                    pb.finish_with_message("done");
+                    return stars;
+                }
+                stars.push((starred_at, repo.into()));
+                pb.inc(1);
+            }
+            if exhausted {
+                pb.finish_with_message("done");
+                return stars;
+            }
+            page += 1;
+        }
+    }
+    pub async fn get_database(&self) -> Vec<Page> {
+        let (chunks, mut received) = mpsc::channel(CHUNKS_AHEAD);
+        let mut results = Vec::new();
+        let collecting = async {
+            while let Some(chunk) = received.recv().await {
+                results.extend(chunk);
+            }
+        };
+        tokio::join!(self.read_database(None, chunks), collecting);
+        results
+    }
+    /// Sends the database to `chunks` as it's read, 100 parsed rows at a time. The channel is
+    /// bounded so rows don't pile up ahead of a slow reader, and dropping the receiver stops the
+    /// reading. `sorts` is the query's.
+    async fn read_database(&self, sorts: Option<Value>, chunks: mpsc::Sender<Vec<Page>>) {
+        let pb = self.ui.counter("database");
+        let mut parsing: Option<tokio::task::JoinHandle<Vec<Page>>> = None;
+        let mut fetched = 0;
+        let mut cursor = None;
+        loop {
+            let (pages, next_cursor) = self
+                .api
+                .query_database(cursor, sorts.clone())
+                .await
+                .unwrap();
+            // the previous batch was parsed while this one was being fetched
+            if let Some(parsing) = parsing.take() {
+                if chunks.send(parsing.await.unwrap()).await.is_err() {
+                    return;
+                }
+            }
+            fetched += pages.len();
+            pb.set_position(fetched as u64);
+            let batch = tokio::task::spawn_blocking(move || {
+                pages
+                    .into_iter()
+                    .map(client::parse_page)
+                    .collect::<Vec<Page>>()
+            });
+            if next_cursor.is_none() {
+                chunks.send(batch.await.unwrap()).await.ok();
+                break;
+            } else {
+                parsing = Some(batch);
+                cursor = next_cursor
+            }
+        }
+        pb.finish_with_message("done");
+    }
+
+    async fn _add_repo(
+        &self,
+        stars: &RepoRef,
+        (release, commit): (Option<NaiveDate>, Option<NaiveDate>),
+        latest: Option<&ReleaseInfo>,
+        discovered: bool,
+        now: DateTime<Utc>,
+    ) -> Result<Created, NotionError> {
+        let schema = self.schema().await;
+        let push = stars.pushed_at.map(|date| date.date_naive());
+        let status = if discovered && schema.contains_key("Status") {
+            Some(status::DISCOVERED)
+        } else {
+            status::enabled().then(|| {
+                let last_active = [release, commit, push].into_iter().flatten().max();
+                status::status(stars, last_active, now.date_naive())
+            })
+        };
+        // everything known about the repo goes out with the create, not in a follow-up update
+        let mut listed = PageUpdate {
+            release,
+            commit,
+            status,
+            configured: self.configured_properties(stars, None).await,
+            ..self.listed(schema, stars)
+        };
+        let security = self.is_security(latest);
+        listed.set(schema, SECURITY_RELEASE, Some(Cell::Checkbox(security)));
+        if CHANGELOG.exists(schema) {
+            let changelog = self.changelog(latest).await;
+            listed.set(schema, CHANGELOG, changelog.map(Cell::Text));
+        }
+        if DISCUSSION.exists(schema) {
+            let discussion = self.latest_discussion(stars).await;
+            listed.set(
+                schema,
+                DISCUSSION,
+                discussion.map(|date| Cell::Date(Some(date))),
+            );
+        }
+        let category = match schema.contains_key(CATEGORY) {
+            true => self.categorize(stars).await,
+            false => None,
+        };
+        if let Some(category) = category {
+            listed
+                .configured
+                .insert(CATEGORY.to_string(), select_value(&category));
+        }
+        let summary = match schema.contains_key(SUMMARY) {
+            true => self.summarize(stars).await,
+            false => None,
+        };
+        if let Some(summary) = summary {
+            listed
+                .configured
+                .insert(SUMMARY.to_string(), json!({ "rich_text": text(summary) }));
+        }
+        if schema.contains_key(SYNC_HASH) {
+            listed.sync_hash = Some(self.sync_hash(stars, &listed, None).await);
+        }
+        self.new_data(stars, &listed, now).await
+    }
+
+    async fn new_data(
+        &self,
+        stars: &RepoRef,
+        listed: &PageUpdate,
+        // when the run that creates the page started
+        started: DateTime<Utc>,
+    ) -> Result<Created, NotionError> {
+        let release = &stars.html_url;
+        let properties = HashMap::from([
+            (
+                "名称".to_string(),
+                PropertyValue::Title {
+                    id: PropertyId::from_str("title").unwrap(),
+                    title: text(stars.name.clone()),
+                },
+            ),
+            (
+                "release".to_owned(),
+                PropertyValue::Url {
+                    id: PropertyId::from_str("pr%7Cj").unwrap(),
+                    url: Some(release.clone()),
+                },
+            ),
+            (
+                "owner".to_owned(),
+                PropertyValue::Text {
+                    id: PropertyId::from_str("OHG%3B").unwrap(),
+                    rich_text: text(stars.owner.clone()),
+                },
+            ),
+        ]);
+        let schema = self.schema().await;
+        let mut properties = json!(properties);
+        properties
+            .as_object_mut()
+            .unwrap()
+            .extend(listed.properties());
+        // only ever written here, the creation date doesn't change
+        let created = stars
+            .created_at
+            .map(|date| date.date_naive())
+            .filter(|_| schema.contains_key(CREATED));
+        if let Some(created) = created {
+            properties[CREATED] = date_value(created);
+        }
+        // optional, tells synced rows apart from ones added by hand
+        if schema.contains_key("Synced by") {
+            properties["Synced by"] = json!({
+                "rich_text": text(format!(
+                    "notionstar {} at {}",
+                    env!("CARGO_PKG_VERSION"),
+                    started.to_rfc3339_opts(SecondsFormat::Secs, true)
+                ))
+            });
+        }
+
+        let mut body = json!({ "properties": self.writable(properties).await });
+        if let Some(icon) = icons::icon(stars.language.as_deref()) {
+            body["icon"] = json!({ "type": "emoji", "emoji": icon });
+        }
+        let template = self.template().await;
+        if !template.is_empty() {
+            body["children"] = json!(template);
+        }
+        let created = match self.api.create_page(body.clone()).await {
+            // Notion sometimes creates the page and still fails the call, look before the next
+            // run adds it again, by URL since another owner's repo can have the same name
+            Err(err) => match self.api.find_page("release", release).await {
+                Ok(Some(page)) => Ok(Created::Recovered(
+                    page["id"].as_str().unwrap_or_default().to_string(),
+                )),
+                _ => Err(err),
+            },
+            created => created,
+        };
+        if let Ok(created) = &created {
+            audit::record(self, "create", Some(created.id()), None, &body);
+        }
+        created
+    }
+
+    /// Dates of the latest release and the latest commit on the default branch, or on what the
+    /// repo's config section picks instead.
+    async fn fetch_activity(&self, repo: &RepoRef) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let release = self.latest_release(repo, None).await;
+        (
+            release.as_ref().and_then(release_date),
+            self.latest_commit(repo).await,
+        )
+    }
+    /// The release that counts as the latest, given the repo's `tag_prefix` and prerelease settings.
+    /// `known` is the tag and date the page already has, which spare asking the API when the
+    /// releases feed still shows that tag.
+    async fn latest_release(
+        &self,
+        repo: &RepoRef,
+        known: Option<(&str, NaiveDate)>,
+    ) -> Option<ReleaseInfo> {
+        let owner = &repo.owner;
+        let config = self.config.repo(&full_name(repo));
+        let prereleases = config.include_prereleases == Some(true);
+        // `releases/latest` never returns prereleases, nor picks between several packages' tags
+        if prereleases || config.tag_prefix.is_some() {
+            let prefix = config.tag_prefix.unwrap_or_default();
+            self.github
+                .releases(owner, &repo.name)
+                .await
+                .into_iter()
+                .find(|release| {
+                    release.tag_name.starts_with(&prefix) && (prereleases || !release.prerelease)
+                })
+        } else {
+            // the feed has no publish dates, a release it shows for the first time gets its own
+            // from the API
+            match self.github.feed_release(owner, &repo.name).await {
+                Some(None) => None,
+                Some(Some(mut release)) => match known {
+                    Some((tag, date)) if tag == release.tag_name => {
+                        release.published_at = Some(date.and_time(NaiveTime::MIN).and_utc());
+                        Some(release)
+                    }
+                    _ => self.github.latest_release(owner, &repo.name).await,
+                },
+                None => self.github.latest_release(owner, &repo.name).await,
+            }
+        }
+    }
+    /// The columns that come with the star listing, for the ones the database has.
+    fn listed(&self, schema: &HashMap<String, Value>, repo: &RepoRef) -> PageUpdate {
+        let mut listed = PageUpdate::default();
+        let push = repo
+            .pushed_at
+            .map(|date| Cell::Date(Some(date.date_naive())));
+        listed.set(schema, LAST_PUSH, push);
+        listed.set(
+            schema,
+            DEFAULT_BRANCH,
+            repo.default_branch.clone().map(Cell::Text),
+        );
+        listed.set(schema, LANGUAGE, repo.language.clone().map(Cell::Select));
+        listed.set(schema, TOPICS, repo.topics.clone().map(Cell::MultiSelect));
+        listed.set(schema, LICENSE, repo.license.clone().map(Cell::Select));
+        let dependency = self.dependencies.contains(repo);
+        listed.set(schema, DEPENDENCY, Some(Cell::Checkbox(dependency)));
+        listed.set(schema, TEMPLATE, Some(Cell::Checkbox(repo.is_template)));
+        listed.set(schema, MIRROR, Some(Cell::Url(repo.mirror_url.clone())));
+        listed
+    }
+    /// Whether a release's tag or notes match `security_pattern`.
+    fn is_security(&self, release: Option<&ReleaseInfo>) -> bool {
+        release.is_some_and(|release| {
+            self.security.is_match(&release.tag_name)
+                || self
+                    .security
+                    .is_match(release.body.as_deref().unwrap_or_default())
+        })
+    }
+    async fn latest_discussion(&self, repo: &RepoRef) -> Option<NaiveDate> {
+        let owner = &repo.owner;
+        self.github.latest_discussion(owner, &repo.name).await
+    }
+    /// The packaged version to write, empty once the package is gone. A failed lookup leaves the
+    /// column as it is.
+    fn packaged(
+        &self,
+        source: &str,
+        repo: &RepoRef,
+        version: Result<Option<String>, String>,
+    ) -> Option<String> {
+        match version {
+            Ok(version) => Some(version.unwrap_or_default()),
+            Err(err) => {
+                self.ui.error(format!(
+                    "failed to look up {} on {}: {}",
+                    full_name(repo),
+                    source,
+                    err
+                ));
+                None
+            }
+        }
+    }
+    /// The configured category the LLM picks for a repo from its name, description and topics.
+    async fn categorize(&self, repo: &RepoRef) -> Option<String> {
+        let llm = self.llm.as_ref()?;
+        let categories = &self.config.llm.as_ref()?.categories;
+        if categories.is_empty() {
+            return None;
+        }
+        let instructions = format!(
+            "Pick the category that fits this GitHub repository best. \
+             Answer with exactly one of these and nothing else: {}",
+            categories.join("; ")
+        );
+        let input = format!(
+            "{}\n{}\nTopics: {}",
+            full_name(repo),
+            repo.description.as_deref().unwrap_or_default(),
+            repo.topics.as_deref().unwrap_or_default().join(", ")
+        );
+        let answer = match llm.ask(&instructions, &input).await {
+            Ok(answer) => answer,
+            Err(err) => {
+                self.ui
+                    .error(format!("failed to categorize {}: {}", full_name(repo), err));
+                return None;
+            }
+        };
+        // models like to add quotes or a full stop
+        let answer = answer.trim_matches(|c: char| c.is_whitespace() || "\"'`.*".contains(c));
+        let category = categories
+            .iter()
+            .find(|category| category.eq_ignore_ascii_case(answer));
+        if category.is_none() {
+            self.ui.error(format!(
+                "LLM put {} under unknown category {}",
+                full_name(repo),
+                answer
+            ));
+        }
+        category.cloned()
+    }
+    /// The `Changelog` excerpt of a release's notes, translated into the configured
+    /// `changelog_language`. `None` when the translation failed, so the column keeps what it has
+    /// rather than flipping between languages.
+    async fn changelog(&self, release: Option<&ReleaseInfo>) -> Option<String> {
+        let excerpt = notes::excerpt(
+            release
+                .and_then(|release| release.body.as_deref())
+                .unwrap_or_default(),
+        );
+        let language = self
+            .config
+            .llm
+            .as_ref()
+            .and_then(|llm| llm.changelog_language.as_ref());
+        let (Some(llm), Some(language)) = (&self.llm, language) else {
+            return Some(excerpt);
+        };
+        if excerpt.is_empty() {
+            return Some(excerpt);
+        }
+        let instructions = format!(
+            "Translate these release notes into {}. Keep version numbers, names and code as \
+             they are, and the line breaks too. Answer with the translation only.",
+            language
+        );
+        match llm.ask(&instructions, &excerpt).await {
+            Ok(translation) => Some(notes::clip(translation)),
+            Err(err) => {
+                self.ui
+                    .error(format!("failed to translate release notes: {}", err));
+                None
+            }
+        }
+    }
+    /// A sentence on what the repo is, in the configured `summary_language`, from its README.
+    async fn summarize(&self, repo: &RepoRef) -> Option<String> {
+        let llm = self.llm.as_ref()?;
+        let language = self.config.llm.as_ref()?.summary_language.as_ref()?;
+        let owner = &repo.owner;
+        let readme = self.github.readme(owner, &repo.name).await;
+        let readme = readme.as_deref().unwrap_or_default();
+        // the start says what the project is, the rest would only cost tokens
+        let end = readme
+            .char_indices()
+            .nth(6000)
+            .map_or(readme.len(), |(end, _)| end);
+        let instructions = format!(
+            "Describe what this GitHub repository is in one short sentence in {}. \
+             Answer with the sentence only.",
+            language
+        );
+        let input = format!(
+            "{}\n{}\n\n{}",
+            full_name(repo),
+            repo.description.as_deref().unwrap_or_default(),
+            &readme[..end]
+        );
+        match llm.ask(&instructions, &input).await {
+            Ok(summary) => Some(summary),
+            Err(err) => {
+                self.ui
+                    .error(format!("failed to summarize {}: {}", full_name(repo), err));
+                None
+            }
+        }
+    }
+    /// When the repo's configured image last got a new tag.
+    async fn latest_image(&self, repo: &RepoRef) -> Option<NaiveDate> {
+        let image = self.config.repo(&full_name(repo)).image?;
+        match images::latest_push(&self.http, &image).await {
+            Ok(date) => Some(date),
+            Err(err) => {
+                self.ui
+                    .error(format!("failed to check image {}: {}", image, err));
+                None
+            }
+        }
+    }
+    async fn latest_commit(&self, repo: &RepoRef) -> Option<NaiveDate> {
+        let owner = &repo.owner;
+        if self.config.repo(&full_name(repo)).skip_commit_tracking == Some(true) {
+            return None;
+        }
+        self.github
+            .latest_commit(owner, &repo.name, self.branch(repo).as_deref())
+            .await
+    }
+    /// The branch commits are read from.
+    fn branch(&self, repo: &RepoRef) -> Option<String> {
+        self.config
+            .repo(&full_name(repo))
+            .branch
+            .or(repo.default_branch.clone())
+    }
+    /// The `Sync hash` of `values` for `page`, or for a page about to be created from them. Values
+    /// not looked up this run count as what the page has, and blank text as none, so the hash only
+    /// moves when something actually changed and a new page's is what the first refresh computes.
+    /// A column added to the database or the config moves it too, so it gets filled in.
+    async fn sync_hash(&self, repo: &RepoRef, values: &PageUpdate, page: Option<&Page>) -> String {
+        let schema = self.schema().await;
+        let columns = column::OPTIONAL
+            .iter()
+            .filter(|column| column.exists(schema))
+            .filter_map(|column| {
+                let cell = match values.columns.get(column.name) {
+                    Some(cell) => Some(cell.clone()),
+                    None => page.and_then(|page| column.read(page)),
+                };
+                cell.filter(|cell| !cell.is_blank())
+                    .map(|cell| (column.name, cell))
+            })
+            .collect();
+        let hashed = PageUpdate {
+            columns,
+            // written when blank rather than kept in sync, their columns count below
+            configured: Map::new(),
+            sync_hash: None,
+            ..values.clone()
+        };
+        let mut columns = schema.keys().cloned().collect::<Vec<String>>();
+        columns.sort();
+        let configured = self.config.repo(&full_name(repo)).properties;
+        let configured = self
+            .config
+            .properties
+            .keys()
+            .chain(configured.keys())
+            .collect::<BTreeSet<&String>>();
+        hashed.hash(&json!({ "database": columns, "configured": configured }))
+    }
+    /// The configured column values for a repo. A new page gets the static `[properties]` too,
+    /// an existing one only the repo's values for columns it has nothing in.
+    async fn configured_properties(
+        &self,
+        repo: &RepoRef,
+        page: Option<&Page>,
+    ) -> Map<String, Value> {
+        let schema = self.schema().await;
+        let configured = self.config.repo(&full_name(repo)).properties;
+        let values = match page {
+            None => self
+                .config
+                .properties
+                .clone()
+                .into_iter()
+                .chain(configured)
+                .collect::<BTreeMap<String, toml::Value>>(),
+            Some(page) => configured
+                .into_iter()
+                .filter(|(name, _)| blank_property(page, name))
+                .collect(),
+        };
+        let mut properties = Map::new();
+        for (name, value) in values {
+            let kind = schema
+                .get(&name)
+                .and_then(|property| property["type"].as_str());
+            match config::property_value(kind, &value) {
+                Some(value) => {
+                    properties.insert(name, value);
+                }
+                None => self.ui.error(format!(
+                    "can't write configured value {} to column {} ({})",
+                    value,
+                    name,
+                    kind.unwrap_or("missing")
+                )),
+            }
+        }
+        properties
+    }
+    async fn add_repo(
+        &self,
+        stars: Vec<&RepoRef>,
+        discovered: &HashSet<String>,
+        deadline: Option<Instant>,
+        state: &mut State,
+        now: DateTime<Utc>,
+        summary: &mut Summary,
+    ) -> Vec<(String, (Option<NaiveDate>, Option<NaiveDate>))> {
+        let pb = self.ui.bar("creating", stars.len() as u64);
+        let mut added = Vec::new();
+        for star in stars {
+            if let Some(reason) = stop_reason(deadline) {
+                pb.abandon_with_message(reason);
+                return added;
+            }
+            pb.set_message(star.name.clone());
+            let latest = self.latest_release(star, None).await;
+            let activity = (
+                latest.as_ref().and_then(release_date),
+                self.latest_commit(star).await,
+            );
+            state.observe(
+                &full_name(star),
+                &[activity.0, activity.1],
+                now.date_naive(),
+            );
+            state.release_tag(
+                &full_name(star),
+                latest.as_ref().map(|release| release.tag_name.as_str()),
+            );
+            let created = self
+                ._add_repo(
+                    star,
+                    activity,
+                    latest.as_ref(),
+                    discovered.contains(&star.name),
+                    now,
+                )
+                .await;
+            match created {
+                Ok(created) => {
+                    if matches!(created, Created::Recovered(_)) {
+                        self.ui
+                            .println(format!("{} was created despite an error", star.name));
+                        summary.recovered.push(star.name.clone());
+                    }
+                    added.push((star.name.clone(), activity));
+                }
+                Err(err) => {
+                    let err = SyncError {
+                        operation: "create",
+                        repo: full_name(star),
+                        page_id: None,
+                        error: err,
+                    };
+                    self.ui.error(err.to_string());
+                    summary.errors.push(err);
+                }
+            }
+            pb.inc(1);
+        }
+        pb.finish_with_message("done");
+        added
+    }
+    /// Remembers that `repo` went private or was deleted, so it's only checked for coming back. It's
+    /// reported the first time, and the page gets the Unavailable status where there's a column.
+    async fn mark_unavailable(
+        &self,
+        page: &Page,
+        repo: String,
+        state: &mut State,
+        summary: &mut Summary,
+    ) {
+        if state.unavailable(&repo) {
+            return;
+        }
+        state.set_unavailable(&repo, true);
+        if self.schema().await.contains_key("Status") {
+            let update = PageUpdate {
+                status: Some(status::UNAVAILABLE),
+                ..Default::default()
+            };
+            if let Err(err) = self.update_page(page, &update).await {
+                let err = SyncError {
+                    operation: "mark unavailable",
+                    repo: repo.clone(),
+                    page_id: Some(page.id.to_string()),
+                    error: err,
+                };
+                self.ui.error(err.to_string());
+                summary.errors.push(err);
+            }
+        }
+        summary.unavailable.push(repo);
+    }
+    /// Returns the titles that were archived and the errors for the ones that weren't.
+    async fn archive_repo(
+        &self,
+        stars: Vec<&Page>,
+        deadline: Option<Instant>,
+    ) -> (Vec<String>, Vec<SyncError>) {
+        if !stars.is_empty() {
+            let path = backup::write_backup(&self.ui, &stars);
+            self.ui.println(format!(
+                "backup of archived pages written to {}",
+                path.display()
+            ));
+        }
+        let pb = self.ui.bar("archiving", stars.len() as u64);
+        let mut archived = Vec::new();
+        let mut errors = Vec::new();
+        for star in stars {
+            if let Some(reason) = stop_reason(deadline) {
+                pb.abandon_with_message(reason);
+                return (archived, errors);
+            }
+            pb.set_message(star.title().unwrap());
+            match self
+                .api
+                .update_page(&star.id.to_string(), archive_body())
+                .await
+            {
+                Ok(()) => {
+                    let page_id = star.id.to_string();
+                    audit::record(self, "archive", Some(&page_id), Some(star), &archive_body());
+                    archived.push(star.title().unwrap());
+                }
+                Err(err) => {
+                    let err = SyncError {
+                        operation: "archive",
+                        repo: star.title().unwrap(),
+                        page_id: Some(star.id.to_string()),
+                        error: err,
+                    };
+                    self.ui.error(err.to_string());
+                    errors.push(err);
+                }
+            }
+            pb.inc(1);
+        }
+        pb.finish_with_message("done");
+        (archived, errors)
+    }
+    async fn update_page(&self, page: &Page, update: &PageUpdate) -> Result<(), NotionError> {
+        let body = update.properties();
+        if body.is_empty() {
+            return Ok(());
+        }
+        self.patch_properties(page, "update", body.into()).await
+    }
+    /// Writes `properties` to the page, recorded in the audit log under `operation`.
+    async fn patch_properties(
+        &self,
+        page: &Page,
+        operation: &str,
+        properties: Value,
+    ) -> Result<(), NotionError> {
+        let properties = self.writable(properties).await;
+        if properties
+            .as_object()
+            .is_some_and(|properties| properties.is_empty())
+        {
+            return Ok(());
+        }
+        let body = json!({ "properties": properties });
+        self.api
+            .update_page(&page.id.to_string(), body.clone())
+            .await?;
+        let page_id = page.id.to_string();
+        audit::record(self, operation, Some(&page_id), Some(page), &body);
+        Ok(())
+    }
+    /// Drops values for columns the database computes itself, Notion rejects writes to those,
+    /// and adds any select options the values need.
+    async fn writable(&self, mut properties: Value) -> Value {
+        let schema = self.schema().await;
+        if let Some(properties) = properties.as_object_mut() {
+            properties.retain(|name, _| {
+                !schema
+                    .get(name)
+                    .and_then(|property| property["type"].as_str())
+                    .is_some_and(|kind| READ_ONLY_TYPES.contains(&kind))
+            });
+        }
+        self.add_options(schema, &properties).await;
+        properties
+    }
+    async fn template(&self) -> &[Value] {
+        self.template
+            .get_or_init(|| async {
+                let page_id = match template::page_id() {
+                    Some(page_id) => page_id,
+                    None => return Vec::new(),
+                };
+                match self.api.block_children(&page_id).await {
+                    Ok(blocks) => template::content(blocks),
+                    Err(err) => {
+                        self.ui
+                            .error(format!("failed to read the template page: {}", err));
+                        Vec::new()
+                    }
+                }
+            })
+            .await
+    }
+    async fn schema(&self) -> &HashMap<String, Value> {
+        self.schema
+            .get_or_init(|| async {
+                self.api.database_properties().await.unwrap_or_else(|err| {
+                    self.ui
+                        .error(format!("failed to read the database schema: {}", err));
+                    HashMap::new()
+                })
+            })
+            .await
+    }
+    /// Creates the select options `properties` uses that the database doesn't have yet, colored
+    /// by name so boards look the same whichever run or machine created them. Columns the
+    /// database doesn't have are never created here.
+    async fn add_options(&self, schema: &HashMap<String, Value>, properties: &Value) {
+        let mut update = Map::new();
+        {
+            let mut known = self.options.lock().unwrap();
+            for (name, value) in properties.as_object().into_iter().flatten() {
+                if !schema.contains_key(name) {
+                    continue;
+                }
+                let (kind, used) = match options::used(value) {
+                    Some(used) => used,
+                    None => continue,
+                };
+                let existing = known.entry(name.clone()).or_insert_with(|| {
+                    schema
+                        .get(name)
+                        .and_then(|property| property[kind]["options"].as_array())
+                        .cloned()
+                        .unwrap_or_default()
+                });
+                let mut added = false;
+                for option in used {
+                    if !existing.iter().any(|existing| existing["name"] == option) {
+                        existing.push(options::option(option));
+                        added = true;
+                    }
+                }
+                if added {
+                    // Notion takes the full option list, leaving one out would delete it
+                    update.insert(
+                        name.clone(),
+                        json!({ kind: { "options": existing.clone() } }),
+                    );
+                }
+            }
+        }
+        if update.is_empty() {
+            return;
+        }
+        if let Err(err) = self.api.update_database(update.into()).await {
+            self.ui
+                .error(format!("failed to add select options: {}", err));
+        }
+    }
+}
+
+/// `REQUEST_TIMEOUT` in seconds, default 30. Exits on anything else than a number.
+fn request_timeout(ui: &Ui) -> Duration {
+    let Ok(secs) = env::var("REQUEST_TIMEOUT") else {
+        return Duration::from_secs(30);
+    };
+    match secs.trim().parse() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            ui.error(format!(
+                "REQUEST_TIMEOUT must be a number of seconds, not {:?}",
+                secs
+            ));
+            std::process::exit(1);
+        }
+    }
+}
+
+fn stop_reason(deadline: Option<Instant>) -> Option<&'static str> {
+    if shutdown::requested() {
+        Some("interrupted")
+    } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        Some("deadline reached")
+    } else {
+        None
+    }
+}
+
+/// Date property value addressed by name only, for properties whose id we don't hard-code.
+fn date_value(date: NaiveDate) -> Value {
+    json!({ "date": { "start": date.to_string() } })
+}
+
+fn select_value(name: &str) -> Value {
+    json!({ "select": { "name": name } })
+}
+
+fn multi_select_value(names: &[String]) -> Value {
+    json!({ "multi_select": names.iter().map(|name| json!({ "name": name })).collect::<Vec<Value>>() })
+}
+
+/// A column's value, by its exact name or else ignoring ASCII case: the commit date is written as
+/// `上次Commit` but read as `上次commit`, and databases have either.
+fn property<'a>(page: &'a Page, name: &str) -> Option<&'a PropertyValue> {
+    let properties = &page.properties.properties;
+    properties.get(name).or_else(|| {
+        properties
+            .iter()
+            .find(|(column, _)| column.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    })
+}
+
+fn date_property(page: &Page, name: &str) -> Option<NaiveDate> {
+    match property(page, name) {
+        Some(PropertyValue::Date {
+            date: Some(date), ..
+        }) => match date.start {
+            DateOrDateTime::Date(date) => Some(date),
+            // set by hand with a time, only the day is compared
+            DateOrDateTime::DateTime(time) => Some(time.date_naive()),
+        },
+        _ => None,
+    }
+}
+
+/// The one form repo and gist URLs are written in: `https`, lowercase host without `www.`, no
+/// trailing slash or `.git`.
+fn canonical_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let (host, path) = url.split_once('/').unwrap_or((url, ""));
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    match path {
+        "" => format!("https://{}", host),
+        path => format!("https://{}/{}", host, path),
+    }
+}
+
+/// Whether two URLs are of the same repo or gist. GitHub doesn't mind the case of the path.
+fn same_url(a: &str, b: &str) -> bool {
+    canonical_url(a).eq_ignore_ascii_case(&canonical_url(b))
+}
+
+fn url_property(page: &Page, name: &str) -> Option<String> {
+    match property(page, name) {
+        Some(PropertyValue::Url { url, .. }) => url.clone(),
+        _ => None,
+    }
+}
+
+/// Unstarred pages keep their `Unstarred` status in the trash, so a restore shows why they left.
+fn archive_body() -> Value {
+    if status::enabled() {
+        json!({
+            "archived": true,
+            "properties": { "Status": status::property(status::UNSTARRED) },
+        })
+    } else {
+        json!({ "archived": true })
+    }
+}
+
+fn status_property(page: &Page, name: &str) -> Option<String> {
+    match property(page, name) {
+        Some(PropertyValue::Status {
+            status: Some(status),
+            ..
+        }) => status.name.clone(),
+        _ => None,
+    }
+}
+
+fn select_property(page: &Page, name: &str) -> Option<String> {
+    match property(page, name) {
+        Some(PropertyValue::Select {
+            select: Some(select),
+            ..
+        }) => select.name.clone(),
+        _ => None,
+    }
+}
+
+fn multi_select_property(page: &Page, name: &str) -> Option<Vec<String>> {
+    match property(page, name) {
+        Some(PropertyValue::MultiSelect { multi_select, .. }) => Some(
+            multi_select
+                .iter()
+                .flatten()
+                .filter_map(|option| option.name.clone())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Pages with a ticked `Keep` checkbox, or a `Keep` option in any select, are never archived.
+fn kept(page: &Page) -> bool {
+    page.properties
+        .properties
+        .iter()
+        .any(|(name, value)| match value {
+            PropertyValue::Checkbox { checkbox, .. } => *checkbox && name == "Keep",
+            PropertyValue::Select {
+                select: Some(select),
+                ..
+            } => select.name.as_deref() == Some("Keep"),
+            PropertyValue::MultiSelect {
+                multi_select: Some(options),
+                ..
+            } => options
+                .iter()
+                .any(|option| option.name.as_deref() == Some("Keep")),
+            _ => false,
+        })
+}
+
+/// Pages with a ticked `Sync off` checkbox, or `Sync` set to `off`, are left entirely alone.
+fn sync_off(page: &Page) -> bool {
+    match page.properties.properties.get("Sync off") {
+        Some(PropertyValue::Checkbox { checkbox, .. }) => *checkbox,
+        _ => matches!(
+            page.properties.properties.get("Sync"),
+            Some(PropertyValue::Select { select: Some(option), .. } | PropertyValue::Status { status: Some(option), .. })
+                if option.name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case("off"))
+        ),
+    }
+}
+
+/// Whether the page has nothing in a column, or doesn't have the column at all.
+fn blank_property(page: &Page, name: &str) -> bool {
+    let value = match property(page, name) {
+        Some(value) => json!(value),
+        None => return true,
+    };
+    match &value[value["type"].as_str().unwrap_or_default()] {
+        Value::Null => true,
+        Value::String(value) => value.is_empty(),
+        Value::Array(values) => values.is_empty(),
+        _ => false,
+    }
+}
+
+/// Rows the sync created link `release` to a repo, hand-made ones usually have no such URL.
+fn manual_row(page: &Page) -> bool {
+    let url = match url_property(page, "release") {
+        Some(url) => url,
+        None => return true,
+    };
+    match url.strip_prefix("https://").or(url.strip_prefix("http://")) {
+        // host, owner, name
+        Some(path) => path.split('/').filter(|part| !part.is_empty()).count() < 3,
+        None => true,
+    }
+}
+
+fn checkbox_property(page: &Page, name: &str) -> Option<bool> {
+    match property(page, name) {
+        Some(PropertyValue::Checkbox { checkbox, .. }) => Some(*checkbox),
+        _ => None,
+    }
+}
+
+fn number_property(page: &Page, name: &str) -> Option<u64> {
+    match property(page, name) {
+        Some(PropertyValue::Number {
+            number: Some(number),
+            ..
+        }) => number.as_u64().or_else(|| {
+            // whole numbers can come back as `12.0`
+            number
+                .as_f64()
+                .filter(|number| number.fract() == 0.0 && *number >= 0.0)
+                .map(|number| number as u64)
+        }),
+        _ => None,
+    }
+}
+
+fn text_property(page: &Page, name: &str) -> Option<String> {
+    match property(page, name) {
+        Some(PropertyValue::Text { rich_text, .. }) => {
+            Some(rich_text.iter().map(|t| t.plain_text()).collect())
+        }
+        _ => None,
+    }
+}
+
+/// Every option picked in the page's selects and multi-selects, what notification routes match on.
+fn page_tags(page: &Page) -> Vec<String> {
+    page.properties
+        .properties
+        .keys()
+        .flat_map(|name| {
+            select_property(page, name)
+                .into_iter()
+                .chain(multi_select_property(page, name).into_iter().flatten())
+        })
+        .collect()
+}
+
+fn event_tags(page: &Page, repo: &RepoRef, dependencies: &Dependencies) -> Vec<String> {
+    page_tags(page)
+        .into_iter()
+        .chain(repo_tags(repo, dependencies))
+        .collect()
+}
+
+/// The repo's topics and language, and `dependency` when it's in a configured lockfile.
+fn repo_tags(repo: &RepoRef, dependencies: &Dependencies) -> Vec<String> {
+    repo.topics
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .chain(repo.language.clone())
+        .chain(
+            dependencies
+                .contains(repo)
+                .then(|| "dependency".to_string()),
+        )
+        .collect()
+}
+
+/// Drafts and feed entries not dated yet have none.
+fn release_date(release: &ReleaseInfo) -> Option<NaiveDate> {
+    release.published_at.map(|date| date.naive_utc().date())
+}
+
+/// Muted until the page's `Mute until` date, or while its `Mute` box is ticked when there's none.
+fn muted_page(page: &Page, today: NaiveDate) -> bool {
+    match date_property(page, MUTE_UNTIL) {
+        Some(until) => today < until,
+        None => checkbox_property(page, MUTE) == Some(true),
+    }
+}
+
+/// `owner/name` of the repo a page was synced from.
+fn page_full_name(page: &Page) -> String {
+    format!(
+        "{}/{}",
+        text_property(page, "owner").unwrap_or_default(),
+        page.title().unwrap()
+    )
+}
+
+fn full_name(repo: &RepoRef) -> String {
+    format!("{}/{}", repo.owner, repo.name)
+}
+
+fn text(name: String) -> Vec<RichText> {
+    Vec::from([RichText::Text {
+        rich_text: RichTextCommon {
+            plain_text: name.to_owned(),
+            href: None,
+            annotations: Some(Annotations {
+                bold: Some(false),
+                code: Some(false),
+                color: Some(notion::models::text::TextColor::Default),
+                italic: Some(false),
+                strikethrough: Some(false),
+                underline: Some(false),
+            }),
+        },
+        text: Text {
+            content: name,
+            link: None,
+        },
+    }])
+}
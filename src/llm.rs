@@ -0,0 +1,79 @@
+use crate::{config::LlmConfig, hash, redact::redact, telemetry};
+use opentelemetry::KeyValue;
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, env, fs, sync::Mutex};
+
+fn cache_path() -> String {
+    env::var("LLM_CACHE").unwrap_or("notionstar-llm.json".to_string())
+}
+
+/// A chat completions endpoint, OpenAI's or anything speaking its API such as Ollama's
+/// `/v1`. Answers are kept in `LLM_CACHE` (default `notionstar-llm.json`) keyed by the model and
+/// prompt, so asking the same again costs nothing.
+pub struct Llm {
+    http: reqwest::Client,
+    url: String,
+    model: String,
+    /// `LLM_API_KEY`, local servers don't need one
+    key: Option<String>,
+    cache: Mutex<BTreeMap<String, String>>,
+}
+
+impl Llm {
+    pub fn new(config: &LlmConfig, http: &reqwest::Client) -> Llm {
+        let cache = fs::read_to_string(cache_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Llm {
+            http: http.clone(),
+            url: config.url.trim_end_matches('/').to_string(),
+            model: config.model.clone(),
+            key: env::var("LLM_API_KEY").ok(),
+            cache: Mutex::new(cache),
+        }
+    }
+
+    pub async fn ask(&self, instructions: &str, input: &str) -> Result<String, String> {
+        let key = format!(
+            "{:016x}",
+            hash::fnv1a(format!("{}\0{}\0{}", self.model, instructions, input).as_bytes())
+        );
+        if let Some(answer) = self.cache.lock().unwrap().get(&key) {
+            return Ok(answer.clone());
+        }
+        let mut request = self
+            .http
+            .post(format!("{}/chat/completions", self.url))
+            .json(&json!({
+                "model": self.model,
+                "messages": [
+                    { "role": "system", "content": instructions },
+                    { "role": "user", "content": input },
+                ],
+            }));
+        if let Some(token) = &self.key {
+            request = request.bearer_auth(token);
+        }
+        let attributes = vec![KeyValue::new("model", self.model.clone())];
+        let resp = telemetry::span("llm", attributes, request.send())
+            .await
+            .map_err(|err| redact(&err.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(format!("returned {}", resp.status()));
+        }
+        let body = resp.json::<Value>().await.map_err(|err| err.to_string())?;
+        let answer = body["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or("no answer in the response")?
+            .trim()
+            .to_string();
+        let mut cache = self.cache.lock().unwrap();
+        cache.insert(key, answer.clone());
+        // a cache that can't be written only costs another request next time
+        if let Ok(content) = serde_json::to_string_pretty(&*cache) {
+            fs::write(cache_path(), content).ok();
+        }
+        Ok(answer)
+    }
+}
@@ -1,392 +1,348 @@
+use clap::{CommandFactory, Parser, Subcommand};
 use dotenv::dotenv;
-use indicatif::ProgressBar;
-use notion::{
-    chrono::NaiveDate,
-    ids::PropertyId,
-    models::{
-        paging::{Pageable, PagingCursor},
-        properties::{DateOrDateTime, DateValue, PropertyValue},
-        text::{Annotations, RichText, RichTextCommon, Text},
-        Page, PageCreateRequest, Parent, Properties,
-    },
-    NotionApi,
-};
-use octocrab::{
-    self,
-    models::{repos::Release, Repository},
+use notionstar::{
+    actions, backup, bench, daemon, export, export::ExportFormat, gists, oauth, redact, repair,
+    report, secrets, selfupdate, serve, service, shutdown, sqlite, sync, telemetry, tui, ui::Ui,
+    undo, verify, Notion, SyncArgs,
 };
-use reqwest;
-use serde_json;
-use std::{collections::HashMap, str::FromStr};
-use std::{collections::HashSet, env};
-use tokio;
-
-#[tokio::main]
-async fn main() {
-    dotenv().ok();
-    let notion = Notion::new().await;
-    let database = notion.get_database().await;
-    let stars = notion.get_stars().await;
-    let star_map: HashMap<String, Repository> = stars
-        .iter()
-        .map(|star| (star.name.clone(), star.clone()))
-        .collect();
-    let star_index = star_map.keys().collect::<HashSet<&String>>();
-    let database_index = database
-        .iter()
-        .map(|page| page.title().unwrap())
-        .collect::<HashSet<String>>();
-    let update_stars = stars
-        .iter()
-        .filter(|star| !database_index.contains(&star.name))
-        .collect::<Vec<&Repository>>();
-    println!(
-        "update_stars: {:?}",
-        update_stars
-            .iter()
-            .map(|page| page.name.clone())
-            .collect::<Vec<String>>()
-    );
-
-    notion.add_repo(update_stars).await;
-    let delete_stars = database
-        .iter()
-        .filter(|page| !star_index.contains(&page.title().unwrap()))
-        .collect::<Vec<&Page>>();
-
-    println!(
-        "delete_stars: {:?}",
-        delete_stars
-            .iter()
-            .map(|page| page.title().unwrap())
-            .collect::<Vec<String>>()
-    );
+use std::{env, fs, io::stdout, path::PathBuf, sync::Arc};
 
-    notion.archive_repo(delete_stars).await;
-
-    let new_database = notion.get_database().await;
-    println!("updating database");
-    let pb = ProgressBar::new(stars.len() as u64);
-    println!("Starting add repo");
-    pb.set_style(
-        indicatif::ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-            .unwrap(),
-    );
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// No progress bars or info output, only the final summary and errors (for cron logs)
+    #[arg(long, short, global = true)]
+    quiet: bool,
+    /// Save every GitHub/Notion API response under DIR for later `--replay`
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Answer API reads from fixtures saved by `--record` instead of the network; writes are skipped
+    #[arg(long, global = true, value_name = "DIR")]
+    replay: Option<PathBuf>,
+    #[command(flatten)]
+    sync: SyncArgs,
+}
 
-    for page in new_database {
-        let name = page.title().unwrap();
-        pb.set_message("updating ".to_string() + &name);
-        let repo = star_map.get(&name).unwrap();
-        let lastupdate = match notion
-            .get_release(&repo.to_owned().owner.unwrap().login, &name)
-            .await
-        {
-            Ok(release) => Some(release.published_at.unwrap().naive_utc().date()),
-            Err(_) => None,
-        };
-        let notion_last_update = match page.properties.properties.get("上次release").unwrap() {
-            PropertyValue::Date { id: _, date } => match date {
-                Some(date) => match date.start {
-                    DateOrDateTime::Date(date) => Some(date),
-                    _ => None,
-                },
-                None => None,
-            },
-            _ => None,
-        };
-        let release_date = if lastupdate != notion_last_update {
-            lastupdate
-        } else {
-            None
-        };
-        let commit = match notion
-            .github
-            .repos(repo.to_owned().owner.unwrap().login, &name)
-            .list_commits()
-            .send()
-            .await
-        {
-            Ok(commits) => match commits.items.first() {
-                Some(commit) => match commit.commit.committer.to_owned().unwrap().date {
-                    Some(date) => Some(date.naive_utc().date()),
-                    None => None,
-                },
-                None => None,
-            },
-            Err(_) => None,
-        };
-        let notion_commit = match page.properties.properties.get("上次commit") {
-            Some(date) => match date {
-                PropertyValue::Date { id: _, date } => match date {
-                    Some(date) => match date.start {
-                        DateOrDateTime::Date(date) => Some(date),
-                        _ => None,
-                    },
-                    None => None,
-                },
-                _ => None,
-            },
-            None => None,
-        };
-        let commit_date = if commit != notion_commit {
-            commit
-        } else {
-            None
-        };
-        if release_date.is_none() && commit_date.is_none() {
-            pb.inc(1);
-            continue;
-        } else {
-            println!(
-                "\nrelease: {:?}->{:?}, commit: {:?}->{:?}\n",
-                notion_last_update, release_date, notion_commit, commit_date
-            );
-        }
-        notion
-            .update_date(&page.id.to_string(), &release_date, &commit_date)
-            .await;
-        pb.inc(1);
-    }
-    pb.finish_and_clear();
+#[derive(Subcommand)]
+enum Command {
+    /// Sync GitHub stars into the Notion database (default)
+    Sync(SyncArgs),
+    /// Mirror starred gists into a second database, `GISTS_DATABASE`
+    Gists,
+    /// Keep running, syncing on the `[daemon]` schedules of the config file
+    Daemon {
+        /// Answer `/healthz` and `/readyz` on this address, e.g. `0.0.0.0:8080`
+        #[arg(long)]
+        health: Option<String>,
+    },
+    /// Install or remove `daemon` as a service of this user's session (systemd, launchd or Task Scheduler)
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    /// Connect to a Notion workspace through the public integration's OAuth page and save the token to `.env`
+    Login {
+        /// Port of the `http://localhost:<port>/callback` redirect URI registered for the integration
+        #[arg(long, default_value_t = oauth::DEFAULT_PORT)]
+        port: u16,
+    },
+    /// Review the plan of a full sync in a table, pick the rows to apply and watch it run
+    Tui,
+    /// Compare GitHub stars against Notion and print a drift report without writing anything
+    Verify,
+    /// Backfill pages missing required properties from GitHub and normalize their URLs
+    Repair,
+    /// Unarchive (or recreate) the pages recorded in an archive backup
+    Restore { backup: PathBuf },
+    /// Revert the writes of a run recorded in the `audit_log`
+    Undo {
+        /// The run's id, shown in its summary
+        #[arg(long)]
+        run: String,
+    },
+    /// Dump every synced row and its property values to a file
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Render the database to a static HTML page to share with people without Notion access
+    Report {
+        /// Directory `index.html` is written to
+        #[arg(long, value_name = "DIR")]
+        html: PathBuf,
+    },
+    /// Time full syncs against fixtures saved with `--record`, without the network
+    Bench {
+        fixtures: PathBuf,
+        /// How many syncs to time
+        #[arg(long, default_value_t = 10)]
+        runs: usize,
+    },
+    /// Run SQL against a local database written by `--target sqlite`
+    Query {
+        sql: String,
+        #[arg(long, default_value = "notionstar.db")]
+        db: PathBuf,
+    },
+    /// Print a shell completion script, e.g. `notionstar completions bash > /etc/bash_completion.d/notionstar`
+    Completions { shell: clap_complete::Shell },
+    /// Listen for GitHub `release`/`push` webhooks and refresh the repo's page as they arrive
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+    },
+    /// Download the latest release binary for this platform and replace the running executable
+    SelfUpdate,
+    /// Write man pages for notionstar and its subcommands into a directory
+    #[command(hide = true)]
+    Mangen { out_dir: PathBuf },
 }
 
-struct Notion {
-    api: NotionApi,
-    database_id: notion::ids::DatabaseId,
-    github: octocrab::Octocrab,
-    token: String,
+#[derive(Subcommand)]
+enum ServiceAction {
+    /// Start `daemon` in the current directory with the session, now and after every login
+    Install,
+    /// Stop the service and remove it
+    Uninstall,
 }
-impl Notion {
-    async fn new() -> Notion {
-        let token = env::var("NOTION_API").unwrap();
-        Notion {
-            api: NotionApi::new(token.clone()).unwrap(),
-            database_id: notion::ids::DatabaseId::from_str(env::var("DATABASE").unwrap().as_str())
-                .unwrap(),
-            github: octocrab::Octocrab::builder()
-                .personal_token(env::var("GITHUB_API").unwrap())
-                .build()
-                .unwrap(),
-            token: token,
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    redact::install_panic_hook();
+    let cli = Cli::parse();
+    if let Err(err) = secrets::load_files() {
+        Ui::new(cli.quiet).error(err);
+        std::process::exit(1);
+    }
+    let quiet = cli.quiet;
+    let traced = telemetry::enabled();
+    if traced {
+        if let Err(err) = telemetry::init() {
+            Ui::new(quiet).error(format!("failed to set up tracing: {}", err));
         }
     }
-    async fn get_stars(&self) -> Vec<octocrab::models::Repository> {
-        let mut stars = Vec::new();
-        let mut page = 1;
-        loop {
-            let star_page = self
-                .github
-                .current()
-                .list_repos_starred_by_authenticated_user()
-                .per_page(100)
-                .page(page)
-                .send()
-                .await
-                .unwrap()
-                .items;
-
-            if (&star_page).is_empty() {
-                break;
+    let connect = Connect {
+        quiet,
+        record: cli.record,
+        replay: cli.replay,
+        traced,
+    };
+    let command = cli.command.unwrap_or(Command::Sync(cli.sync));
+    if !quiet
+        && connect.replay.is_none()
+        && matches!(
+            command,
+            Command::Sync(_)
+                | Command::Tui
+                | Command::Gists
+                | Command::Verify
+                | Command::Repair
+                | Command::Export { .. }
+                | Command::Report { .. }
+                | Command::Restore { .. }
+                | Command::Undo { .. }
+        )
+    {
+        selfupdate::notify_if_outdated().await;
+    }
+    match command {
+        Command::Sync(args) => {
+            let assert_empty_plan = args.assert_empty_plan;
+            let notion = Arc::new(connect.notion().await);
+            shutdown::listen();
+            // run on its own task so a panic (expired token, rate limit) can still be reported
+            let run = tokio::spawn({
+                let notion = notion.clone();
+                async move { sync(&notion, &args).await }
+            })
+            .await;
+            // flushed before the exits and the panic below, which skip the end of main
+            telemetry::shutdown().await;
+            let summary = match run {
+                Ok(summary) => summary,
+                Err(err) => {
+                    let panic = err.into_panic();
+                    let message = panic
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| panic.downcast_ref::<String>().cloned())
+                        .unwrap_or_default();
+                    notion
+                        .report_failure(format!(
+                            "notionstar sync failed: {}",
+                            redact::redact(&message)
+                        ))
+                        .await;
+                    std::panic::resume_unwind(panic);
+                }
+            };
+            if actions::enabled() {
+                actions::report(&summary);
+            }
+            if summary.interrupted {
+                std::process::exit(shutdown::EXIT_INTERRUPTED);
             }
-            stars.extend(star_page);
-            page += 1;
-            println!("stars count {}", stars.len());
+            if assert_empty_plan && !summary.second_plan.is_empty() {
+                notion
+                    .ui
+                    .error("the plan after applying isn't empty, see the summary");
+                std::process::exit(1);
+            }
+            if actions::enabled() {
+                std::process::exit(actions::exit_code(&summary));
+            }
+            return;
         }
-        println!("stars getting finished");
-        return stars;
-    }
-    async fn get_database(&self) -> Vec<notion::models::Page> {
-        println!("database getting started");
-        let mut results = Vec::new();
-        let mut next_cursor: Option<PagingCursor> = None;
-        loop {
-            let dequery = notion::models::search::DatabaseQuery::default();
-            let query = if next_cursor.is_some() {
-                dequery.start_from(next_cursor.to_owned())
-            } else {
-                dequery
+        Command::Export { format, output } => {
+            let notion = connect.notion().await;
+            let database = notion.get_database().await;
+            export::export(&database, format, &output);
+            notion.ui.println(format!(
+                "exported {} rows to {}",
+                database.len(),
+                output.display()
+            ));
+        }
+        Command::Report { html } => {
+            let notion = connect.notion().await;
+            let database = notion.get_database().await;
+            let today = notion::chrono::Utc::now().date_naive();
+            if let Err(err) = report::html(&database, &html, today) {
+                notion.ui.error(format!(
+                    "failed to write report to {}: {}",
+                    html.display(),
+                    err
+                ));
+                std::process::exit(1);
+            }
+            notion.ui.println(format!(
+                "wrote a report of {} repos to {}",
+                database.len(),
+                html.join("index.html").display()
+            ));
+        }
+        Command::Gists => {
+            let Ok(database) = env::var("GISTS_DATABASE") else {
+                Ui::new(quiet).error("GISTS_DATABASE is not set");
+                std::process::exit(1);
             };
-            let database = self
-                .api
-                .query_database(&self.database_id, query)
-                .await
-                .unwrap();
-            results.extend(database.results);
-            println!("database count {}", results.len());
-            if database.next_cursor.is_none() {
-                break;
-            } else {
-                next_cursor = database.next_cursor
+            gists::sync(&connect.notion_for(database).await).await
+        }
+        Command::Daemon { health } => {
+            let notion = Arc::new(connect.notion().await);
+            if let Some(health) = health {
+                let listener = bind(&notion, &health).await;
+                let notion = notion.clone();
+                tokio::spawn(async move { serve::health(&notion, listener).await });
             }
+            daemon::daemon(notion).await
         }
-
-        return results;
-    }
-
-    async fn _add_repo(&self, stars: Repository) -> Page {
-        let owner = stars.owner.unwrap().login;
-        let name = stars.name;
-        return self
-            .new_data(
-                name.to_owned(),
-                stars.html_url.unwrap().to_string(),
-                owner.to_owned(),
-            )
-            .await;
-    }
-
-    async fn new_data(&self, name: String, release: String, owner: String) -> Page {
-        let properties = Properties {
-            properties: HashMap::from([
-                (
-                    "名称".to_string(),
-                    PropertyValue::Title {
-                        id: PropertyId::from_str("title").unwrap(),
-                        title: text(name),
-                    },
-                ),
-                (
-                    "release".to_owned(),
-                    PropertyValue::Url {
-                        id: PropertyId::from_str("pr%7Cj").unwrap(),
-                        url: Some(release),
-                    },
-                ),
-                (
-                    "owner".to_owned(),
-                    PropertyValue::Text {
-                        id: PropertyId::from_str("OHG%3B").unwrap(),
-                        rich_text: text(owner),
-                    },
-                ),
-            ]),
-        };
-
-        return self
-            .api
-            .create_page(PageCreateRequest {
-                parent: Parent::Database {
-                    database_id: self.database_id.to_owned(),
-                },
-                properties: properties,
-            })
-            .await
-            .unwrap();
-    }
-
-    async fn get_release(&self, owner: &String, name: &String) -> Result<Release, octocrab::Error> {
-        return self.github.repos(owner, name).releases().get_latest().await;
-    }
-    async fn add_repo(&self, stars: Vec<&Repository>) {
-        let pb = ProgressBar::new(stars.len() as u64);
-        println!("Starting add repo");
-        pb.set_style(
-            indicatif::ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-                .unwrap(),
-        );
-        for star in stars {
-            pb.set_message("adding ".to_string() + &star.name);
-            self._add_repo(star.to_owned()).await;
-            pb.inc(1);
+        Command::Service { action } => {
+            let ui = Ui::new(quiet);
+            match action {
+                ServiceAction::Install => service::install(&ui),
+                ServiceAction::Uninstall => service::uninstall(&ui),
+            }
         }
-        pb.finish_and_clear();
-    }
-    async fn archive_repo(&self, stars: Vec<&Page>) {
-        let pb = ProgressBar::new(stars.len() as u64);
-        println!("Starting archive repo");
-        pb.set_style(
-            indicatif::ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-                .unwrap(),
-        );
-        let session = reqwest::Client::new();
-        for star in stars {
-            pb.set_message("archive ".to_string() + &star.title().unwrap());
-            let resp = session
-                .patch("https://api.notion.com/v1/pages/".to_owned() + &star.id.to_string())
-                .header("Authorization", format!("Bearer {}", self.token))
-                .header("Notion-Version", "2022-06-28")
-                .json(&HashMap::from([("archived", true)]))
-                .send()
-                .await
-                .unwrap();
-            if !resp.status().is_success() {
-                println!("{}", resp.text().await.unwrap());
+        Command::Login { port } => {
+            let ui = Ui::new(quiet);
+            if let Err(err) = oauth::login(&ui, port).await {
+                ui.error(format!("login failed: {}", err));
+                std::process::exit(1);
             }
-            pb.inc(1);
         }
-        pb.finish_and_clear()
-    }
-    async fn update_date(
-        &self,
-        page_id: &String,
-        release: &Option<NaiveDate>,
-        commit: &Option<NaiveDate>,
-    ) {
-        let session = reqwest::Client::new();
-        let mut body = HashMap::new();
-        if release.is_some() {
-            body.insert(
-                "上次release",
-                PropertyValue::Date {
-                    id: PropertyId::from_str("pkvi").unwrap(),
-                    date: Some(DateValue {
-                        start: DateOrDateTime::Date(release.unwrap()),
-                        end: None,
-                        time_zone: None,
-                    }),
-                },
-            );
+        Command::Tui => {
+            let notion = connect.open(Ui::captured(), None).await;
+            if let Err(err) = tui::tui(notion).await {
+                Ui::new(quiet).error(format!("terminal error: {}", err));
+                std::process::exit(1);
+            }
         }
-        if commit.is_some() {
-            body.insert(
-                "上次Commit",
-                PropertyValue::Date {
-                    id: PropertyId::from_str("%7B%3Ddw").unwrap(),
-                    date: Some(DateValue {
-                        start: DateOrDateTime::Date(commit.unwrap()),
-                        end: None,
-                        time_zone: None,
-                    }),
-                },
-            );
+        Command::Verify => verify::verify(&connect.notion().await).await,
+        Command::Repair => repair::repair(&connect.notion().await).await,
+        Command::Restore { backup } => backup::restore(&connect.notion().await, &backup).await,
+        Command::Undo { run } => undo::undo(&connect.notion().await, &run).await,
+        Command::Bench { fixtures, runs } => println!("{}", bench::bench(&fixtures, runs).await),
+        Command::Query { sql, db } => sqlite::query(&db, &sql),
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "notionstar", &mut stdout())
         }
-        if body.is_empty() {
-            return;
+        Command::Serve { listen } => {
+            let notion = connect.notion().await;
+            let listener = bind(&notion, &listen).await;
+            notion
+                .ui
+                .println(format!("listening for webhooks on {}", listen));
+            shutdown::listen();
+            serve::serve(&notion, listener).await;
         }
-        let resp = session
-            .patch("https://api.notion.com/v1/pages/".to_owned() + page_id)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("Notion-Version", "2022-06-28")
-            .json(&serde_json::json!({ "properties": body }))
-            .send()
-            .await
-            .unwrap();
-        if !resp.status().is_success() {
-            println!("{}", resp.text().await.unwrap());
+        Command::SelfUpdate => {
+            let ui = Ui::new(quiet);
+            if let Err(err) = selfupdate::self_update(&ui).await {
+                ui.error(format!("self-update failed: {}", err));
+                std::process::exit(1);
+            }
+        }
+        Command::Mangen { out_dir } => {
+            fs::create_dir_all(&out_dir).unwrap();
+            clap_mangen::generate_to(Cli::command(), &out_dir).unwrap();
         }
     }
+    telemetry::shutdown().await;
 }
 
-fn text(name: String) -> Vec<RichText> {
-    Vec::from([RichText::Text {
-        rich_text: RichTextCommon {
-            plain_text: name.to_owned(),
-            href: None,
-            annotations: Some(Annotations {
-                bold: Some(false),
-                code: Some(false),
-                color: Some(notion::models::text::TextColor::Default),
-                italic: Some(false),
-                strikethrough: Some(false),
-                underline: Some(false),
-            }),
-        },
-        text: Text {
-            content: name,
-            link: None,
-        },
-    }])
+async fn bind(notion: &Notion, address: &str) -> tokio::net::TcpListener {
+    tokio::net::TcpListener::bind(address)
+        .await
+        .unwrap_or_else(|err| {
+            notion
+                .ui
+                .error(format!("failed to listen on {}: {}", address, err));
+            std::process::exit(1);
+        })
+}
+
+/// Builds the client the way the global `--record`/`--replay` flags ask for.
+struct Connect {
+    quiet: bool,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    /// put every API call in a span, see `telemetry`
+    traced: bool,
+}
+
+impl Connect {
+    async fn notion(&self) -> Notion {
+        self.open(Ui::new(self.quiet), None).await
+    }
+
+    async fn notion_for(&self, database: String) -> Notion {
+        self.open(Ui::new(self.quiet), Some(database)).await
+    }
+
+    /// Opens `database`, or `DATABASE` when that's `None`.
+    async fn open(&self, ui: Ui, database: Option<String>) -> Notion {
+        let notion = match &self.replay {
+            Some(dir) => Notion::replay(dir, ui),
+            None => {
+                let notion = match database {
+                    Some(database) => Notion::with_database(ui, database).await,
+                    None => Notion::new(ui).await,
+                };
+                match &self.record {
+                    Some(dir) => notion.record(dir),
+                    None => notion,
+                }
+            }
+        };
+        match self.traced {
+            true => notion.traced(),
+            false => notion,
+        }
+    }
 }
@@ -0,0 +1,37 @@
+use crate::state::env_number;
+use regex::Regex;
+
+/// Longest text Notion takes in one rich text object.
+const MAX_LEN: usize = 2000;
+
+/// The first `CHANGELOG_LINES` (default 3) non-empty lines of release notes as plain text, with
+/// Markdown headings, emphasis, code ticks, links and HTML comments reduced to their text.
+pub fn excerpt(body: &str) -> String {
+    let comments = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    let links = Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap();
+    let body = comments.replace_all(body, "");
+    let body = links.replace_all(&body, "$1");
+    let lines = body
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches('#')
+                .replace("**", "")
+                .replace('`', "")
+                .trim()
+                .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .take(env_number("CHANGELOG_LINES", 3).max(1) as usize)
+        .collect::<Vec<String>>()
+        .join("\n");
+    clip(lines)
+}
+
+/// Cut to what fits in one rich text object.
+pub fn clip(text: String) -> String {
+    if text.chars().count() <= MAX_LEN {
+        return text;
+    }
+    text.chars().take(MAX_LEN - 1).collect::<String>() + "…"
+}
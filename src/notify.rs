@@ -0,0 +1,558 @@
+use crate::{
+    config::{NotifierConfig, Route},
+    redact::redact,
+    state::State,
+    telemetry, Notion,
+};
+use async_trait::async_trait;
+use notion::chrono::{DateTime, Utc};
+use opentelemetry::KeyValue;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{stdout, IsTerminal, Write},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+/// Discord rejects messages longer than this.
+const DISCORD_LIMIT: usize = 2000;
+/// Same for Telegram.
+const TELEGRAM_LIMIT: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    /// a new star got a page
+    Added,
+    /// an unstarred repo's page was archived
+    Archived,
+    /// a newer release than the page had
+    Release,
+    /// a new release whose tag or notes match the security pattern, on top of its `release` event
+    Security,
+    /// the run hit errors or stopped, sent right away even on routes with `every`
+    Failure,
+    /// a lot more stars than the last run saw
+    #[serde(rename = "star_jump")]
+    StarJump,
+    /// the repo was archived on GitHub since the last run
+    #[serde(rename = "upstream_archived")]
+    UpstreamArchived,
+    /// counts standing in for a batch longer than its route's `batch_limit`
+    Summary,
+}
+
+impl EventKind {
+    /// What a group of events of this kind is headed with in a message.
+    fn heading(self) -> &'static str {
+        match self {
+            EventKind::Added => "new stars",
+            EventKind::Archived => "archived",
+            EventKind::Release => "releases",
+            EventKind::Security => "security releases",
+            EventKind::Failure => "failures",
+            EventKind::StarJump => "star jumps",
+            EventKind::UpstreamArchived => "archived upstream",
+            EventKind::Summary => "summaries",
+        }
+    }
+}
+
+/// Something a run did that someone may want to hear about.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub kind: EventKind,
+    /// `owner/name`
+    pub repo: String,
+    /// the page's select and multi-select options, or the repo's topics and language
+    pub tags: Vec<String>,
+    pub message: String,
+}
+
+/// Events routed to one notifier but sent together later, see `every` on routes.
+#[derive(Serialize, Deserialize)]
+pub struct Digest {
+    pub started: DateTime<Utc>,
+    pub events: Vec<Event>,
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, events: &[Event]) -> Result<(), String>;
+}
+
+/// The kinds of the events, in the order they first come up.
+fn kinds(events: &[Event]) -> Vec<EventKind> {
+    let mut kinds = Vec::new();
+    for event in events {
+        if !kinds.contains(&event.kind) {
+            kinds.push(event.kind);
+        }
+    }
+    kinds
+}
+
+/// Events as one message, one line each, grouped by kind.
+fn text(events: &[Event]) -> String {
+    if let [event] = events {
+        return event.message.clone();
+    }
+    let mut text = format!("notionstar: {} updates", events.len());
+    for kind in kinds(events) {
+        let lines = events
+            .iter()
+            .filter(|event| event.kind == kind)
+            .map(|event| event.message.as_str())
+            .collect::<Vec<&str>>();
+        text.push_str(&format!(
+            "\n\n{} ({})\n{}",
+            kind.heading(),
+            lines.len(),
+            lines.join("\n")
+        ));
+    }
+    text
+}
+
+/// One event with how many of each kind there were, for batches too long to send whole.
+fn rollup(events: &[Event], link: Option<&str>) -> Event {
+    let counts = kinds(events)
+        .into_iter()
+        .map(|kind| {
+            let count = events.iter().filter(|event| event.kind == kind).count();
+            format!("{} {}", count, kind.heading())
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    let mut message = format!("notionstar: {} updates ({})", events.len(), counts);
+    if let Some(link) = link {
+        message = format!("{}, see {}", message, link);
+    }
+    Event {
+        kind: EventKind::Summary,
+        repo: String::new(),
+        tags: Vec::new(),
+        message,
+    }
+}
+
+fn truncate(text: String, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text;
+    }
+    text.chars().take(limit - 1).collect::<String>() + "…"
+}
+
+async fn post(http: &reqwest::Client, url: &str, body: serde_json::Value) -> Result<(), String> {
+    deliver(http.post(url).json(&body)).await
+}
+
+async fn deliver(request: reqwest::RequestBuilder) -> Result<(), String> {
+    match request.send().await {
+        Ok(resp) if resp.status().is_success() => Ok(()),
+        Ok(resp) => Err(format!("returned {}", resp.status())),
+        Err(err) => Err(redact(&err.to_string())),
+    }
+}
+
+pub struct Stdout;
+
+#[async_trait]
+impl Notifier for Stdout {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        println!("{}", text(events));
+        Ok(())
+    }
+}
+
+pub struct Discord {
+    http: reqwest::Client,
+    url: String,
+}
+
+#[async_trait]
+impl Notifier for Discord {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        let content = truncate(text(events), DISCORD_LIMIT);
+        post(&self.http, &self.url, json!({ "content": content })).await
+    }
+}
+
+pub struct Slack {
+    http: reqwest::Client,
+    url: String,
+}
+
+#[async_trait]
+impl Notifier for Slack {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        post(&self.http, &self.url, json!({ "text": text(events) })).await
+    }
+}
+
+/// Posts the events themselves as JSON, for anything that wants to process them.
+pub struct Webhook {
+    http: reqwest::Client,
+    url: String,
+}
+
+#[async_trait]
+impl Notifier for Webhook {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        post(&self.http, &self.url, json!({ "events": events })).await
+    }
+}
+
+/// Posts into a room as the user the access token belongs to.
+pub struct Matrix {
+    http: reqwest::Client,
+    homeserver: String,
+    token: String,
+    /// the room id (`!abc:example.org`), not an alias
+    room: String,
+}
+
+#[async_trait]
+impl Notifier for Matrix {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        let room = self
+            .room
+            .replace('!', "%21")
+            .replace(':', "%3A")
+            .replace('#', "%23");
+        // resending with the same transaction id would be deduplicated by the server
+        let txn = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let url = format!(
+            "{}/_matrix/client/v3/rooms/{}/send/m.room.message/notionstar-{}",
+            self.homeserver.trim_end_matches('/'),
+            room,
+            txn
+        );
+        let body = json!({ "msgtype": "m.text", "body": text(events) });
+        deliver(self.http.put(url).bearer_auth(&self.token).json(&body)).await
+    }
+}
+
+/// Publishes to an ntfy topic, which phones subscribed to it show as push notifications.
+pub struct Ntfy {
+    http: reqwest::Client,
+    server: String,
+    topic: String,
+    /// for topics with access control
+    token: Option<String>,
+}
+
+#[async_trait]
+impl Notifier for Ntfy {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        let url = format!("{}/{}", self.server.trim_end_matches('/'), self.topic);
+        let mut request = self.http.post(url).body(text(events));
+        if let [event] = events {
+            // tapping the notification opens the repo
+            request = request
+                .header("Title", &event.repo)
+                .header("Click", format!("https://github.com/{}", event.repo));
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        deliver(request).await
+    }
+}
+
+/// Messages a chat through a bot, which also takes commands in `serve` mode.
+pub struct Telegram {
+    http: reqwest::Client,
+    api_url: String,
+    token: String,
+    chat: String,
+}
+
+impl Telegram {
+    pub async fn say(&self, text: String) -> Result<(), String> {
+        let url = format!(
+            "{}/bot{}/sendMessage",
+            self.api_url.trim_end_matches('/'),
+            self.token
+        );
+        let body = json!({ "chat_id": self.chat, "text": truncate(text, TELEGRAM_LIMIT) });
+        // the token is part of the URL, keep it out of error messages
+        post(&self.http, &url, body)
+            .await
+            .map_err(|err| err.replace(&self.token, "***"))
+    }
+
+    /// Updates sent to the bot from `offset` on, waiting up to `wait` for one to come in. Asking
+    /// with an offset past an update confirms it, Telegram doesn't send it again.
+    pub async fn updates(&self, offset: i64, wait: Duration) -> Result<Vec<Value>, String> {
+        let url = format!(
+            "{}/bot{}/getUpdates",
+            self.api_url.trim_end_matches('/'),
+            self.token
+        );
+        let hide = |err: reqwest::Error| redact(&err.to_string()).replace(&self.token, "***");
+        let resp = self
+            .http
+            .get(url)
+            .query(&[
+                ("offset", offset.to_string()),
+                ("timeout", wait.as_secs().to_string()),
+            ])
+            // the client's own timeout can be shorter than the wait
+            .timeout(wait + Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(hide)?;
+        if !resp.status().is_success() {
+            return Err(format!("returned {}", resp.status()));
+        }
+        let mut body = resp.json::<Value>().await.map_err(hide)?;
+        Ok(serde_json::from_value(body["result"].take()).unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl Notifier for Telegram {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        self.say(text(events)).await
+    }
+}
+
+/// The bot a `telegram` notifier talks through.
+pub fn telegram(config: &NotifierConfig, http: &reqwest::Client) -> Option<Telegram> {
+    match config {
+        NotifierConfig::Telegram {
+            token,
+            chat,
+            api_url,
+            ..
+        } => Some(Telegram {
+            http: http.clone(),
+            api_url: api_url
+                .clone()
+                .unwrap_or("https://api.telegram.org".to_string()),
+            token: token.clone(),
+            chat: chat.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// A native notification on this machine. Only shown when run from a terminal, a cron job on the
+/// same laptop has no one watching.
+pub struct Desktop;
+
+#[async_trait]
+impl Notifier for Desktop {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        if !stdout().is_terminal() {
+            return Ok(());
+        }
+        let summary = match events {
+            [event] => event.repo.clone(),
+            _ => format!("notionstar: {} updates", events.len()),
+        };
+        let body = events
+            .iter()
+            .map(|event| event.message.clone())
+            .collect::<Vec<String>>()
+            .join("\n");
+        // showing blocks on D-Bus on Linux
+        tokio::task::spawn_blocking(move || {
+            notify_rust::Notification::new()
+                .appname("notionstar")
+                .summary(&summary)
+                .body(&body)
+                .show()
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .await
+        .map_err(|err| err.to_string())?
+    }
+}
+
+/// Mails through the local `sendmail`, so there's no SMTP setup here.
+pub struct Email {
+    to: String,
+}
+
+#[async_trait]
+impl Notifier for Email {
+    async fn send(&self, events: &[Event]) -> Result<(), String> {
+        let subject = match events {
+            [event] => event.message.clone(),
+            _ => format!("notionstar: {} updates", events.len()),
+        };
+        let message = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            self.to,
+            subject,
+            text(events)
+        );
+        let mut sendmail = Command::new("sendmail")
+            .arg("-t")
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("failed to run sendmail: {}", err))?;
+        sendmail
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(message.as_bytes())
+            .map_err(|err| err.to_string())?;
+        match sendmail.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("sendmail exited with {}", status)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+fn notifier(config: &NotifierConfig, http: &reqwest::Client) -> Box<dyn Notifier> {
+    let http = http.clone();
+    match config {
+        NotifierConfig::Stdout => Box::new(Stdout),
+        NotifierConfig::Desktop => Box::new(Desktop),
+        NotifierConfig::Discord { url } => Box::new(Discord {
+            http,
+            url: url.clone(),
+        }),
+        NotifierConfig::Slack { url } => Box::new(Slack {
+            http,
+            url: url.clone(),
+        }),
+        NotifierConfig::Webhook { url } => Box::new(Webhook {
+            http,
+            url: url.clone(),
+        }),
+        NotifierConfig::Matrix {
+            homeserver,
+            token,
+            room,
+        } => Box::new(Matrix {
+            http,
+            homeserver: homeserver.clone(),
+            token: token.clone(),
+            room: room.clone(),
+        }),
+        NotifierConfig::Ntfy {
+            topic,
+            server,
+            token,
+        } => Box::new(Ntfy {
+            http,
+            server: server.clone().unwrap_or("https://ntfy.sh".to_string()),
+            topic: topic.clone(),
+            token: token.clone(),
+        }),
+        NotifierConfig::Telegram { .. } => Box::new(telegram(config, &http).unwrap()),
+        NotifierConfig::Email { to } => Box::new(Email { to: to.clone() }),
+    }
+}
+
+async fn send(notion: &Notion, name: &str, events: &[Event]) -> bool {
+    let Some(config) = notion.config.notifiers.get(name) else {
+        return true;
+    };
+    let notifier = notifier(config, &notion.http);
+    let attributes = vec![KeyValue::new("notifier", name.to_string())];
+    match telemetry::span("notify", attributes, notifier.send(events)).await {
+        Ok(()) => true,
+        Err(err) => {
+            notion
+                .ui
+                .error(format!("failed to notify {}: {}", name, err));
+            false
+        }
+    }
+}
+
+/// An event about the run rather than a repo, so only routes without `tags` or `repos` take it.
+pub fn failure(message: String) -> Event {
+    Event {
+        kind: EventKind::Failure,
+        repo: String::new(),
+        tags: Vec::new(),
+        message,
+    }
+}
+
+/// Sends each event to the notifier of the first route it matches. Routes with `every` queue their
+/// events in the state file instead and send them in one message once that long has passed, routes
+/// with `batch` send the run's in one message at the end.
+/// Muted repos, by the bot, the config file or `muted` (lowercase, from the pages), are skipped.
+pub async fn dispatch(
+    notion: &Notion,
+    state: &mut State,
+    events: Vec<Event>,
+    muted: &HashSet<String>,
+    now: DateTime<Utc>,
+) {
+    let routes = &notion.config.routes;
+    let mut batches = BTreeMap::<&str, (&Route, Vec<Event>)>::new();
+    for event in events {
+        let repo = event.repo.to_lowercase();
+        if state.muted.contains(&repo)
+            || muted.contains(&repo)
+            || notion.config.repo(&event.repo).muted(now.date_naive())
+        {
+            continue;
+        }
+        let Some(route) = routes.iter().find(|route| route.matches(&event)) else {
+            continue;
+        };
+        if route.every.is_some() && event.kind != EventKind::Failure {
+            state
+                .digests
+                .entry(route.notifier.clone())
+                .or_insert_with(|| Digest {
+                    started: now,
+                    events: Vec::new(),
+                })
+                .events
+                .push(event);
+        } else if route.batch && event.kind != EventKind::Failure {
+            batches
+                .entry(&route.notifier)
+                .or_insert_with(|| (route, Vec::new()))
+                .1
+                .push(event);
+        } else {
+            send(notion, &route.notifier, &[event]).await;
+        }
+    }
+    for (name, (route, events)) in batches {
+        let events = match route.batch_limit {
+            Some(limit) if events.len() > limit => vec![rollup(&events, route.link.as_deref())],
+            _ => events,
+        };
+        send(notion, name, &events).await;
+    }
+    let due = state
+        .digests
+        .iter()
+        .filter(|(name, digest)| {
+            let every = routes
+                .iter()
+                .filter(|route| &route.notifier == *name)
+                .filter_map(|route| route.every())
+                .min();
+            // no longer routed to with `every`, so there's nothing to wait for
+            match every {
+                Some(every) => now - digest.started >= every,
+                None => true,
+            }
+        })
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<String>>();
+    for name in due {
+        let digest = state.digests.remove(&name).unwrap();
+        // a digest that couldn't be sent is tried again next run
+        if !send(notion, &name, &digest.events).await {
+            state.digests.insert(name, digest);
+        }
+    }
+}
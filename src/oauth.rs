@@ -0,0 +1,211 @@
+use crate::{
+    redact::redact,
+    serve::{read_request, respond_text},
+    ui::Ui,
+};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    process::Command,
+};
+use tokio::net::TcpListener;
+
+/// The port of the redirect URI, which has to be registered exactly as
+/// `http://localhost:<port>/callback` in the public integration's settings.
+pub const DEFAULT_PORT: u16 = 8976;
+
+/// What Notion hands back for an authorization code.
+#[derive(Deserialize)]
+pub struct Grant {
+    pub access_token: String,
+    pub workspace_name: Option<String>,
+    /// the page the user duplicated from the integration's template, if it has one
+    pub duplicated_template_id: Option<String>,
+}
+
+/// A Notion public integration, `NOTION_CLIENT_ID` and `NOTION_CLIENT_SECRET`.
+pub struct OAuth {
+    http: reqwest::Client,
+    api_url: String,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+}
+
+impl OAuth {
+    pub fn from_env(port: u16) -> Result<OAuth, String> {
+        let var = |name: &str| env::var(name).map_err(|_| format!("{} is not set", name));
+        Ok(OAuth {
+            http: reqwest::Client::new(),
+            api_url: env::var("NOTION_API_URL")
+                .unwrap_or("https://api.notion.com/v1".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            client_id: var("NOTION_CLIENT_ID")?,
+            client_secret: var("NOTION_CLIENT_SECRET")?,
+            redirect_uri: format!("http://localhost:{}/callback", port),
+        })
+    }
+
+    /// Where the user clicks "Allow". `state` comes back with the code.
+    pub fn authorize_url(&self, state: &str) -> String {
+        reqwest::Url::parse_with_params(
+            &format!("{}/oauth/authorize", self.api_url),
+            [
+                ("client_id", self.client_id.as_str()),
+                ("response_type", "code"),
+                ("owner", "user"),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("state", state),
+            ],
+        )
+        .unwrap()
+        .to_string()
+    }
+
+    /// Waits for the browser to come back to the redirect URI and returns the code it carries.
+    /// Anything else reaching the port, or a callback with another `state`, is turned away.
+    pub async fn callback(&self, listener: &TcpListener, state: &str) -> Result<String, String> {
+        loop {
+            let (mut stream, _) = listener.accept().await.map_err(|err| err.to_string())?;
+            let Some(request) = read_request(&mut stream).await else {
+                continue;
+            };
+            if request.path != "/callback" {
+                respond_text(&mut stream, "404 Not Found", "not found").await;
+                continue;
+            }
+            let params = reqwest::Url::parse(&format!("http://localhost/?{}", request.query))
+                .map(|url| url.query_pairs().into_owned().collect::<Vec<_>>())
+                .unwrap_or_default();
+            let param = |name: &str| {
+                params
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.clone())
+            };
+            if param("state").as_deref() != Some(state) {
+                respond_text(&mut stream, "400 Bad Request", "unexpected state").await;
+                continue;
+            }
+            if let Some(error) = param("error") {
+                respond_text(&mut stream, "200 OK", "Access was not granted.").await;
+                return Err(format!("access was not granted: {}", error));
+            }
+            let Some(code) = param("code") else {
+                respond_text(&mut stream, "400 Bad Request", "no code").await;
+                continue;
+            };
+            respond_text(
+                &mut stream,
+                "200 OK",
+                "notionstar is connected, you can close this tab.",
+            )
+            .await;
+            return Ok(code);
+        }
+    }
+
+    /// Trades the code for an access token.
+    pub async fn exchange(&self, code: &str) -> Result<Grant, String> {
+        let resp = self
+            .http
+            .post(format!("{}/oauth/token", self.api_url))
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .json(&json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": self.redirect_uri,
+            }))
+            .send()
+            .await
+            .map_err(|err| redact(&err.to_string()))?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!("returned {}: {}", status, redact(&body)));
+        }
+        resp.json().await.map_err(|err| err.to_string())
+    }
+}
+
+/// Sets `name` in a `.env` file, replacing the line that set it before and keeping the rest. The
+/// file holds tokens, so only its owner gets to read it.
+pub fn save_env(path: &Path, name: &str, value: &str) -> io::Result<()> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let mut lines = content
+        .lines()
+        .filter(|line| {
+            line.trim_start()
+                .strip_prefix(name)
+                .is_none_or(|rest| !rest.trim_start().starts_with('='))
+        })
+        .map(str::to_string)
+        .collect::<Vec<String>>();
+    lines.push(format!("{}={}", name, value));
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // a file that was already there keeps its mode, it's narrowed before the token goes in
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all((lines.join("\n") + "\n").as_bytes())
+}
+
+/// Best effort, the URL is printed as well.
+fn open_browser(url: &str) {
+    let opened = match env::consts::OS {
+        "macos" => Command::new("open").arg(url).status(),
+        "windows" => Command::new("rundll32")
+            .args(["url.dll,FileProtocolHandler", url])
+            .status(),
+        _ => Command::new("xdg-open").arg(url).status(),
+    };
+    opened.ok();
+}
+
+/// Connects notionstar to a workspace through the public integration's OAuth flow and saves the
+/// token as `NOTION_API` in `.env`, so nobody has to create an internal integration.
+pub async fn login(ui: &Ui, port: u16) -> Result<(), String> {
+    let oauth = OAuth::from_env(port)?;
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|err| format!("failed to listen on port {}: {}", port, err))?;
+    // guards the callback against forged redirects, so it has to be unguessable
+    let mut state = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut state)
+        .map_err(|_| "failed to generate the OAuth state".to_string())?;
+    let state = hex::encode(state);
+    let url = oauth.authorize_url(&state);
+    ui.println(format!(
+        "open this page to connect notionstar to Notion:\n{}",
+        url
+    ));
+    open_browser(&url);
+    let code = oauth.callback(&listener, &state).await?;
+    let grant = oauth.exchange(&code).await?;
+    save_env(Path::new(".env"), "NOTION_API", &grant.access_token)
+        .map_err(|err| format!("failed to write .env: {}", err))?;
+    ui.println(format!(
+        "connected to {}, token saved to .env",
+        grant.workspace_name.as_deref().unwrap_or("the workspace")
+    ));
+    if let Some(page) = grant.duplicated_template_id {
+        ui.println(format!("the template was duplicated into page {}", page));
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use serde_json::{json, Value};
+
+/// Every option color Notion offers except `default`, which renders gray.
+const COLORS: [&str; 8] = [
+    "brown", "orange", "yellow", "green", "blue", "purple", "pink", "red",
+];
+
+/// Color for a select option, the same name always gets the same color.
+pub fn color(name: &str) -> &'static str {
+    let hash = crate::hash::fnv1a(name.as_bytes());
+    COLORS[(hash % COLORS.len() as u64) as usize]
+}
+
+pub fn option(name: &str) -> Value {
+    json!({ "name": name, "color": color(name) })
+}
+
+/// The option names a select or multi-select property value uses, along with its type.
+pub fn used(value: &Value) -> Option<(&'static str, Vec<&str>)> {
+    if let Some(select) = value.get("select") {
+        return Some(("select", select["name"].as_str().into_iter().collect()));
+    }
+    value
+        .get("multi_select")
+        .and_then(Value::as_array)
+        .map(|options| {
+            (
+                "multi_select",
+                options
+                    .iter()
+                    .filter_map(|option| option["name"].as_str())
+                    .collect(),
+            )
+        })
+}
@@ -0,0 +1,96 @@
+use crate::{changes::Snapshot, client, hash, state::State, text, Notion};
+use notion::chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// What the starred repos of one owner add up to.
+struct Owner {
+    name: String,
+    repos: usize,
+    stars: u64,
+    latest_release: Option<NaiveDate>,
+}
+
+/// Per owner, from the repos as the runs last saw them, the most starred-from owners first.
+fn rollup(snapshot: &BTreeMap<String, Snapshot>) -> Vec<Owner> {
+    let mut owners = BTreeMap::<&str, Owner>::new();
+    for (repo, seen) in snapshot {
+        let name = repo.split('/').next().unwrap_or_default();
+        let owner = owners.entry(name).or_insert_with(|| Owner {
+            name: name.to_string(),
+            repos: 0,
+            stars: 0,
+            latest_release: None,
+        });
+        owner.repos += 1;
+        owner.stars += u64::from(seen.stars);
+        owner.latest_release = owner.latest_release.max(seen.release);
+    }
+    let mut owners = owners.into_values().collect::<Vec<Owner>>();
+    owners.sort_by(|a, b| b.repos.cmp(&a.repos).then(b.stars.cmp(&a.stars)));
+    owners
+}
+
+fn blocks(owners: &[Owner]) -> Vec<Value> {
+    owners
+        .iter()
+        .map(|owner| {
+            let line = format!(
+                "{} — {} repo{}, {} stars, latest release {}",
+                owner.name,
+                owner.repos,
+                if owner.repos == 1 { "" } else { "s" },
+                owner.stars,
+                owner
+                    .latest_release
+                    .map_or("none".to_string(), |date| date.to_string())
+            );
+            json!({
+                "object": "block",
+                "type": "bulleted_list_item",
+                "bulleted_list_item": { "rich_text": text(line) },
+            })
+        })
+        .collect()
+}
+
+/// Rewrites the content of the config's `owners_page` with one line per owner: how many of their repos
+/// are starred, their combined stars and the newest release among them. The page is only
+/// touched when that changed since the last run.
+pub async fn update(notion: &Notion, state: &mut State) {
+    let Some(page) = notion
+        .config
+        .owners_page
+        .as_deref()
+        .and_then(client::database_id)
+    else {
+        return;
+    };
+    let blocks = blocks(&rollup(&state.snapshot));
+    let digest = format!(
+        "{:016x}",
+        hash::fnv1a(serde_json::to_string(&blocks).unwrap().as_bytes())
+    );
+    if state.owners_hash.as_ref() == Some(&digest) {
+        return;
+    }
+    let result = async {
+        for block in notion.api.block_children(&page).await? {
+            notion
+                .api
+                .delete_block(block["id"].as_str().unwrap_or_default())
+                .await?;
+        }
+        for chunk in blocks.chunks(100) {
+            notion.api.append_blocks(&page, chunk.to_vec()).await?;
+        }
+        Ok::<(), crate::error::NotionError>(())
+    }
+    .await;
+    match result {
+        Ok(()) => state.owners_hash = Some(digest),
+        Err(err) => notion
+            .ui
+            .error(format!("failed to update the owners page: {}", err)),
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{full_name, repo::RepoRef, telemetry};
+use opentelemetry::KeyValue;
+use serde_json::Value;
+use std::env;
+
+/// Whether a package's links fit the repo: any GitHub link among them has to be to the repo
+/// itself, packages without one are taken on the name alone.
+pub(crate) fn same_project(urls: &[&str], full_name: &str) -> bool {
+    let full_name = format!("github.com/{}", full_name.to_lowercase());
+    let github = urls
+        .iter()
+        .map(|url| url.to_lowercase())
+        .filter(|url| url.contains("github.com/"))
+        .collect::<Vec<String>>();
+    github.is_empty() || github.iter().any(|url| url.contains(&full_name))
+}
+
+/// `None` for a package that doesn't exist, errors for anything that leaves that open.
+async fn get(http: &reqwest::Client, url: String) -> Result<Option<Value>, String> {
+    let request = http.get(&url).header("User-Agent", "notionstar").send();
+    let resp = telemetry::span("packages", vec![KeyValue::new("url", url)], request)
+        .await
+        .map_err(|err| err.to_string())?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        return Err(format!("returned {}", resp.status()));
+    }
+    resp.json().await.map(Some).map_err(|err| err.to_string())
+}
+
+/// Stable version of the Homebrew formula or cask named like the repo.
+pub async fn homebrew(http: &reqwest::Client, repo: &RepoRef) -> Result<Option<String>, String> {
+    let api = env::var("HOMEBREW_API_URL").unwrap_or("https://formulae.brew.sh/api".to_string());
+    let api = api.trim_end_matches('/');
+    let name = repo.name.to_lowercase();
+    if let Some(formula) = get(http, format!("{}/formula/{}.json", api, name)).await? {
+        let urls = [&formula["homepage"], &formula["urls"]["stable"]["url"]]
+            .into_iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<&str>>();
+        if same_project(&urls, &full_name(repo)) {
+            return Ok(formula["versions"]["stable"].as_str().map(str::to_string));
+        }
+    }
+    let Some(cask) = get(http, format!("{}/cask/{}.json", api, name)).await? else {
+        return Ok(None);
+    };
+    let urls = [&cask["homepage"], &cask["url"]]
+        .into_iter()
+        .filter_map(Value::as_str)
+        .collect::<Vec<&str>>();
+    Ok(same_project(&urls, &full_name(repo))
+        .then(|| cask["version"].as_str().map(str::to_string))
+        .flatten())
+}
+
+/// Version of the AUR package named like the repo, or its `-bin` or `-git` variant.
+pub async fn aur(http: &reqwest::Client, repo: &RepoRef) -> Result<Option<String>, String> {
+    let aur = env::var("AUR_URL").unwrap_or("https://aur.archlinux.org".to_string());
+    let name = repo.name.to_lowercase();
+    let names = [
+        name.clone(),
+        format!("{}-bin", name),
+        format!("{}-git", name),
+    ];
+    let query = names
+        .iter()
+        .map(|name| format!("arg[]={}", name))
+        .collect::<Vec<String>>()
+        .join("&");
+    let info = get(
+        http,
+        format!("{}/rpc/v5/info?{}", aur.trim_end_matches('/'), query),
+    )
+    .await?
+    .unwrap_or_default();
+    let results = info["results"].as_array().cloned().unwrap_or_default();
+    // in the order of `names`, the plain package is the one most people install
+    Ok(names.iter().find_map(|name| {
+        let package = results
+            .iter()
+            .find(|package| package["Name"].as_str() == Some(name.as_str()))?;
+        let urls = package["URL"].as_str().into_iter().collect::<Vec<&str>>();
+        same_project(&urls, &full_name(repo))
+            .then(|| package["Version"].as_str().map(str::to_string))
+            .flatten()
+    }))
+}
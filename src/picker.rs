@@ -0,0 +1,79 @@
+use crate::{
+    client::{HttpNotionClient, DEFAULT_VERSION},
+    config::Config,
+    request_timeout,
+    ui::Ui,
+};
+use std::{
+    env, fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
+
+/// Asks which of the databases shared with the integration to sync into, when neither
+/// `DATABASE` nor the config file's `database` says, and saves the answer to the config file so
+/// it's asked once. Exits when there's nothing to pick from or nobody to ask.
+pub async fn pick_database(ui: &Ui) -> String {
+    let fail = |message: &str| -> ! {
+        ui.error(message);
+        std::process::exit(1);
+    };
+    if !io::stdin().is_terminal() {
+        fail("DATABASE is not set");
+    }
+    let token = env::var("NOTION_API").unwrap_or_else(|_| fail("NOTION_API is not set"));
+    let client = HttpNotionClient::new(
+        token,
+        String::new(),
+        &env::var("NOTION_API_URL").unwrap_or("https://api.notion.com/v1".to_string()),
+        env::var("NOTION_VERSION").unwrap_or(DEFAULT_VERSION.to_string()),
+        request_timeout(ui),
+    );
+    let databases = client
+        .search_databases()
+        .await
+        .unwrap_or_else(|err| fail(&format!("failed to search for databases: {}", err)));
+    if databases.is_empty() {
+        fail("no database is shared with the integration, add it under the database's ⋯ > Connections");
+    }
+    println!("DATABASE is not set, pick the database to sync stars into:");
+    for (number, (id, title)) in databases.iter().enumerate() {
+        let title = if title.is_empty() { "Untitled" } else { title };
+        println!("{:>3}  {}  {}", number + 1, title, id);
+    }
+    let id = loop {
+        print!("number: ");
+        io::stdout().flush().ok();
+        // off the runtime's threads, waiting on the terminal blocks
+        let read = tokio::task::spawn_blocking(|| {
+            let mut answer = String::new();
+            io::stdin()
+                .lock()
+                .read_line(&mut answer)
+                .map(|read| (read, answer))
+        });
+        let answer = match read.await {
+            Ok(Ok((read, answer))) if read > 0 => answer,
+            _ => fail("no database picked"),
+        };
+        match answer.trim().parse::<usize>() {
+            Ok(number) if (1..=databases.len()).contains(&number) => {
+                break databases[number - 1].0.clone();
+            }
+            _ => println!("enter a number from 1 to {}", databases.len()),
+        }
+    };
+    let path = Config::path();
+    match save_database(Path::new(&path), &id) {
+        Ok(()) => println!("saved as `database` in {}", path),
+        Err(err) => ui.error(format!("failed to save the database to {}: {}", path, err)),
+    }
+    id
+}
+
+/// Adds `database = "<id>"` to the config file, at the top where a key outside any table has to
+/// go. The rest of the file is left as it was, comments included.
+pub fn save_database(path: &Path, id: &str) -> io::Result<()> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    fs::write(path, format!("database = \"{}\"\n{}", id, content))
+}
@@ -0,0 +1,74 @@
+use regex::Regex;
+use std::{env, sync::OnceLock};
+
+/// Environment variables whose values must never show up in output.
+const SECRET_VARS: [&str; 5] = [
+    "NOTION_API",
+    "NOTION_CLIENT_SECRET",
+    "GITHUB_API",
+    "WEBHOOK_URL",
+    "LLM_API_KEY",
+];
+
+const MASK: &str = "***";
+
+fn patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // notion integration tokens
+            (r"\b(secret|ntn)_[A-Za-z0-9]{20,}", MASK),
+            // github personal access / oauth / app tokens
+            (
+                r"\b(gh[pousr]_[A-Za-z0-9]{20,}|github_pat_[A-Za-z0-9_]{20,})",
+                MASK,
+            ),
+            (
+                r"(?i)\b(bearer|authorization:\s*token)\s+[A-Za-z0-9._\-]{8,}",
+                "$1 ***",
+            ),
+            (
+                r"(?i)([?&](access_token|token|key|secret)=)[^&\s]+",
+                "${1}***",
+            ),
+            (r"(://)[^/\s:@]+:[^/\s@]+@", "${1}***@"),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+        .collect()
+    })
+}
+
+/// Masks configured secrets and anything that looks like a token or credential.
+pub fn redact(text: &str) -> String {
+    let mut text = text.to_string();
+    for name in SECRET_VARS {
+        if let Ok(secret) = env::var(name) {
+            if !secret.is_empty() {
+                text = text.replace(&secret, MASK);
+            }
+        }
+    }
+    for (pattern, replacement) in patterns() {
+        text = pattern.replace_all(&text, *replacement).into_owned();
+    }
+    text
+}
+
+/// Panics from `unwrap()` on request errors can carry URLs and headers, so redact those too.
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "Box<dyn Any>".to_string(),
+            },
+        };
+        let location = info
+            .location()
+            .map(|location| format!(" at {}", location))
+            .unwrap_or_default();
+        eprintln!("notionstar panicked{}: {}", location, redact(&message));
+    }));
+}
@@ -0,0 +1,109 @@
+use crate::{
+    date_property,
+    error::{NotionError, SyncError},
+    full_name, same_url, sync_off, text, text_property, url_property, Notion, PageUpdate,
+};
+use notion::models::Page;
+use serde_json::{json, Map};
+use std::collections::HashMap;
+
+pub async fn repair(notion: &Notion) {
+    let database = notion.get_database().await;
+    let stars = notion.get_stars().await;
+    let star_map = stars
+        .iter()
+        .map(|star| (star.name.clone(), star))
+        .collect::<HashMap<_, _>>();
+    let mut fixed = Vec::new();
+    let mut unmatched = Vec::new();
+
+    let pb = notion.ui.bar("repairing", database.len() as u64);
+    for page in &database {
+        pb.inc(1);
+        let title = page.title().unwrap();
+        pb.set_message(title.clone());
+        if sync_off(page) {
+            continue;
+        }
+        let star = match star_map.get(&title) {
+            Some(star) => star,
+            None => {
+                unmatched.push(title);
+                continue;
+            }
+        };
+        let owner = star.owner.clone();
+        let url = star.html_url.clone();
+        let mut properties = Map::new();
+        let mut changes = Vec::new();
+        if !url_property(page, "release").is_some_and(|stored| same_url(&stored, &url)) {
+            properties.insert("release".to_string(), json!({ "url": url }));
+            changes.push("url");
+        }
+        if text_property(page, "owner").unwrap_or_default().is_empty() {
+            properties.insert(
+                "owner".to_string(),
+                json!({ "rich_text": text(owner.clone()) }),
+            );
+            changes.push("owner");
+        }
+        let missing_release = date_property(page, "上次release").is_none();
+        let missing_commit = date_property(page, "上次commit").is_none();
+        if missing_release || missing_commit {
+            let (release, commit) = notion.fetch_activity(star).await;
+            let release = release.filter(|_| missing_release);
+            let commit = commit.filter(|_| missing_commit);
+            if release.is_some() {
+                changes.push("release date");
+            }
+            if commit.is_some() {
+                changes.push("commit date");
+            }
+            properties.extend(
+                PageUpdate {
+                    release,
+                    commit,
+                    ..Default::default()
+                }
+                .properties(),
+            );
+        }
+        // everything a page needs goes out in one request
+        if !properties.is_empty() {
+            if let Err(err) = notion
+                .patch_properties(page, "repair", json!(properties))
+                .await
+            {
+                report(notion, "backfill", &full_name(star), page, err);
+                continue;
+            }
+        }
+        if !changes.is_empty() {
+            fixed.push(format!("{}: {}", title, changes.join(", ")));
+        }
+    }
+    pb.finish_with_message("done");
+
+    for line in &fixed {
+        println!("fixed {}", line);
+    }
+    for title in &unmatched {
+        println!("no starred repo for page {}", title);
+    }
+    println!(
+        "{} pages checked, {} repaired, {} unmatched",
+        database.len(),
+        fixed.len(),
+        unmatched.len()
+    );
+}
+
+fn report(notion: &Notion, operation: &'static str, repo: &str, page: &Page, error: NotionError) {
+    let err = SyncError {
+        operation,
+        repo: repo.to_string(),
+        page_id: Some(page.id.to_string()),
+        error,
+    };
+    notion.ui.error(err.to_string());
+}
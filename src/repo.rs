@@ -0,0 +1,83 @@
+use crate::canonical_url;
+use notion::chrono::{DateTime, Utc};
+use octocrab::models::Repository;
+use serde::{Deserialize, Serialize};
+
+/// What the sync needs of a GitHub repo, taken out of the API's `Repository` once as it's listed.
+/// Kept in the state file for every star, so unset fields are left out of it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RepoRef {
+    pub id: u64,
+    pub owner: String,
+    pub name: String,
+    pub html_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pushed_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topics: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// SPDX id, or the name when GitHub couldn't match one (`NOASSERTION`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    pub stargazers_count: u32,
+    pub open_issues_count: u32,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub is_template: bool,
+    /// where a mirror's upstream lives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_url: Option<String>,
+}
+
+fn is_false(flag: &bool) -> bool {
+    !flag
+}
+
+impl From<Repository> for RepoRef {
+    fn from(repo: Repository) -> RepoRef {
+        let html_url = repo.html_url.map_or_else(
+            || {
+                format!(
+                    "https://github.com/{}/{}",
+                    repo.owner.as_ref().unwrap().login,
+                    repo.name
+                )
+            },
+            |url| canonical_url(url.as_str()),
+        );
+        RepoRef {
+            id: repo.id.0,
+            owner: repo.owner.unwrap().login,
+            name: repo.name,
+            html_url,
+            description: repo.description,
+            created_at: repo.created_at,
+            pushed_at: repo.pushed_at,
+            default_branch: repo.default_branch,
+            topics: repo.topics,
+            language: repo
+                .language
+                .and_then(|language| language.as_str().map(str::to_string)),
+            license: repo.license.map(|license| {
+                if license.spdx_id == "NOASSERTION" {
+                    license.name
+                } else {
+                    license.spdx_id
+                }
+            }),
+            stargazers_count: repo.stargazers_count.unwrap_or(0),
+            open_issues_count: repo.open_issues_count.unwrap_or(0),
+            archived: repo.archived.unwrap_or(false),
+            is_template: repo.is_template.unwrap_or(false),
+            mirror_url: repo.mirror_url.map(String::from),
+        }
+    }
+}
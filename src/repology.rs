@@ -0,0 +1,139 @@
+use crate::{bump, packages::same_project, telemetry};
+use opentelemetry::KeyValue;
+use serde_json::Value;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Repology asks API clients to stay under one request a second.
+const PAUSE: Duration = Duration::from_secs(1);
+
+/// When the next request may go out, so only requests that really hit Repology wait.
+static NEXT: Mutex<Option<Instant>> = Mutex::new(None);
+
+async fn pace() {
+    let wait = {
+        let mut next = NEXT.lock().unwrap();
+        let now = Instant::now();
+        let at = next.map_or(now, |next| next.max(now));
+        *next = Some(at + PAUSE);
+        at - now
+    };
+    tokio::time::sleep(wait).await;
+}
+
+/// Where the packages of `project` are kept under the `cache_dir`, if there's one.
+fn cache_path(cache: Option<&Path>, project: &str) -> Option<PathBuf> {
+    Some(cache?.join(format!("repology-{}.json", project)))
+}
+
+/// Packages saved by an earlier run that are younger than `CACHE_TTL_REPOLOGY`, default 24h.
+fn cached(cache: Option<&Path>, project: &str) -> Option<Vec<Value>> {
+    let path = cache_path(cache, project)?;
+    let ttl = env::var("CACHE_TTL_REPOLOGY")
+        .ok()
+        .and_then(|ttl| humantime::parse_duration(&ttl).ok())
+        .unwrap_or(Duration::from_secs(24 * 60 * 60));
+    let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default();
+    (age < ttl)
+        .then(|| serde_json::from_str(&fs::read_to_string(&path).ok()?).ok())
+        .flatten()
+}
+
+/// Homepages and downloads a package lists, which tell projects sharing a name apart.
+fn urls(package: &Value) -> Vec<&str> {
+    ["www", "downloads", "homepage"]
+        .iter()
+        .flat_map(|field| match &package[field] {
+            Value::Array(urls) => urls.iter().filter_map(Value::as_str).collect(),
+            url => url.as_str().into_iter().collect::<Vec<&str>>(),
+        })
+        .collect()
+}
+
+async fn packages(
+    http: &reqwest::Client,
+    cache: Option<&Path>,
+    project: &str,
+) -> Result<Vec<Value>, String> {
+    if let Some(packages) = cached(cache, project) {
+        return Ok(packages);
+    }
+    pace().await;
+    let api = env::var("REPOLOGY_URL").unwrap_or("https://repology.org".to_string());
+    let request = http
+        .get(format!(
+            "{}/api/v1/project/{}",
+            api.trim_end_matches('/'),
+            project
+        ))
+        // requests without one are refused
+        .header("User-Agent", "notionstar")
+        .send();
+    let attributes = vec![KeyValue::new("project", project.to_string())];
+    let resp = telemetry::span("repology", attributes, request)
+        .await
+        .map_err(|err| err.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Repology returned {}", resp.status()));
+    }
+    let packages = resp
+        .json::<Vec<Value>>()
+        .await
+        .map_err(|err| err.to_string())?;
+    // failing to write the cache only costs a request next time
+    if let (Some(path), Ok(content)) =
+        (cache_path(cache, project), serde_json::to_string(&packages))
+    {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        fs::write(path, content).ok();
+    }
+    Ok(packages)
+}
+
+/// How distro packages of the project named like `full_name` compare to `latest`, the repo's
+/// latest release tag: `up to date` when some distro ships it, `lagging` when none does,
+/// `not packaged` when Repology knows no packages of it. Packages pointing at another GitHub repo
+/// are a different project sharing the name and don't count. Without a release Repology's own
+/// idea of the newest version counts.
+pub async fn status(
+    http: &reqwest::Client,
+    cache: Option<&Path>,
+    full_name: &str,
+    latest: Option<&str>,
+    prefix: &str,
+) -> Result<&'static str, String> {
+    let name = full_name.rsplit('/').next().unwrap_or(full_name);
+    let packages = packages(http, cache, &name.to_lowercase())
+        .await?
+        .into_iter()
+        .filter(|package| same_project(&urls(package), full_name))
+        .collect::<Vec<Value>>();
+    if packages.is_empty() {
+        return Ok("not packaged");
+    }
+    let current = match latest {
+        Some(latest) => {
+            let wanted = bump::version(latest, prefix);
+            packages.iter().any(|package| {
+                let version = package["version"].as_str().unwrap_or_default();
+                match (&wanted, bump::version(version, "")) {
+                    (Some(wanted), Some(version)) => version >= *wanted,
+                    _ => latest.trim_start_matches(|c: char| !c.is_ascii_digit()) == version,
+                }
+            })
+        }
+        None => packages
+            .iter()
+            .any(|package| package["status"].as_str() == Some("newest")),
+    };
+    Ok(if current { "up to date" } else { "lagging" })
+}
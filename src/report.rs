@@ -0,0 +1,147 @@
+use crate::{
+    date_property, multi_select_property, page_full_name, select_property, state::env_number,
+    url_property,
+};
+use notion::{chrono::NaiveDate, models::Page};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+/// Sorts the table by the clicked column, again to reverse it. Dates are ISO so text order is
+/// date order.
+const SORT_SCRIPT: &str = r#"
+document.querySelectorAll("th").forEach((th, column) => th.addEventListener("click", () => {
+  const body = th.closest("table").tBodies[0];
+  const descending = th.dataset.order !== "desc";
+  th.closest("tr").querySelectorAll("th").forEach(other => delete other.dataset.order);
+  th.dataset.order = descending ? "desc" : "asc";
+  const rows = [...body.rows].sort((a, b) =>
+    a.cells[column].textContent.localeCompare(b.cells[column].textContent, undefined, { numeric: true }));
+  if (descending) rows.reverse();
+  body.append(...rows);
+}));
+"#;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 72em; padding: 0 1em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ddd; padding: .3em .6em; text-align: left; }
+th { cursor: pointer; user-select: none; }
+th[data-order=asc]::after { content: ' ▲'; }
+th[data-order=desc]::after { content: ' ▼'; }
+.bar { background: #4c8bf5; color: white; padding: .1em .4em; margin: .15em 0; white-space: nowrap; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The newest of the release, commit and push dates.
+pub(crate) fn last_active(page: &Page) -> Option<NaiveDate> {
+    ["上次release", "上次commit", "Last push"]
+        .into_iter()
+        .filter_map(|name| date_property(page, name))
+        .max()
+}
+
+fn date_cell(date: Option<NaiveDate>) -> String {
+    date.map_or(String::new(), |date| date.to_string())
+}
+
+/// Writes `index.html` into `dir`: every repo in a sortable table, how many repos use each
+/// language, and the repos without a release, commit or push for `STALE_AFTER_DAYS` (default
+/// 180). Everything is inline, the directory can be put anywhere as it is.
+pub fn html(pages: &[Page], dir: &Path, today: NaiveDate) -> io::Result<()> {
+    let mut rows = String::new();
+    let mut languages = BTreeMap::<String, usize>::new();
+    let mut stale = Vec::new();
+    for page in pages {
+        let repo = page_full_name(page);
+        let language = select_property(page, "Language");
+        let release = date_property(page, "上次release");
+        let commit = date_property(page, "上次commit");
+        let push = date_property(page, "Last push");
+        *languages
+            .entry(language.clone().unwrap_or("Unknown".to_string()))
+            .or_default() += 1;
+        let last_active = last_active(page);
+        let inactive = last_active.map(|date| (today - date).num_days());
+        if inactive.is_none_or(|days| days >= env_number("STALE_AFTER_DAYS", 180)) {
+            stale.push((last_active, repo.clone()));
+        }
+        let name = match url_property(page, "release") {
+            Some(url) => format!("<a href=\"{}\">{}</a>", escape(&url), escape(&repo)),
+            None => escape(&repo),
+        };
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            name,
+            escape(&language.unwrap_or_default()),
+            escape(
+                &multi_select_property(page, "Topics")
+                    .unwrap_or_default()
+                    .join(", ")
+            ),
+            date_cell(release),
+            date_cell(commit),
+            date_cell(push),
+        ));
+    }
+
+    let mut languages = languages.into_iter().collect::<Vec<_>>();
+    languages.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    let most = languages.first().map_or(1, |(_, count)| *count);
+    let chart = languages
+        .iter()
+        .map(|(language, count)| {
+            format!(
+                "<div class=\"bar\" style=\"width: {:.1}%\">{} ({})</div>\n",
+                *count as f64 / most as f64 * 100.0,
+                escape(language),
+                count
+            )
+        })
+        .collect::<String>();
+
+    // never active first, then the longest inactive
+    stale.sort();
+    let stale = stale
+        .iter()
+        .map(|(last_active, repo)| match last_active {
+            Some(date) => format!("<li>{} — last active {}</li>\n", escape(repo), date),
+            None => format!("<li>{} — no activity recorded</li>\n", escape(repo)),
+        })
+        .collect::<String>();
+
+    let html = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>GitHub stars</title>
+<style>{STYLE}</style>
+</head>
+<body>
+<h1>GitHub stars</h1>
+<p>{count} repos, generated {today}</p>
+<h2>Languages</h2>
+{chart}
+<h2>Repos</h2>
+<table>
+<thead><tr><th>Repo</th><th>Language</th><th>Topics</th><th>Last release</th><th>Last commit</th><th>Last push</th></tr></thead>
+<tbody>
+{rows}</tbody>
+</table>
+<h2>Stale</h2>
+<ul>
+{stale}</ul>
+<script>{SORT_SCRIPT}</script>
+</body>
+</html>
+",
+        count = pages.len(),
+    );
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("index.html"), html)
+}
@@ -0,0 +1,1157 @@
+use crate::{
+    audit, blank_property, bump,
+    changes::{self, Change, Snapshot},
+    checkbox_property,
+    client::ReleaseInfo,
+    column::{
+        Cell, AUR, BREAKING, CHANGELOG, CONTRIBUTORS, DEFAULT_BRANCH, DISCUSSION, DISTROS,
+        HOMEBREW, LAST_PUSH, LATEST_CONTRIBUTOR, LATEST_IMAGE, MILESTONE_DUE, NEXT_MILESTONE,
+        OPEN_ISSUES, OPEN_PRS, SECURITY_RELEASE,
+    },
+    dashboard, date_property,
+    error::SyncError,
+    event_tags, full_name, journal, kept, manual_row, muted_page,
+    notify::{self, Event, EventKind},
+    owners, packages, page_full_name, page_tags, release_date,
+    repo::RepoRef,
+    repo_tags, repology, shutdown,
+    state::State,
+    status, status_property, stop_reason,
+    summary::{Summary, UpdatedRepo},
+    sync_off,
+    target::{RepoRecord, Target},
+    telemetry, text, text_property, transfer, Notion, PageUpdate, SyncArgs, CHUNKS_AHEAD, PINNED,
+    SUMMARY, SYNC_HASH,
+};
+use notion::{
+    chrono::{DateTime, NaiveDate, Utc},
+    models::Page,
+};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    time::Instant,
+};
+use tokio::sync::mpsc;
+
+/// Release and commit dates of the pages created this run, keyed by title.
+type Fresh = HashMap<String, (Option<NaiveDate>, Option<NaiveDate>)>;
+
+/// A page write the second plan checks: page id, repo, what was wanted and the hash written.
+type Written<'s> = (String, &'s RepoRef, PageUpdate, Option<String>);
+
+pub(crate) async fn run_sync(notion: &Notion, args: &SyncArgs) -> Summary {
+    let targets = Target::parse_all(&args.target);
+    let mut run = Run::new(notion, args);
+    let stars = run.list_stars().await;
+    // search results are synced like stars, but never take the place of one
+    let discovered = telemetry::span("sync.discover", vec![], notion.discover(&stars)).await;
+    let synced = Synced::new(&stars, &discovered);
+    let mut index = run.index(&synced).await;
+    if run.archiving() {
+        run.find_moves(&synced, &mut index).await;
+    }
+    run.apply_moves(&mut index).await;
+    let fresh = run.add(&synced, &index).await;
+    run.archive(std::mem::take(&mut index.unstarred)).await;
+    let Refreshed {
+        records,
+        snapshots,
+        limited,
+        present,
+        written,
+    } = run.refresh(&synced, &index, &fresh).await;
+    if args.assert_empty_plan {
+        let planning = second_plan(notion, args, &synced.by_name, written);
+        run.summary.second_plan = telemetry::span("sync.plan", vec![], planning).await;
+    }
+    run.record_changes(&synced, snapshots);
+    run.notify(&index.muted).await;
+    run.keep_stars(stars);
+    run.report(targets, &records, limited, &present).await
+}
+
+/// Refreshes the page of one starred repo, for a webhook about it. The stars the last run listed
+/// stand in for a listing, and the page is looked up by its URL rather than read with the rest
+/// of the database. Nothing is added or archived. Before any run has listed the stars, it's a
+/// sync with `--only`, which lists them.
+pub(crate) async fn refresh_repo(notion: &Notion, name: &str) -> Summary {
+    let args = SyncArgs {
+        only: vec![name.to_string()],
+        ..Default::default()
+    };
+    if State::load().star_watermark.is_none() {
+        return run_sync(notion, &args).await;
+    }
+    let mut run = Run::new(notion, &args);
+    let stars = std::mem::take(&mut run.state.starred);
+    let synced = Synced::new(&stars, &[]);
+    let mut refreshed = Refreshed::default();
+    let mut muted = HashSet::new();
+    match stars.iter().find(|repo| args.wants(repo)) {
+        None => notion
+            .ui
+            .println(format!("{} isn't starred, nothing to refresh", name)),
+        Some(repo) => match notion.api.find_page("release", &repo.html_url).await {
+            Ok(page) => match page.and_then(|page| serde_json::from_value::<Page>(page).ok()) {
+                Some(page) if run.refreshable(&page, repo) => {
+                    if muted_page(&page, run.today) {
+                        muted.insert(full_name(repo).to_lowercase());
+                    }
+                    refreshed.present.insert(full_name(repo));
+                    let fresh = Fresh::new();
+                    let refreshing = run.refresh_page(&page, repo, &synced, &fresh, &mut refreshed);
+                    telemetry::span("sync.refresh", vec![], refreshing).await;
+                }
+                Some(_) => {}
+                None => notion.ui.println(format!(
+                    "no page for {}, nothing to refresh",
+                    full_name(repo)
+                )),
+            },
+            Err(err) => {
+                let err = SyncError {
+                    operation: "find page",
+                    repo: full_name(repo),
+                    page_id: None,
+                    error: err,
+                };
+                notion.ui.error(err.to_string());
+                run.summary.errors.push(err);
+            }
+        },
+    }
+    let Refreshed {
+        records,
+        snapshots,
+        limited,
+        present,
+        ..
+    } = refreshed;
+    run.record_changes(&synced, snapshots);
+    run.notify(&muted).await;
+    run.keep_stars(stars);
+    run.report(Vec::new(), &records, limited, &present).await
+}
+
+/// What a sync run carries from one phase to the next.
+struct Run<'a> {
+    notion: &'a Notion,
+    args: &'a SyncArgs,
+    deadline: Option<Instant>,
+    limit: usize,
+    targeted: bool,
+    now: DateTime<Utc>,
+    today: NaiveDate,
+    state: State,
+    /// whether every star is listed, rather than only the ones added since the last run
+    full: bool,
+    /// a listing cut short by `max_pages` doesn't say what was unstarred
+    listed_all: bool,
+    summary: Summary,
+    events: Vec<Event>,
+}
+
+/// The repos this run syncs.
+struct Synced<'s> {
+    /// stars first, then search results
+    repos: Vec<&'s RepoRef>,
+    /// what pages are matched against, by title
+    by_name: HashMap<String, &'s RepoRef>,
+    /// names of the search results
+    discovered: HashSet<String>,
+}
+
+/// The database read against the stars, before anything is written.
+#[derive(Default)]
+struct Index<'s> {
+    /// titles of the pages that have a repo, or are about to
+    titles: HashSet<String>,
+    unstarred: Vec<Page>,
+    moved: Vec<(Page, &'s RepoRef)>,
+    /// ids of pages of another repo than the star they're named like
+    foreign: HashSet<String>,
+    /// the refreshable pages' last commit, last edit and id, to pick the stalest ones by
+    stale: Vec<(Option<NaiveDate>, DateTime<Utc>, String)>,
+    /// lowercase `owner/name`s of pages muted with their `Mute` box, nothing is sent about them
+    muted: HashSet<String>,
+}
+
+/// What the lookups of a due repo found.
+#[derive(Default)]
+struct Lookup {
+    release: Option<NaiveDate>,
+    commit: Option<NaiveDate>,
+    bump: Option<&'static str>,
+    /// the latest release, when it's a new one that looks like a security fix
+    security_release: Option<ReleaseInfo>,
+}
+
+/// What the refresh saw.
+#[derive(Default)]
+struct Refreshed<'s> {
+    records: Vec<RepoRecord>,
+    snapshots: BTreeMap<String, Snapshot>,
+    /// whether `--limit` left pages out
+    limited: bool,
+    /// repos that still have a page, what the targets keep
+    present: HashSet<String>,
+    written: Vec<Written<'s>>,
+}
+
+impl Index<'_> {
+    fn push_stale(&mut self, page: &Page) {
+        let commit = date_property(page, "上次commit");
+        let edited = page.last_edited_time;
+        self.stale.push((commit, edited, page.id.to_string()));
+    }
+
+    /// Ids of the `limit` refreshable pages that have gone longest without a commit, those
+    /// without any last, then the ones least recently edited. Nothing when there's room for all.
+    fn stalest(&self, limit: usize) -> Option<HashSet<String>> {
+        if self.stale.len() <= limit {
+            return None;
+        }
+        let mut stale = self.stale.iter().collect::<Vec<_>>();
+        stale.sort_by_key(|(commit, edited, _)| (commit.is_none(), *commit, *edited));
+        Some(
+            stale
+                .into_iter()
+                .take(limit)
+                .map(|(_, _, id)| id.clone())
+                .collect(),
+        )
+    }
+}
+
+impl<'s> Synced<'s> {
+    fn new(stars: &'s [RepoRef], discovered: &'s [RepoRef]) -> Synced<'s> {
+        let repos = stars.iter().chain(discovered).collect::<Vec<&RepoRef>>();
+        Synced {
+            by_name: repos
+                .iter()
+                .map(|&repo| (repo.name.clone(), repo))
+                .collect(),
+            discovered: discovered.iter().map(|repo| repo.name.clone()).collect(),
+            repos,
+        }
+    }
+}
+
+impl<'a> Run<'a> {
+    fn new(notion: &'a Notion, args: &'a SyncArgs) -> Run<'a> {
+        let run = audit::start_run();
+        let now = Utc::now();
+        Run {
+            notion,
+            args,
+            deadline: args.deadline.map(|deadline| Instant::now() + deadline),
+            limit: args.limit.unwrap_or(usize::MAX),
+            targeted: !args.only.is_empty(),
+            now,
+            today: now.date_naive(),
+            state: State::load(),
+            full: false,
+            listed_all: true,
+            summary: Summary {
+                run: notion.config.audit_log.is_some().then_some(run),
+                ..Default::default()
+            },
+            events: Vec::new(),
+        }
+    }
+
+    fn refreshable(&self, page: &Page, repo: &RepoRef) -> bool {
+        !self.args.skip_update && !sync_off(page) && self.args.wants(repo)
+    }
+
+    fn archiving(&self) -> bool {
+        !self.targeted && !self.args.skip_archive && self.listed_all
+    }
+
+    /// The stars, every one on a full run. They're the state's list, lent to the run rather than
+    /// copied and given back at the end.
+    async fn list_stars(&mut self) -> Vec<RepoRef> {
+        let notion = self.notion;
+        self.full = self.args.full || self.state.full_sync_due(self.now);
+        if self.full {
+            let (stars, complete) =
+                telemetry::span("sync.stars", vec![], notion.star_listing()).await;
+            if !complete {
+                notion.ui.println(format!(
+                    "star listing stopped at max_pages after {} stars, nothing is archived",
+                    stars.len()
+                ));
+                self.listed_all = false;
+            }
+            stars
+        } else {
+            let since = self.state.star_watermark.unwrap();
+            let new_stars =
+                telemetry::span("sync.stars", vec![], notion.get_stars_since(since)).await;
+            notion
+                .ui
+                .println(format!("{} new stars since last run", new_stars.len()));
+            self.state.add_stars(new_stars);
+            std::mem::take(&mut self.state.starred)
+        }
+    }
+
+    /// Reads the database against the stars. The database goes by a chunk at a time, only the
+    /// titles and the unstarred rows are kept.
+    async fn index<'s>(&self, synced: &Synced<'s>) -> Index<'s> {
+        let mut index = Index::default();
+        // pages named like a star but with another owner written down, a transfer or a namesake
+        let mut other_owner = Vec::new();
+        let (chunks, mut received) = mpsc::channel::<Vec<Page>>(CHUNKS_AHEAD);
+        let indexing = async {
+            while let Some(chunk) = received.recv().await {
+                for page in chunk {
+                    let title = page.title().unwrap();
+                    if muted_page(&page, self.today) {
+                        let name = synced
+                            .by_name
+                            .get(&title)
+                            .map_or_else(|| page_full_name(&page), |repo| full_name(repo));
+                        index.muted.insert(name.to_lowercase());
+                    }
+                    match synced.by_name.get(&title) {
+                        Some(&repo) if transfer::owner_changed(&page, repo) => {
+                            other_owner.push((page, repo));
+                            continue;
+                        }
+                        Some(repo) => {
+                            if self.refreshable(&page, repo) {
+                                index.push_stale(&page);
+                            }
+                        }
+                        None => index.unstarred.push(page),
+                    }
+                    index.titles.insert(title);
+                }
+            }
+        };
+        let reading = async { tokio::join!(self.notion.read_database(None, chunks), indexing) };
+        telemetry::span("sync.read_database", vec![], reading).await;
+        // adds and archives go by `owner/name`, whatever order GitHub and Notion list things in, so
+        // runs over the same data do the same things in the same order
+        // only GitHub can tell a transfer from another repo that has the same name
+        other_owner.sort_by_key(|(page, _)| page_full_name(page));
+        for (page, repo) in other_owner {
+            match transfer::same_repo(self.notion, &page, repo).await {
+                Some(true) => {
+                    if self.refreshable(&page, repo) {
+                        index.push_stale(&page);
+                    }
+                    index.titles.insert(page.title().unwrap());
+                    index.moved.push((page, repo));
+                }
+                // the star gets a page of its own, this one is of a repo that isn't starred
+                Some(false) => {
+                    index.foreign.insert(page.id.to_string());
+                    index.unstarred.push(page);
+                }
+                None => {
+                    self.notion.ui.println(format!(
+                        "can't tell whether {} is {}, left alone",
+                        page_full_name(&page),
+                        full_name(repo)
+                    ));
+                    index.foreign.insert(page.id.to_string());
+                    index.titles.insert(page.title().unwrap());
+                }
+            }
+        }
+        index.unstarred.sort_by_key(page_full_name);
+        index
+    }
+
+    /// Repos that moved to another owner or name keep their page, pointed at the new home, rather
+    /// than it being archived and a page added for the new name.
+    async fn find_moves<'s>(&self, synced: &Synced<'s>, index: &mut Index<'s>) {
+        let by_id = synced
+            .repos
+            .iter()
+            .map(|&star| (star.id, star))
+            .collect::<HashMap<u64, &RepoRef>>();
+        let mut left = Vec::new();
+        for page in std::mem::take(&mut index.unstarred) {
+            // only pages that would be archived, and weren't asked about already, are worth a request
+            let archivable = !index.foreign.contains(&page.id.to_string())
+                && !self.args.excludes(&page_full_name(&page))
+                && !sync_off(&page)
+                && !kept(&page)
+                && !manual_row(&page);
+            let repo = match archivable {
+                true => transfer::moved_to(self.notion, &page, &by_id).await,
+                false => None,
+            };
+            match repo {
+                Some(repo) if !index.titles.contains(&repo.name) => index.moved.push((page, repo)),
+                _ => left.push(page),
+            }
+        }
+        index.unstarred = left;
+    }
+
+    async fn apply_moves(&mut self, index: &mut Index<'_>) {
+        let notion = self.notion;
+        let mut moved = std::mem::take(&mut index.moved);
+        moved.sort_by_key(|(page, _)| page_full_name(page));
+        for (page, repo) in moved {
+            let from = page_full_name(&page);
+            index.titles.insert(repo.name.clone());
+            match transfer::apply(notion, &page, repo).await {
+                Ok(()) => {
+                    notion
+                        .ui
+                        .println(format!("moved: {} -> {}", from, full_name(repo)));
+                    self.summary
+                        .transferred
+                        .push(format!("{} -> {}", from, full_name(repo)));
+                }
+                Err(err) => {
+                    let err = SyncError {
+                        operation: "transfer",
+                        repo: full_name(repo),
+                        page_id: Some(page.id.to_string()),
+                        error: err,
+                    };
+                    notion.ui.error(err.to_string());
+                    self.summary.errors.push(err);
+                }
+            }
+        }
+    }
+
+    /// Creates pages for the repos that don't have one. They go out with fresh activity already,
+    /// which is returned so the refresh doesn't look it up again.
+    async fn add(&mut self, synced: &Synced<'_>, index: &Index<'_>) -> Fresh {
+        let notion = self.notion;
+        let adding = !self.targeted && !self.args.skip_add;
+        let mut update_stars = synced
+            .repos
+            .iter()
+            .copied()
+            .filter(|star| adding && !index.titles.contains(&star.name) && self.args.wants(star))
+            .collect::<Vec<&RepoRef>>();
+        update_stars.sort_by_key(|star| full_name(star));
+        update_stars.truncate(self.limit);
+        notion.ui.println(format!(
+            "update_stars: {:?}",
+            update_stars
+                .iter()
+                .map(|page| page.name.clone())
+                .collect::<Vec<String>>()
+        ));
+
+        let to_add = update_stars.len();
+        let failed = self.summary.errors.len();
+        let adding = notion.add_repo(
+            update_stars,
+            &synced.discovered,
+            self.deadline,
+            &mut self.state,
+            self.now,
+            &mut self.summary,
+        );
+        let fresh = telemetry::span("sync.add", vec![], adding).await;
+        self.summary.added = fresh.iter().map(|(name, _)| name.clone()).collect();
+        for name in &self.summary.added {
+            let repo = synced.by_name[name];
+            let message = match synced.discovered.contains(name) {
+                true => format!("discovered: {}", full_name(repo)),
+                false => format!("new star: {}", full_name(repo)),
+            };
+            self.events.push(Event {
+                kind: EventKind::Added,
+                repo: full_name(repo),
+                tags: repo_tags(repo, &notion.dependencies),
+                message,
+            });
+        }
+        // failed creates are tried again next run too, but they didn't stop this one
+        self.summary.incomplete =
+            self.summary.added.len() + self.summary.errors.len() - failed < to_add;
+        fresh.into_iter().collect()
+    }
+
+    /// Archives the pages of unstarred repos, except the ones kept or written by hand.
+    async fn archive(&mut self, unstarred: Vec<Page>) {
+        let notion = self.notion;
+        let (unmatched, delete_stars): (Vec<&Page>, Vec<&Page>) = unstarred
+            .iter()
+            .filter(|_| self.archiving())
+            .filter(|page| !self.args.excludes(&page_full_name(page)))
+            .filter(|page| !sync_off(page))
+            .filter(|page| {
+                let keep = kept(page);
+                if keep {
+                    notion
+                        .ui
+                        .println(format!("kept despite unstar: {}", page.title().unwrap()));
+                }
+                !keep
+            })
+            .partition(|page| manual_row(page));
+        self.summary.unmatched = unmatched.iter().map(|page| page.title().unwrap()).collect();
+        if !self.summary.unmatched.is_empty() {
+            notion.ui.println(format!(
+                "unmatched manual rows (not archived): {:?}",
+                self.summary.unmatched
+            ));
+        }
+
+        notion.ui.println(format!(
+            "delete_stars: {:?}",
+            delete_stars
+                .iter()
+                .map(|page| page.title().unwrap())
+                .collect::<Vec<String>>()
+        ));
+
+        // going private or being deleted takes the star away too, those pages are kept for if it's back
+        let mut archivable = Vec::new();
+        for page in delete_stars {
+            // the rest are archived next run
+            if stop_reason(self.deadline).is_some() {
+                self.summary.incomplete = true;
+                break;
+            }
+            let repo = page_full_name(page);
+            let visible = match repo.split_once('/') {
+                Some((owner, name)) if !owner.is_empty() => {
+                    notion.github.visible(owner, name).await
+                }
+                // nothing to ask GitHub about
+                _ => Some(true),
+            };
+            match visible {
+                Some(true) => archivable.push(page),
+                Some(false) => {
+                    notion.ui.println(format!(
+                        "{} went private or was deleted, kept rather than archived",
+                        repo
+                    ));
+                    notion
+                        .mark_unavailable(page, repo, &mut self.state, &mut self.summary)
+                        .await;
+                }
+                None => notion.ui.println(format!(
+                    "can't tell whether {} is still there, left alone",
+                    repo
+                )),
+            }
+        }
+        let to_archive = archivable.len();
+        let (archived, errors) = telemetry::span(
+            "sync.archive",
+            vec![],
+            notion.archive_repo(archivable, self.deadline),
+        )
+        .await;
+        if archived.len() + errors.len() < to_archive {
+            self.summary.incomplete = true;
+        }
+        for page in unstarred
+            .iter()
+            .filter(|page| archived.contains(&page.title().unwrap()))
+        {
+            let repo = page_full_name(page);
+            self.events.push(Event {
+                kind: EventKind::Archived,
+                message: format!("unstarred, page archived: {}", repo),
+                repo,
+                tags: page_tags(page),
+            });
+        }
+        self.summary.removed = archived;
+        self.summary.errors.extend(errors);
+    }
+
+    /// Brings the pages of the synced repos up to date, pages created this run included.
+    async fn refresh<'s>(
+        &mut self,
+        synced: &Synced<'s>,
+        index: &Index<'_>,
+        fresh: &Fresh,
+    ) -> Refreshed<'s> {
+        let notion = self.notion;
+        // pages created this run are read back with the rest and count towards the limit, the
+        // most out-of-date of the others fill it up
+        let picked = index.stalest(self.limit.saturating_sub(fresh.len()));
+        let to_refresh = match self.args.skip_update {
+            true => 0,
+            false => picked.as_ref().map_or(index.stale.len(), HashSet::len) + fresh.len(),
+        };
+        let pb = notion.ui.bar("updating", to_refresh as u64);
+        // the query goes by something the refresh doesn't write, rows ordered by what's being
+        // written would move across its cursor and be skipped or seen twice
+        let sorts = json!([{ "timestamp": "created_time", "direction": "ascending" }]);
+        let (chunks, received) = mpsc::channel(CHUNKS_AHEAD);
+        let reading = notion.read_database(Some(sorts), chunks);
+        let mut refreshed = Refreshed::default();
+        let mut seen = HashSet::new();
+        let refresh = async {
+            // dropped when this is done, which stops the reading too
+            let mut received = received;
+            'chunks: while let Some(chunk) = received.recv().await {
+                for page in chunk {
+                    let name = page.title().unwrap();
+                    // pages whose archive failed are still there but have no repo to refresh from
+                    let Some(repo) = synced.by_name.get(&name).copied() else {
+                        continue;
+                    };
+                    // rows written meanwhile can come round again further down
+                    if !self.refreshable(&page, repo)
+                        || index.foreign.contains(&page.id.to_string())
+                        || !seen.insert(page.id.to_string())
+                    {
+                        continue;
+                    }
+                    let left_out = picked
+                        .as_ref()
+                        .is_some_and(|picked| !picked.contains(&page.id.to_string()));
+                    if left_out && !fresh.contains_key(&name) {
+                        continue;
+                    }
+                    refreshed.present.insert(full_name(repo));
+                    if let Some(reason) = stop_reason(self.deadline) {
+                        pb.abandon_with_message(reason);
+                        self.summary.incomplete = true;
+                        break 'chunks;
+                    }
+                    pb.set_message(name);
+                    self.refresh_page(&page, repo, synced, fresh, &mut refreshed)
+                        .await;
+                    pb.inc(1);
+                }
+            }
+        };
+        tokio::join!(
+            telemetry::span("sync.read_database", vec![], reading),
+            telemetry::span("sync.refresh", vec![], refresh)
+        );
+        pb.finish_with_message("done");
+        refreshed.limited = picked.is_some();
+        // reported by name like the rest
+        self.summary.updated.sort_by(|a, b| a.name.cmp(&b.name));
+        self.summary.unavailable.sort();
+        refreshed
+    }
+
+    /// Writes what changed for one page, and records what was seen of its repo.
+    async fn refresh_page<'s>(
+        &mut self,
+        page: &Page,
+        repo: &'s RepoRef,
+        synced: &Synced<'s>,
+        fresh: &Fresh,
+        refreshed: &mut Refreshed<'s>,
+    ) {
+        let notion = self.notion;
+        let (args, today) = (self.args, self.today);
+        let schema = notion.schema().await;
+        let name = page.title().unwrap();
+        // repos that went private or were deleted are only checked for coming back
+        let mut back = false;
+        if self.state.unavailable(&full_name(repo))
+            || status_property(page, "Status").as_deref() == Some(status::UNAVAILABLE)
+        {
+            if notion.github.visible(&repo.owner, &name).await != Some(true) {
+                return;
+            }
+            notion
+                .ui
+                .println(format!("{} can be seen again", full_name(repo)));
+            self.state.set_unavailable(&full_name(repo), false);
+            back = true;
+        }
+        // once anything is pinned, only pinned repos get the lookups beyond release and commit
+        // dates, each an extra request per repo
+        let pinning = !notion.config.pinned.is_empty() || schema.contains_key(PINNED);
+        let pinned = notion.config.is_pinned(&full_name(repo))
+            || checkbox_property(page, PINNED) == Some(true);
+        let deep = !args.skip_enrich && (pinned || !pinning);
+        let notion_last_update = date_property(page, "上次release");
+        let notion_commit = date_property(page, "上次commit");
+        // these come with the star listing, so they're refreshed even for repos that aren't due
+        let push = repo.pushed_at.map(|date| date.date_naive());
+        let mut desired = notion.listed(schema, repo);
+        let mut lookup = Lookup::default();
+        let (release, commit) = if let Some(activity) = fresh.get(&name) {
+            *activity
+        } else if self.targeted || pinned || self.state.due(&full_name(repo), today) {
+            let Some(found) = self.look_up(page, repo, deep, &mut desired).await else {
+                return;
+            };
+            lookup = found;
+            (lookup.release, lookup.commit)
+        } else {
+            (notion_last_update, notion_commit)
+        };
+        refreshed
+            .records
+            .push(RepoRecord::new(repo, release, commit));
+        refreshed.snapshots.insert(
+            full_name(repo),
+            Snapshot {
+                release,
+                commit,
+                stars: repo.stargazers_count,
+                archived: repo.archived,
+            },
+        );
+        let status_column = schema.contains_key("Status");
+        // the Unavailable status is replaced once the repo is back, tracked or not
+        if status::enabled() || (back && status_column) {
+            let last_active = [release, commit, push].into_iter().flatten().max();
+            desired.status = Some(status::status(repo, last_active, today));
+        }
+        if synced.discovered.contains(&name) && status_column {
+            desired.status = Some(status::DISCOVERED);
+        }
+        if deep && self.state.contributors_due(&full_name(repo), today) {
+            let owner = &repo.owner;
+            let branch = notion.branch(repo);
+            if let Some(contributors) = notion
+                .github
+                .contributors(owner, &name, branch.as_deref())
+                .await
+            {
+                let count = Cell::Number(contributors.count);
+                desired.set(schema, CONTRIBUTORS, Some(count));
+                let latest = contributors.latest.map(Cell::Text);
+                desired.set(schema, LATEST_CONTRIBUTOR, latest);
+            }
+            self.state.contributors_checked(&full_name(repo), today);
+        }
+        let mut update = if self.targeted {
+            desired.clone()
+        } else {
+            desired.changed_from(page)
+        };
+        update.configured = notion.configured_properties(repo, Some(page)).await;
+        // written once, pages starred before summaries were turned on get theirs here
+        if deep && schema.contains_key(SUMMARY) && blank_property(page, SUMMARY) {
+            if let Some(summary) = notion.summarize(repo).await {
+                update
+                    .configured
+                    .insert(SUMMARY.to_string(), json!({ "rich_text": text(summary) }));
+            }
+        }
+        // pages whose hash matches what GitHub says now are left alone without comparing anything
+        let mut hash_only = false;
+        if schema.contains_key(SYNC_HASH) {
+            let values = PageUpdate {
+                release,
+                commit,
+                ..desired.clone()
+            };
+            let hash = notion.sync_hash(repo, &values, Some(page)).await;
+            if !self.targeted && text_property(page, SYNC_HASH).as_ref() == Some(&hash) {
+                return;
+            }
+            hash_only = update.is_empty();
+            update.sync_hash = Some(hash);
+        }
+        if update.is_empty() {
+            return;
+        } else if !hash_only {
+            notion.ui.println(format!(
+                "{} release: {:?}->{:?}, commit: {:?}->{:?}, writing: {}",
+                name,
+                notion_last_update,
+                update.release,
+                notion_commit,
+                update.commit,
+                update
+                    .properties()
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+        if args.assert_empty_plan {
+            let desired = PageUpdate {
+                sync_hash: None,
+                ..desired
+            };
+            let hash = update.sync_hash.clone();
+            refreshed
+                .written
+                .push((page.id.to_string(), repo, desired, hash));
+        }
+        match notion.update_page(page, &update).await {
+            Ok(()) if hash_only => {}
+            Ok(()) => {
+                if let Some(release) = update
+                    .release
+                    .filter(|&date| Some(date) > notion_last_update)
+                {
+                    let mut tags = event_tags(page, repo, &notion.dependencies);
+                    let mut message = format!("new release of {} on {}", full_name(repo), release);
+                    if let Some(bump) = lookup.bump {
+                        tags.push(bump.to_string());
+                        message = format!("{} ({})", message, bump);
+                    }
+                    self.events.push(Event {
+                        kind: EventKind::Release,
+                        repo: full_name(repo),
+                        tags,
+                        message,
+                    });
+                }
+                if let Some(release) = &lookup.security_release {
+                    self.events.push(Event {
+                        kind: EventKind::Security,
+                        repo: full_name(repo),
+                        tags: event_tags(page, repo, &notion.dependencies),
+                        message: format!(
+                            "security release of {}: {}",
+                            full_name(repo),
+                            release.tag_name
+                        ),
+                    });
+                }
+                self.summary.updated.push(UpdatedRepo {
+                    name,
+                    release: update.release,
+                    commit: update.commit,
+                    push: update.date(LAST_PUSH),
+                    branch: update.text(DEFAULT_BRANCH),
+                })
+            }
+            Err(err) => {
+                let err = SyncError {
+                    operation: "update dates",
+                    repo: full_name(repo),
+                    page_id: Some(page.id.to_string()),
+                    error: err,
+                };
+                notion.ui.error(err.to_string());
+                self.summary.errors.push(err);
+            }
+        }
+    }
+
+    /// Looks up what's new with a repo that's due, filling in `desired`. Nothing when the repo
+    /// went private or was deleted.
+    async fn look_up(
+        &mut self,
+        page: &Page,
+        repo: &RepoRef,
+        deep: bool,
+        desired: &mut PageUpdate,
+    ) -> Option<Lookup> {
+        let notion = self.notion;
+        let schema = notion.schema().await;
+        let (name, today) = (page.title().unwrap(), self.today);
+        // what the page has, as long as it's of the tag seen last
+        let tag = self.state.release_tag(&full_name(repo), None);
+        let dated = date_property(page, "上次release");
+        let known = tag.as_deref().zip(dated);
+        let latest = notion.latest_release(repo, known).await;
+        let release = latest.as_ref().and_then(release_date);
+        let commit = notion.latest_commit(repo).await;
+        // nothing found can also be GitHub turning the lookups away
+        if latest.is_none()
+            && commit.is_none()
+            && notion.github.visible(&repo.owner, &name).await == Some(false)
+        {
+            notion.ui.println(format!(
+                "{} went private or was deleted, skipped until it's back",
+                full_name(repo)
+            ));
+            notion
+                .mark_unavailable(page, full_name(repo), &mut self.state, &mut self.summary)
+                .await;
+            return None;
+        }
+        // only asked for when there's a column for it, it's an extra request per repo
+        let discussion = match deep && DISCUSSION.exists(schema) {
+            true => notion.latest_discussion(repo).await,
+            false => None,
+        };
+        let image = match deep && LATEST_IMAGE.exists(schema) {
+            true => notion.latest_image(repo).await,
+            false => None,
+        };
+        self.state.observe(
+            &full_name(repo),
+            &[release, commit, discussion, image],
+            today,
+        );
+        desired.set(
+            schema,
+            DISCUSSION,
+            discussion.map(|date| Cell::Date(Some(date))),
+        );
+        desired.set(
+            schema,
+            LATEST_IMAGE,
+            image.map(|date| Cell::Date(Some(date))),
+        );
+        if deep && HOMEBREW.exists(schema) {
+            let version = packages::homebrew(&notion.http, repo).await;
+            let version = notion.packaged("Homebrew", repo, version);
+            desired.set(schema, HOMEBREW, version.map(Cell::Text));
+        }
+        if deep && AUR.exists(schema) {
+            let version = packages::aur(&notion.http, repo).await;
+            let version = notion.packaged("AUR", repo, version);
+            desired.set(schema, AUR, version.map(Cell::Text));
+        }
+        if deep && DISTROS.exists(schema) {
+            let prefix = notion.config.repo(&full_name(repo)).tag_prefix;
+            let tag = latest.as_ref().map(|release| release.tag_name.as_str());
+            match repology::status(
+                &notion.http,
+                notion.config.cache_dir.as_deref(),
+                &full_name(repo),
+                tag,
+                &prefix.unwrap_or_default(),
+            )
+            .await
+            {
+                Ok(status) => {
+                    let status = Cell::Select(status.to_string());
+                    desired.set(schema, DISTROS, Some(status));
+                }
+                Err(err) => notion.ui.error(format!(
+                    "failed to look up {} on Repology: {}",
+                    full_name(repo),
+                    err
+                )),
+            }
+        }
+        if deep && (NEXT_MILESTONE.exists(schema) || MILESTONE_DUE.exists(schema)) {
+            let owner = &repo.owner;
+            let milestone = notion.github.next_milestone(owner, &name).await;
+            // cleared once the milestone is closed
+            let title =
+                milestone
+                    .as_ref()
+                    .map_or(String::new(), |milestone| match milestone.due_on {
+                        Some(due) => format!("{} — due {}", milestone.title, due),
+                        None => milestone.title.clone(),
+                    });
+            desired.set(schema, NEXT_MILESTONE, Some(Cell::Text(title)));
+            let due = milestone.and_then(|milestone| milestone.due_on);
+            desired.set(schema, MILESTONE_DUE, Some(Cell::Date(due)));
+        }
+        let tag = latest.as_ref().map(|release| release.tag_name.as_str());
+        let mut bump = None;
+        let previous = self.state.release_tag(&full_name(repo), tag);
+        if let (Some(previous), Some(tag)) = (previous, tag) {
+            if previous != tag {
+                let prefix = notion.config.repo(&full_name(repo)).tag_prefix;
+                bump = Some(bump::bump(&previous, tag, &prefix.unwrap_or_default()));
+            }
+        }
+        desired.set(
+            schema,
+            BREAKING,
+            bump.map(|bump| Cell::Select(bump.to_string())),
+        );
+        let security = notion.is_security(latest.as_ref());
+        desired.set(schema, SECURITY_RELEASE, Some(Cell::Checkbox(security)));
+        if CHANGELOG.exists(schema) {
+            let changelog = notion.changelog(latest.as_ref()).await;
+            desired.set(schema, CHANGELOG, changelog.map(Cell::Text));
+        }
+        let notion_last_update = date_property(page, "上次release");
+        let security_release = latest.filter(|_| security && release > notion_last_update);
+        desired.release = release;
+        desired.commit = commit;
+        if deep && env::var("TRACK_OPEN_PRS").is_ok() {
+            let owner = &repo.owner;
+            if let Some(prs) = notion.github.open_pull_requests(owner, &name).await {
+                // open_issues_count counts pull requests as issues too
+                let issues = u64::from(repo.open_issues_count).saturating_sub(prs);
+                desired.set(schema, OPEN_PRS, Some(Cell::Number(prs)));
+                desired.set(schema, OPEN_ISSUES, Some(Cell::Number(issues)));
+            }
+        }
+        Some(Lookup {
+            release,
+            commit,
+            bump,
+            security_release,
+        })
+    }
+
+    /// Compares what the refresh saw against the last run's snapshots. Repos this run didn't look
+    /// at keep their last snapshot, unstarred ones are dropped by runs that saw every repo.
+    fn record_changes(&mut self, synced: &Synced<'_>, snapshots: BTreeMap<String, Snapshot>) {
+        self.summary.changes = changes::diff(&self.state.snapshot, &snapshots);
+        self.state.snapshot.extend(snapshots);
+        let starred = synced
+            .by_name
+            .values()
+            .map(|&repo| (full_name(repo), repo))
+            .collect::<HashMap<String, &RepoRef>>();
+        // a webhook's refresh goes without the search results
+        if !self.targeted {
+            self.state
+                .snapshot
+                .retain(|repo, _| starred.contains_key(repo));
+        }
+        for change in &self.summary.changes {
+            let (kind, repo) = match change {
+                Change::StarJump { repo, .. } => (EventKind::StarJump, repo),
+                Change::Archived { repo } => (EventKind::UpstreamArchived, repo),
+                // sent as `release` events once the page has it
+                Change::Release { .. } => continue,
+            };
+            self.events.push(Event {
+                kind,
+                repo: repo.clone(),
+                tags: repo_tags(starred[repo], &self.notion.dependencies),
+                message: change.to_string(),
+            });
+        }
+    }
+
+    /// Sends the run's events, and keeps the changelog, the owners and the dashboard up to date.
+    async fn notify(&mut self, muted: &HashSet<String>) {
+        let notion = self.notion;
+        if let Some(err) = self.summary.errors.first() {
+            self.events.push(notify::failure(format!(
+                "notionstar sync had {} errors, the first: {}",
+                self.summary.errors.len(),
+                err
+            )));
+        }
+        if let Some(path) = &self.args.changelog {
+            if let Err(err) = journal::append(path, self.today, &self.events) {
+                notion.ui.error(format!(
+                    "failed to write changelog {}: {}",
+                    path.display(),
+                    err
+                ));
+            }
+        }
+        let events = std::mem::take(&mut self.events);
+        let dispatch = notify::dispatch(notion, &mut self.state, events, muted, self.now);
+        telemetry::span("sync.notify", vec![], dispatch).await;
+        let owners = owners::update(notion, &mut self.state);
+        telemetry::span("sync.owners", vec![], owners).await;
+        let dashboard = dashboard::update(notion, &mut self.state, self.today);
+        telemetry::span("sync.dashboard", vec![], dashboard).await;
+    }
+
+    /// Gives the stars back to the state and saves it.
+    fn keep_stars(&mut self, stars: Vec<RepoRef>) {
+        match (self.full, self.listed_all) {
+            (true, true) => self.state.full_synced(self.now, stars),
+            (false, _) => self.state.starred = stars,
+            // a listing cut short leaves the last whole one in place
+            (true, false) => {}
+        }
+        self.state.save();
+    }
+
+    /// Mirrors the records to the targets and puts the summary out.
+    async fn report(
+        mut self,
+        targets: Vec<Target>,
+        records: &[RepoRecord],
+        limited: bool,
+        present: &HashSet<String>,
+    ) -> Summary {
+        let notion = self.notion;
+        let summary = &mut self.summary;
+        summary.interrupted = shutdown::requested();
+        // pages the refresh didn't get to aren't gone
+        let read_all = !self.args.skip_update
+            && !self.targeted
+            && !limited
+            && !summary.incomplete
+            && !summary.interrupted;
+        for target in targets {
+            target.write(records);
+            if read_all {
+                target.prune(present);
+            }
+            notion.ui.println(format!(
+                "mirrored {} repos to {}",
+                records.len(),
+                target.path.display()
+            ));
+        }
+        summary.api_calls = notion.calls.take();
+        summary.rate_limit = notion.github.rate_limit().await;
+        if let Ok(url) = env::var("WEBHOOK_URL") {
+            let post = summary.post(&notion.http, &url);
+            if let Err(err) = telemetry::span("webhook", vec![], post).await {
+                notion
+                    .ui
+                    .error(format!("failed to post summary to webhook: {}", err));
+            }
+        }
+        match self.args.json {
+            true => println!("{}", serde_json::to_string_pretty(&summary).unwrap()),
+            false => println!("{}", summary),
+        }
+        self.summary
+    }
+}
+
+/// What a run right after this one would still do. Writes showing up here are of values that
+/// don't read back the way they were written, which every run would write again.
+async fn second_plan(
+    notion: &Notion,
+    args: &SyncArgs,
+    star_map: &HashMap<String, &RepoRef>,
+    written: Vec<Written<'_>>,
+) -> Vec<String> {
+    let database = notion.get_database().await;
+    let titles = database
+        .iter()
+        .map(|page| page.title().unwrap())
+        .collect::<HashSet<String>>();
+    let mut plan = Vec::new();
+    let targeted = !args.only.is_empty();
+    if !targeted && !args.skip_add {
+        for repo in star_map.values() {
+            if !titles.contains(&repo.name) && args.wants(repo) {
+                plan.push(format!("add {}", full_name(repo)));
+            }
+        }
+    }
+    if !targeted && !args.skip_archive {
+        for page in &database {
+            let repo = page_full_name(page);
+            if !star_map.contains_key(&page.title().unwrap())
+                && !args.excludes(&repo)
+                && !sync_off(page)
+                && !kept(page)
+                && !manual_row(page)
+            {
+                plan.push(format!("archive {}", repo));
+            }
+        }
+    }
+    let pages = database
+        .iter()
+        .map(|page| (page.id.to_string(), page))
+        .collect::<HashMap<String, &Page>>();
+    for (id, repo, desired, hash) in written {
+        let Some(page) = pages.get(&id) else {
+            continue;
+        };
+        let mut update = desired.changed_from(page);
+        update.configured = notion.configured_properties(repo, Some(page)).await;
+        update.sync_hash =
+            hash.filter(|hash| text_property(page, SYNC_HASH).as_ref() != Some(hash));
+        if !update.is_empty() {
+            let columns = update.properties().keys().cloned().collect::<Vec<String>>();
+            plan.push(format!("write {}: {}", full_name(repo), columns.join(", ")));
+        }
+    }
+    plan.sort();
+    plan
+}
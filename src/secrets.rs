@@ -0,0 +1,30 @@
+use std::{env, fs};
+
+/// Variables that can also be read from the file named by `<NAME>_FILE`, the way Docker secrets
+/// and Kubernetes secret volumes hand them over, so they don't show in `docker inspect`.
+const FROM_FILE: [&str; 7] = [
+    "NOTION_API",
+    "NOTION_CLIENT_SECRET",
+    "GITHUB_API",
+    "DATABASE",
+    "GISTS_DATABASE",
+    "WEBHOOK_URL",
+    "LLM_API_KEY",
+];
+
+/// Sets each variable from its `_FILE`, with the trailing newline editors and `echo` leave. Both
+/// forms at once is an error rather than a guess at which one is meant.
+pub fn load_files() -> Result<(), String> {
+    for name in FROM_FILE {
+        let Ok(path) = env::var(format!("{}_FILE", name)) else {
+            continue;
+        };
+        if env::var(name).is_ok() {
+            return Err(format!("both {} and {}_FILE are set", name, name));
+        }
+        let value = fs::read_to_string(&path)
+            .map_err(|err| format!("failed to read {}_FILE {}: {}", name, path, err))?;
+        env::set_var(name, value.trim_end_matches(['\r', '\n']));
+    }
+    Ok(())
+}
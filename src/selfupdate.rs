@@ -0,0 +1,148 @@
+use crate::ui::Ui;
+use ring::digest;
+use semver::Version;
+use serde::Deserialize;
+use std::{env, fs, time::Duration};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/sena-nana/notionstar/releases/latest";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+fn current_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).unwrap()
+}
+
+fn tag_version(tag: &str) -> Option<Version> {
+    Version::parse(tag.trim_start_matches('v')).ok()
+}
+
+async fn latest_release(http: &reqwest::Client) -> Result<Release, reqwest::Error> {
+    let url = env::var("NOTIONSTAR_RELEASES_URL").unwrap_or(RELEASES_URL.to_string());
+    http.get(url)
+        .header("User-Agent", "notionstar")
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Release>()
+        .await
+}
+
+/// Release binaries are named like `notionstar-x86_64-linux` / `notionstar-aarch64-macos` /
+/// `notionstar-x86_64-windows.exe`, after `std::env::consts::{ARCH, OS}`.
+fn asset_name() -> String {
+    format!(
+        "notionstar-{}-{}{}",
+        env::consts::ARCH,
+        env::consts::OS,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+fn asset<'a>(release: &'a Release, name: &str) -> Option<&'a Asset> {
+    release.assets.iter().find(|asset| asset.name == name)
+}
+
+async fn download(http: &reqwest::Client, asset: &Asset) -> Result<Vec<u8>, reqwest::Error> {
+    let bytes = http
+        .get(&asset.browser_download_url)
+        .header("User-Agent", "notionstar")
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(bytes.to_vec())
+}
+
+/// Whether `binary` hashes to the digest in a `sha256sum` style checksum file.
+fn checksum_matches(binary: &[u8], checksum: &[u8]) -> bool {
+    let expected = String::from_utf8_lossy(checksum);
+    let Some(expected) = expected.split_whitespace().next() else {
+        return false;
+    };
+    let actual = hex::encode(digest::digest(&digest::SHA256, binary));
+    expected.eq_ignore_ascii_case(&actual)
+}
+
+/// Prints a one-line hint when a newer release exists; set `NOTIONSTAR_NO_UPDATE_CHECK` to opt out.
+pub async fn notify_if_outdated() {
+    if env::var("NOTIONSTAR_NO_UPDATE_CHECK").is_ok() {
+        return;
+    }
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .unwrap();
+    if let Ok(release) = latest_release(&http).await {
+        if tag_version(&release.tag_name).is_some_and(|latest| latest > current_version()) {
+            eprintln!(
+                "notionstar {} is available (running {}), run `notionstar self-update` to upgrade",
+                release.tag_name,
+                current_version()
+            );
+        }
+    }
+}
+
+/// Replaces the running binary with the latest release's, once its checksum matches. Nothing is
+/// installed on an error.
+pub async fn self_update(ui: &Ui) -> Result<(), String> {
+    let http = reqwest::Client::new();
+    let release = latest_release(&http)
+        .await
+        .map_err(|err| format!("can't read the latest release: {}", err))?;
+    let latest = tag_version(&release.tag_name)
+        .ok_or_else(|| format!("latest release {} isn't a version", release.tag_name))?;
+    if latest <= current_version() {
+        ui.println(format!("already up to date ({})", current_version()));
+        return Ok(());
+    }
+    let name = asset_name();
+    let (Some(binary), Some(checksum)) = (
+        asset(&release, &name),
+        asset(&release, &format!("{}.sha256", name)),
+    ) else {
+        return Err(format!(
+            "release {} has no {} with a {}.sha256 next to it",
+            release.tag_name, name, name
+        ));
+    };
+    ui.println(format!("downloading {}", binary.name));
+    let failed = |err: reqwest::Error| format!("download failed: {}", err);
+    let checksum = download(&http, checksum).await.map_err(failed)?;
+    let binary = download(&http, binary).await.map_err(failed)?;
+    if !checksum_matches(&binary, &checksum) {
+        return Err(format!(
+            "{} doesn't match its checksum, not installed",
+            name
+        ));
+    }
+    let exe =
+        env::current_exe().map_err(|err| format!("can't find the running binary: {}", err))?;
+    let new = exe.with_extension("new");
+    let install = || -> std::io::Result<()> {
+        fs::write(&new, &binary)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+        }
+        // windows can't overwrite a running executable but can rename it out of the way
+        #[cfg(windows)]
+        fs::rename(&exe, exe.with_extension("old"))?;
+        fs::rename(&new, &exe)
+    };
+    install().map_err(|err| format!("can't replace {}: {}", exe.display(), err))?;
+    ui.println(format!("updated {} -> {}", current_version(), latest));
+    Ok(())
+}
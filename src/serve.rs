@@ -0,0 +1,317 @@
+use crate::{bot, config::NotifierConfig, notify, run, shutdown, telemetry, Notion};
+use ring::hmac;
+use serde_json::Value;
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        OwnedSemaphorePermit, Semaphore,
+    },
+};
+
+/// Webhook events that mean a repo's release or commit dates may have changed.
+const REFRESH_EVENTS: [&str; 2] = ["release", "push"];
+/// The largest payload GitHub sends.
+const MAX_BODY: usize = 25 * 1024 * 1024;
+/// Connections whose request hasn't come in whole by then are dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections being read or waiting their turn at once, each can hold up to `MAX_BODY`. More
+/// are answered 503 before anything is read.
+const MAX_CONNECTIONS: usize = 16;
+
+/// A request read whole, holding one of the `MAX_CONNECTIONS` until it's answered.
+type Connection = (TcpStream, Request, OwnedSemaphorePermit);
+
+pub(crate) struct Request {
+    pub path: String,
+    /// what followed `?`, still percent-encoded
+    pub query: String,
+    /// lowercase names
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// Answers GitHub webhooks: a `release` or `push` event refreshes the page of the repo it's about
+/// right away, that page alone, with the stars the last run listed. Deliveries must be signed with
+/// `GITHUB_WEBHOOK_SECRET`, and only the repo name is taken from the payload: everything written
+/// is fetched from GitHub again. Requests are handled one at a time so runs never overlap.
+///
+/// Updates posted to `/telegram` are commands for a `telegram` notifier's bot.
+///
+/// `/healthz` and `/readyz` are answered right away, even while a refresh runs. After Ctrl-C or
+/// SIGTERM nothing new is taken and the refresh in flight stops after the page it's writing.
+pub async fn serve(notion: &Notion, listener: TcpListener) {
+    if env::var("GITHUB_WEBHOOK_SECRET").is_err() {
+        notion
+            .ui
+            .error("GITHUB_WEBHOOK_SECRET isn't set, GitHub webhook deliveries are turned away");
+    }
+    let (queue, mut requests) = mpsc::unbounded_channel();
+    let mut incoming = Incoming::new(listener);
+    let accept = async move {
+        loop {
+            let (mut stream, request, slot) = tokio::select! {
+                _ = shutdown::wait() => break,
+                request = incoming.next(notion) => request,
+            };
+            if !probe(notion, &mut stream, &request.path).await {
+                queue.send((stream, request, slot)).ok();
+            }
+        }
+    };
+    let work = async {
+        while let Some((mut stream, request, _slot)) = requests.recv().await {
+            if shutdown::requested() {
+                respond(&mut stream, "503 Service Unavailable").await;
+                continue;
+            }
+            handle(notion, stream, request).await;
+        }
+    };
+    tokio::join!(accept, work);
+}
+
+/// Only answers `/healthz` and `/readyz`, for `daemon`, which has no webhooks to take.
+pub async fn health(notion: &Notion, listener: TcpListener) {
+    let mut incoming = Incoming::new(listener);
+    loop {
+        let (mut stream, request, _slot) = tokio::select! {
+            _ = shutdown::wait() => return,
+            request = incoming.next(notion) => request,
+        };
+        if !probe(notion, &mut stream, &request.path).await {
+            respond(&mut stream, "404 Not Found").await;
+        }
+    }
+}
+
+/// `/healthz` is fine while the process runs, `/readyz` once the database's schema could be read
+/// and until a shutdown starts. Other paths are left to the caller.
+async fn probe(notion: &Notion, stream: &mut TcpStream, path: &str) -> bool {
+    let ready = match path {
+        "/healthz" => true,
+        "/readyz" => !shutdown::requested() && !notion.schema().await.is_empty(),
+        _ => return false,
+    };
+    respond(
+        stream,
+        if ready {
+            "200 OK"
+        } else {
+            "503 Service Unavailable"
+        },
+    )
+    .await;
+    true
+}
+
+/// Requests as they come in whole. Each connection is read on a task of its own, so a slow or
+/// idle client holds up nobody else.
+struct Incoming {
+    listener: TcpListener,
+    read: UnboundedSender<Connection>,
+    requests: UnboundedReceiver<Connection>,
+    slots: Arc<Semaphore>,
+}
+
+impl Incoming {
+    fn new(listener: TcpListener) -> Incoming {
+        let (read, requests) = mpsc::unbounded_channel();
+        Incoming {
+            listener,
+            read,
+            requests,
+            slots: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
+        }
+    }
+
+    async fn next(&mut self, notion: &Notion) -> Connection {
+        loop {
+            tokio::select! {
+                connection = self.listener.accept() => match connection {
+                    Ok((mut stream, _)) => match self.slots.clone().try_acquire_owned() {
+                        Ok(slot) => {
+                            tokio::spawn(read(stream, slot, self.read.clone()));
+                        }
+                        Err(_) => {
+                            tokio::spawn(async move {
+                                respond(&mut stream, "503 Service Unavailable").await
+                            });
+                        }
+                    },
+                    Err(err) => notion
+                        .ui
+                        .error(format!("failed to accept connection: {}", err)),
+                },
+                // never closed, `self` holds a sender
+                Some(request) = self.requests.recv() => return request,
+            }
+        }
+    }
+}
+
+async fn read(
+    mut stream: TcpStream,
+    slot: OwnedSemaphorePermit,
+    requests: UnboundedSender<Connection>,
+) {
+    match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Some(request)) => {
+            requests.send((stream, request, slot)).ok();
+        }
+        Ok(None) => respond(&mut stream, "400 Bad Request").await,
+        Err(_) => respond(&mut stream, "408 Request Timeout").await,
+    }
+}
+
+/// Whether a GitHub delivery carries the `X-Hub-Signature-256` of its body under
+/// `GITHUB_WEBHOOK_SECRET`. Without a secret nothing is taken.
+fn signed(request: &Request) -> bool {
+    let Ok(secret) = env::var("GITHUB_WEBHOOK_SECRET") else {
+        return false;
+    };
+    let signature = request
+        .headers
+        .get("x-hub-signature-256")
+        .and_then(|signature| signature.strip_prefix("sha256="))
+        .and_then(|signature| hex::decode(signature).ok());
+    let Some(signature) = signature else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, &request.body, &signature).is_ok()
+}
+
+/// A webhook delivery or bot command, answered and then acted on.
+async fn handle(notion: &Notion, mut stream: TcpStream, request: Request) {
+    if request.path == "/telegram" {
+        telegram(notion, stream, request).await;
+        return;
+    }
+    if !signed(&request) {
+        respond(&mut stream, "401 Unauthorized").await;
+        return;
+    }
+    let event = request
+        .headers
+        .get("x-github-event")
+        .cloned()
+        .unwrap_or_default();
+    let repo = REFRESH_EVENTS
+        .contains(&event.as_str())
+        .then(|| serde_json::from_slice::<Value>(&request.body).ok())
+        .flatten()
+        .and_then(|payload| {
+            payload["repository"]["full_name"]
+                .as_str()
+                .map(str::to_string)
+        });
+    let Some(repo) = repo else {
+        // `ping` and anything else not worth a refresh
+        respond(&mut stream, "204 No Content").await;
+        return;
+    };
+    // GitHub gives up on a delivery after 10 seconds, so answer before refreshing
+    respond(&mut stream, "202 Accepted").await;
+    drop(stream);
+    notion
+        .ui
+        .println(format!("{} event for {}, refreshing", event, repo));
+    telemetry::span("refresh", vec![], run::refresh_repo(notion, &repo)).await;
+}
+
+/// Runs a bot command, see `bot::answer`. Only messages from the configured chat count, and only
+/// requests carrying the bot's `secret`.
+async fn telegram(notion: &Notion, mut stream: TcpStream, request: Request) {
+    let update = serde_json::from_slice::<Value>(&request.body).unwrap_or_default();
+    let chat = update["message"]["chat"]["id"].to_string();
+    let text = update["message"]["text"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    // bots without a secret take no commands, anyone could post to the endpoint as them
+    let token = request.headers.get("x-telegram-bot-api-secret-token");
+    let bot = bot::commanded(notion)
+        .map(|(_, config)| config)
+        .find(|config| {
+            matches!(
+                config,
+                NotifierConfig::Telegram { chat: allowed, secret: Some(secret), .. }
+                    if *allowed == chat && token == Some(secret)
+            )
+        });
+    let Some(bot) = bot else {
+        respond(&mut stream, "403 Forbidden").await;
+        return;
+    };
+    // Telegram redelivers anything not answered promptly
+    respond(&mut stream, "200 OK").await;
+    drop(stream);
+    let reply = bot::answer(notion, &text).await;
+    let bot = notify::telegram(bot, &notion.http).unwrap();
+    if let Err(err) = bot.say(reply).await {
+        notion
+            .ui
+            .error(format!("failed to answer on Telegram: {}", err));
+    }
+}
+
+pub(crate) async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await.ok()?;
+    // `POST /path HTTP/1.1`
+    let path = request_line.split_whitespace().nth(1)?;
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let (path, query) = (path.to_string(), query.to_string());
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let length = match headers.get("content-length") {
+        Some(length) => length.parse().ok()?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return None;
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.ok()?;
+    Some(Request {
+        path,
+        query,
+        headers,
+        body,
+    })
+}
+
+/// Answers with a line of text, for a person looking at the browser tab.
+pub(crate) async fn respond_text(stream: &mut TcpStream, status: &str, text: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        text.len(),
+        text
+    );
+    stream.write_all(response.as_bytes()).await.ok();
+}
+
+async fn respond(stream: &mut TcpStream, status: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status
+    );
+    stream.write_all(response.as_bytes()).await.ok();
+}
@@ -0,0 +1,250 @@
+use crate::ui::Ui;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Name of the unit, agent label or scheduled task.
+const NAME: &str = "notionstar";
+const LAUNCHD_LABEL: &str = "io.github.sena-nana.notionstar";
+
+/// What the service runs: `notionstar daemon` from the current directory, so the `.env`, config
+/// file and state file found here now are the ones it uses.
+struct Service {
+    exe: PathBuf,
+    dir: PathBuf,
+    /// `CONFIG_FILE` as an absolute path, when it's set
+    config: Option<PathBuf>,
+}
+
+impl Service {
+    fn current() -> Service {
+        let dir = env::current_dir().unwrap();
+        Service {
+            exe: env::current_exe().unwrap(),
+            config: env::var("CONFIG_FILE").ok().map(|config| dir.join(config)),
+            dir,
+        }
+    }
+}
+
+/// Runs a service manager command, reporting instead of failing so the commands after it still
+/// run. Returns whether it went through.
+fn run(ui: &Ui, program: &str, args: &[&str]) -> bool {
+    match Command::new(program).args(args).status() {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            ui.error(format!(
+                "{} {} exited with {}",
+                program,
+                args.join(" "),
+                status
+            ));
+            false
+        }
+        Err(err) => {
+            ui.error(format!("failed to run {}: {}", program, err));
+            false
+        }
+    }
+}
+
+/// Writes the unit, agent or task file, exiting when it can't.
+fn write(ui: &Ui, path: &Path, content: String) {
+    let written =
+        fs::create_dir_all(path.parent().unwrap()).and_then(|()| fs::write(path, content));
+    if let Err(err) = written {
+        ui.error(format!("failed to write {}: {}", path.display(), err));
+        std::process::exit(1);
+    }
+    ui.println(format!("wrote {}", path.display()));
+}
+
+/// Exits non-zero when a service manager command failed, after all of them had their go.
+fn done(ok: bool) {
+    if !ok {
+        std::process::exit(1);
+    }
+}
+
+fn home() -> PathBuf {
+    PathBuf::from(env::var("HOME").unwrap())
+}
+
+fn systemd_path() -> PathBuf {
+    let config = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or(home().join(".config"));
+    config.join(format!("systemd/user/{}.service", NAME))
+}
+
+fn systemd_unit(service: &Service) -> String {
+    let environment = match &service.config {
+        Some(config) => format!("Environment=\"CONFIG_FILE={}\"\n", config.display()),
+        None => String::new(),
+    };
+    format!(
+        "[Unit]
+Description=Sync GitHub stars into Notion
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+ExecStart=\"{}\" daemon --quiet
+WorkingDirectory={}
+{}Restart=on-failure
+RestartSec=60
+
+[Install]
+WantedBy=default.target
+",
+        service.exe.display(),
+        service.dir.display(),
+        environment
+    )
+}
+
+fn launchd_path() -> PathBuf {
+    home().join(format!("Library/LaunchAgents/{}.plist", LAUNCHD_LABEL))
+}
+
+fn launchd_plist(service: &Service) -> String {
+    let escape = |path: &Path| {
+        path.display()
+            .to_string()
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let environment = match &service.config {
+        Some(config) => format!(
+            "  <key>EnvironmentVariables</key>
+  <dict>
+    <key>CONFIG_FILE</key>
+    <string>{}</string>
+  </dict>
+",
+            escape(config)
+        ),
+        None => String::new(),
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{}</string>
+  <key>ProgramArguments</key>
+  <array>
+    <string>{}</string>
+    <string>daemon</string>
+    <string>--quiet</string>
+  </array>
+  <key>WorkingDirectory</key>
+  <string>{}</string>
+{}  <key>RunAtLoad</key>
+  <true/>
+  <key>KeepAlive</key>
+  <dict>
+    <key>SuccessfulExit</key>
+    <false/>
+  </dict>
+  <key>StandardErrorPath</key>
+  <string>{}</string>
+</dict>
+</plist>
+"#,
+        LAUNCHD_LABEL,
+        escape(&service.exe),
+        escape(&service.dir),
+        environment,
+        escape(&service.dir.join("notionstar.log"))
+    )
+}
+
+/// Installs `notionstar daemon` to start with the session: a systemd user unit on Linux, a
+/// launchd agent on macOS, a scheduled task run at logon on Windows.
+pub fn install(ui: &Ui) {
+    let service = Service::current();
+    let ok = match env::consts::OS {
+        "macos" => {
+            let path = launchd_path();
+            write(ui, &path, launchd_plist(&service));
+            run(ui, "launchctl", &["load", "-w", &path.to_string_lossy()])
+        }
+        "windows" => {
+            let command = format!(
+                "cmd /c cd /d \"{}\" && \"{}\" daemon --quiet",
+                service.dir.display(),
+                service.exe.display()
+            );
+            if service.config.is_some() {
+                ui.error("CONFIG_FILE isn't passed on to the task, put it in .env instead");
+            }
+            run(
+                ui,
+                "schtasks",
+                &[
+                    "/Create", "/TN", NAME, "/TR", &command, "/SC", "ONLOGON", "/F",
+                ],
+            ) && run(ui, "schtasks", &["/Run", "/TN", NAME])
+        }
+        _ => {
+            let path = systemd_path();
+            write(ui, &path, systemd_unit(&service));
+            let ok = run(ui, "systemctl", &["--user", "daemon-reload"])
+                && run(
+                    ui,
+                    "systemctl",
+                    &["--user", "enable", "--now", &format!("{}.service", NAME)],
+                );
+            ui.println(format!(
+                "logs: journalctl --user -u {}; to keep it running while logged out: loginctl enable-linger",
+                NAME
+            ));
+            ok
+        }
+    };
+    done(ok);
+}
+
+/// Stops the service and removes what `install` wrote.
+pub fn uninstall(ui: &Ui) {
+    let ok = match env::consts::OS {
+        "macos" => {
+            let path = launchd_path();
+            let unloaded = run(ui, "launchctl", &["unload", "-w", &path.to_string_lossy()]);
+            remove(ui, &path) && unloaded
+        }
+        "windows" => {
+            // a task that isn't running can't be ended, which is fine on the way out
+            run(ui, "schtasks", &["/End", "/TN", NAME]);
+            run(ui, "schtasks", &["/Delete", "/TN", NAME, "/F"])
+        }
+        _ => {
+            let disabled = run(
+                ui,
+                "systemctl",
+                &["--user", "disable", "--now", &format!("{}.service", NAME)],
+            );
+            let removed = remove(ui, &systemd_path());
+            run(ui, "systemctl", &["--user", "daemon-reload"]) && disabled && removed
+        }
+    };
+    done(ok);
+}
+
+fn remove(ui: &Ui, path: &Path) -> bool {
+    match fs::remove_file(path) {
+        Ok(()) => {
+            ui.println(format!("removed {}", path.display()));
+            true
+        }
+        Err(err) => {
+            ui.error(format!("failed to remove {}: {}", path.display(), err));
+            false
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    OnceLock,
+};
+use tokio::{signal::ctrl_c, sync::Notify};
+
+/// Exit code for a run stopped by Ctrl-C/SIGTERM, after its progress was saved.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static NOTIFY: OnceLock<Notify> = OnceLock::new();
+
+fn notify() -> &'static Notify {
+    NOTIFY.get_or_init(Notify::new)
+}
+
+/// The first signal asks the sync loops to stop after the page in flight; a second one aborts.
+pub fn listen() {
+    tokio::spawn(async {
+        wait_for_signal().await;
+        request();
+        eprintln!("stopping after the current page, press Ctrl-C again to abort");
+        wait_for_signal().await;
+        std::process::exit(EXIT_INTERRUPTED);
+    });
+}
+
+/// Same as the first Ctrl-C, for front ends that take the terminal's keys themselves.
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+    notify().notify_waiters();
+}
+
+/// Returns once a stop was asked for.
+pub async fn wait() {
+    let notified = notify().notified();
+    tokio::pin!(notified);
+    // registered before the check, so a request in between isn't missed
+    notified.as_mut().enable();
+    if requested() {
+        return;
+    }
+    notified.await;
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate()).unwrap();
+    tokio::select! {
+        _ = ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    ctrl_c().await.unwrap();
+}
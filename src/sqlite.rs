@@ -0,0 +1,74 @@
+use crate::target::RepoRecord;
+use rusqlite::{params, types::ValueRef, Connection};
+use std::path::Path;
+
+pub fn write(path: &Path, records: &[RepoRecord]) {
+    let mut conn = Connection::open(path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS repos (
+            owner TEXT NOT NULL,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            description TEXT,
+            language TEXT,
+            topics TEXT NOT NULL,
+            stars INTEGER NOT NULL,
+            last_release TEXT,
+            last_commit TEXT,
+            PRIMARY KEY (owner, name)
+        );",
+    )
+    .unwrap();
+    let tx = conn.transaction().unwrap();
+    // rebuild from the current star list so unstarred repos disappear like they do in Notion
+    tx.execute("DELETE FROM repos", []).unwrap();
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO repos (owner, name, url, description, language, topics, stars, last_release, last_commit)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )
+            .unwrap();
+        for record in records {
+            insert
+                .execute(params![
+                    record.owner,
+                    record.name,
+                    record.url,
+                    record.description,
+                    record.language,
+                    record.topics.join(","),
+                    record.stars,
+                    record.release.map(|date| date.to_string()),
+                    record.commit.map(|date| date.to_string()),
+                ])
+                .unwrap();
+        }
+    }
+    tx.commit().unwrap();
+}
+
+pub fn query(path: &Path, sql: &str) {
+    let conn = Connection::open(path).unwrap();
+    let mut stmt = conn.prepare(sql).unwrap();
+    let columns = stmt
+        .column_names()
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<String>>();
+    println!("{}", columns.join("\t"));
+    let mut rows = stmt.query([]).unwrap();
+    while let Some(row) = rows.next().unwrap() {
+        let values = (0..columns.len())
+            .map(|i| match row.get_ref(i).unwrap() {
+                ValueRef::Null => String::new(),
+                ValueRef::Integer(value) => value.to_string(),
+                ValueRef::Real(value) => value.to_string(),
+                ValueRef::Text(value) | ValueRef::Blob(value) => {
+                    String::from_utf8_lossy(value).to_string()
+                }
+            })
+            .collect::<Vec<String>>();
+        println!("{}", values.join("\t"));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{changes::Snapshot, notify::Digest, repo::RepoRef};
+use notion::chrono::{DateTime, Duration, NaiveDate, Utc};
+use octocrab::models::Repository;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+};
+
+const ACTIVITY_HISTORY: usize = 10;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct State {
+    #[serde(default)]
+    pub repos: BTreeMap<String, RepoState>,
+    pub last_full_sync: Option<DateTime<Utc>>,
+    /// stars starred after this instant haven't been seen yet
+    pub star_watermark: Option<DateTime<Utc>>,
+    /// the star list as of the last run, so incremental runs only need to list new stars
+    #[serde(default, deserialize_with = "starred")]
+    pub starred: Vec<RepoRef>,
+    /// notifications waiting to be sent together, keyed by notifier
+    #[serde(default)]
+    pub digests: BTreeMap<String, Digest>,
+    /// lowercase `owner/name`s nothing is sent about, set with the Telegram bot's `/mute`
+    #[serde(default)]
+    pub muted: BTreeSet<String>,
+    /// each repo as the last run that refreshed it saw it, keyed by `owner/name`
+    #[serde(default)]
+    pub snapshot: BTreeMap<String, Snapshot>,
+    /// of what was last written to the `owners_page`
+    #[serde(default)]
+    pub owners_hash: Option<String>,
+    /// what was last written to the `DASHBOARD_PAGE`, and the blocks it was written as
+    #[serde(default)]
+    pub dashboard_hash: Option<String>,
+    #[serde(default)]
+    pub dashboard_blocks: Vec<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct RepoState {
+    pub last_checked: Option<NaiveDate>,
+    /// release/commit dates seen so far, newest last
+    #[serde(default)]
+    pub activity: Vec<NaiveDate>,
+    #[serde(default)]
+    pub contributors_checked: Option<NaiveDate>,
+    /// tag of the latest release seen, to tell how big the next one is
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// went private or was deleted, only checked for coming back until it does
+    #[serde(default)]
+    pub unavailable: bool,
+}
+
+/// Star lists saved before they were `RepoRef`s held the API's repos as they came.
+fn starred<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<RepoRef>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Starred {
+        Slim(Vec<RepoRef>),
+        Full(Vec<Repository>),
+    }
+    Ok(match Starred::deserialize(deserializer)? {
+        Starred::Slim(repos) => repos,
+        Starred::Full(repos) => repos.into_iter().map(RepoRef::from).collect(),
+    })
+}
+
+fn path() -> String {
+    env::var("STATE_FILE").unwrap_or("notionstar-state.json".to_string())
+}
+
+pub fn env_number(name: &str, default: i64) -> i64 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+impl State {
+    /// A state file that can't be read, e.g. truncated or edited by hand, is moved aside to
+    /// `<path>.broken` and the run starts over like a first one, rather than failing every run.
+    pub fn load() -> State {
+        let path = path();
+        let Ok(content) = fs::read_to_string(&path) else {
+            return State::default();
+        };
+        match serde_json::from_str(&content) {
+            Ok(state) => state,
+            Err(err) => {
+                let aside = format!("{}.broken", path);
+                if let Err(moving) = fs::rename(&path, &aside) {
+                    eprintln!(
+                        "state file {} is unreadable ({}) and couldn't be moved aside: {}",
+                        path, err, moving
+                    );
+                    std::process::exit(1);
+                }
+                eprintln!(
+                    "state file {} is unreadable ({}), moved it to {} and starting over",
+                    path, err, aside
+                );
+                State::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let path = path();
+        if let Err(err) = fs::write(&path, serde_json::to_string_pretty(self).unwrap()) {
+            eprintln!("failed to write state file {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+
+    /// A full star listing (which is what notices unstars) runs every `FULL_SYNC_HOURS` (default 24).
+    pub fn full_sync_due(&self, now: DateTime<Utc>) -> bool {
+        match (self.last_full_sync, self.star_watermark) {
+            (Some(last_full_sync), Some(_)) => {
+                now - last_full_sync >= Duration::hours(env_number("FULL_SYNC_HOURS", 24))
+            }
+            _ => true,
+        }
+    }
+
+    pub fn full_synced(&mut self, now: DateTime<Utc>, stars: Vec<RepoRef>) {
+        self.last_full_sync = Some(now);
+        self.star_watermark = Some(now);
+        self.starred = stars;
+    }
+
+    pub fn add_stars(&mut self, stars: Vec<(DateTime<Utc>, RepoRef)>) {
+        for (starred_at, repo) in stars {
+            if self.star_watermark < Some(starred_at) {
+                self.star_watermark = Some(starred_at);
+            }
+            if !self.starred.iter().any(|starred| starred.id == repo.id) {
+                self.starred.insert(0, repo);
+            }
+        }
+    }
+
+    /// Repos without activity for `DORMANT_AFTER_DAYS` (default 30) are only checked
+    /// every `DORMANT_CHECK_DAYS` (default 7); everything else is checked on every run.
+    pub fn due(&self, repo: &str, today: NaiveDate) -> bool {
+        let state = match self.repos.get(repo) {
+            Some(state) => state,
+            None => return true,
+        };
+        let (last_checked, last_active) = match (state.last_checked, state.activity.last()) {
+            (Some(last_checked), Some(last_active)) => (last_checked, *last_active),
+            _ => return true,
+        };
+        if (today - last_active).num_days() < env_number("DORMANT_AFTER_DAYS", 30) {
+            return true;
+        }
+        (today - last_checked).num_days() >= env_number("DORMANT_CHECK_DAYS", 7)
+    }
+
+    /// Contributors are only looked up when `CONTRIBUTORS_CHECK_DAYS` is set, at most that often.
+    pub fn contributors_due(&self, repo: &str, today: NaiveDate) -> bool {
+        let days = match env::var("CONTRIBUTORS_CHECK_DAYS").map(|days| days.parse::<i64>()) {
+            Ok(Ok(days)) => days,
+            _ => return false,
+        };
+        match self
+            .repos
+            .get(repo)
+            .and_then(|state| state.contributors_checked)
+        {
+            Some(checked) => (today - checked).num_days() >= days,
+            None => true,
+        }
+    }
+
+    pub fn contributors_checked(&mut self, repo: &str, today: NaiveDate) {
+        self.repos
+            .entry(repo.to_string())
+            .or_default()
+            .contributors_checked = Some(today);
+    }
+
+    pub fn unavailable(&self, repo: &str) -> bool {
+        self.repos.get(repo).is_some_and(|state| state.unavailable)
+    }
+
+    pub fn set_unavailable(&mut self, repo: &str, unavailable: bool) {
+        self.repos.entry(repo.to_string()).or_default().unavailable = unavailable;
+    }
+
+    /// Records the latest release's tag and returns the one seen before it.
+    pub fn release_tag(&mut self, repo: &str, tag: Option<&str>) -> Option<String> {
+        let state = self.repos.entry(repo.to_string()).or_default();
+        match tag {
+            Some(tag) => state.tag.replace(tag.to_string()),
+            None => state.tag.clone(),
+        }
+    }
+
+    pub fn observe(&mut self, repo: &str, dates: &[Option<NaiveDate>], today: NaiveDate) {
+        let state = self.repos.entry(repo.to_string()).or_default();
+        state.last_checked = Some(today);
+        for date in dates.iter().flatten() {
+            if !state.activity.contains(date) {
+                state.activity.push(*date);
+            }
+        }
+        state.activity.sort();
+        let excess = state.activity.len().saturating_sub(ACTIVITY_HISTORY);
+        state.activity.drain(..excess);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{repo::RepoRef, state::env_number};
+use notion::chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::env;
+
+pub const ACTIVE: &str = "Active";
+pub const STALE: &str = "Stale";
+pub const UPSTREAM_ARCHIVED: &str = "Upstream archived";
+pub const UNSTARRED: &str = "Unstarred";
+/// went private or was deleted, left alone until GitHub shows it again
+pub const UNAVAILABLE: &str = "Unavailable";
+/// found by one of the config file's `searches` rather than starred, written whenever the
+/// database has a `Status` column
+pub const DISCOVERED: &str = "Discovered";
+
+/// Opt-in, the database needs a `Status` property with the four options above.
+pub fn enabled() -> bool {
+    env::var("TRACK_STATUS").is_ok()
+}
+
+/// Repos without a release, commit or push for `STALE_AFTER_DAYS` (default 180) are stale.
+pub fn status(repo: &RepoRef, last_active: Option<NaiveDate>, today: NaiveDate) -> &'static str {
+    if repo.archived {
+        return UPSTREAM_ARCHIVED;
+    }
+    match last_active {
+        Some(date) if (today - date).num_days() < env_number("STALE_AFTER_DAYS", 180) => ACTIVE,
+        _ => STALE,
+    }
+}
+
+pub fn property(status: &str) -> Value {
+    json!({ "status": { "name": status } })
+}
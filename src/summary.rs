@@ -0,0 +1,140 @@
+use crate::{changes::Change, client::RateLimit, error::SyncError, redact::redact};
+use notion::chrono::NaiveDate;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Default, Serialize)]
+pub struct Summary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub updated: Vec<UpdatedRepo>,
+    pub errors: Vec<SyncError>,
+    /// added repos whose create failed although the page was made, found when looking again
+    pub recovered: Vec<String>,
+    /// `old/name -> new/name` of repos transferred or renamed, whose pages were updated in place
+    pub transferred: Vec<String>,
+    /// repos found gone private or deleted this run, skipped until they can be seen again
+    pub unavailable: Vec<String>,
+    /// unstarred-looking rows without a repo URL, probably added by hand, so never archived
+    pub unmatched: Vec<String>,
+    /// what changed upstream since the last run: new releases, star jumps, archived repos
+    pub changes: Vec<Change>,
+    /// the run stopped early (e.g. `--deadline`), remaining pages are picked up next time
+    pub incomplete: bool,
+    /// stopped by Ctrl-C/SIGTERM
+    pub interrupted: bool,
+    /// requests made by endpoint category, answers from the cache left out
+    pub api_calls: BTreeMap<String, u64>,
+    /// what is left of GitHub's budget after the run
+    pub rate_limit: Option<RateLimit>,
+    /// what a run right after this one would still do, worked out with `--assert-empty-plan`
+    pub second_plan: Vec<String>,
+    /// id its writes are filed under in the `audit_log`, for `undo --run`
+    pub run: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UpdatedRepo {
+    pub name: String,
+    pub release: Option<NaiveDate>,
+    pub commit: Option<NaiveDate>,
+    pub push: Option<NaiveDate>,
+    pub branch: Option<String>,
+}
+
+impl Summary {
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty()
+            || !self.removed.is_empty()
+            || !self.updated.is_empty()
+            || !self.transferred.is_empty()
+            || !self.unavailable.is_empty()
+    }
+
+    pub async fn post(&self, http: &reqwest::Client, url: &str) -> Result<(), String> {
+        match http.post(url).json(self).send().await {
+            Ok(resp) if resp.status().is_success() => Ok(()),
+            Ok(resp) => Err(format!("webhook returned {}", resp.status())),
+            Err(err) => Err(redact(&err.to_string())),
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} archived, {} updated, {} errors{}",
+            self.added.len(),
+            self.removed.len(),
+            self.updated.len(),
+            self.errors.len(),
+            if self.incomplete { " (incomplete)" } else { "" }
+        )?;
+        if !self.recovered.is_empty() {
+            write!(
+                f,
+                "\n{} pages found created after a failed create: {}",
+                self.recovered.len(),
+                self.recovered.join(", ")
+            )?;
+        }
+        if !self.transferred.is_empty() {
+            write!(
+                f,
+                "\n{} repos moved: {}",
+                self.transferred.len(),
+                self.transferred.join(", ")
+            )?;
+        }
+        if !self.unavailable.is_empty() {
+            write!(
+                f,
+                "\n{} repos unavailable: {}",
+                self.unavailable.len(),
+                self.unavailable.join(", ")
+            )?;
+        }
+        for change in &self.changes {
+            write!(f, "\n{}", change)?;
+        }
+        if !self.unmatched.is_empty() {
+            write!(
+                f,
+                "\n{} unmatched manual rows: {}",
+                self.unmatched.len(),
+                self.unmatched.join(", ")
+            )?;
+        }
+        if !self.second_plan.is_empty() {
+            write!(
+                f,
+                "\nsecond run would still do {} things: {}",
+                self.second_plan.len(),
+                self.second_plan.join("; ")
+            )?;
+        }
+        if let Some(run) = &self.run {
+            write!(f, "\naudit log run: {}", run)?;
+        }
+        if !self.api_calls.is_empty() {
+            let calls = self
+                .api_calls
+                .iter()
+                .map(|(category, count)| format!("{} {}", category, count))
+                .collect::<Vec<String>>();
+            write!(f, "\nAPI calls: {}", calls.join(", "))?;
+        }
+        if let Some(limit) = &self.rate_limit {
+            write!(
+                f,
+                "\nGitHub rate limit: {} of {} left, resets at {}",
+                limit.remaining, limit.limit, limit.reset
+            )?;
+        }
+        Ok(())
+    }
+}
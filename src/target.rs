@@ -0,0 +1,124 @@
+use crate::repo::RepoRef;
+use clap::ValueEnum;
+use notion::chrono::NaiveDate;
+use serde_json::json;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub struct RepoRecord {
+    pub owner: String,
+    pub name: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub topics: Vec<String>,
+    pub stars: u32,
+    pub release: Option<NaiveDate>,
+    pub commit: Option<NaiveDate>,
+}
+
+impl RepoRecord {
+    pub fn new(repo: &RepoRef, release: Option<NaiveDate>, commit: Option<NaiveDate>) -> Self {
+        RepoRecord {
+            owner: repo.owner.clone(),
+            name: repo.name.clone(),
+            url: repo.html_url.clone(),
+            description: repo.description.clone(),
+            language: repo.language.clone(),
+            topics: repo.topics.clone().unwrap_or_default(),
+            stars: repo.stargazers_count,
+            release,
+            commit,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TargetKind {
+    Markdown,
+    Sqlite,
+}
+
+pub struct Target {
+    kind: TargetKind,
+    pub path: PathBuf,
+}
+
+impl Target {
+    pub fn parse_all(args: &[String]) -> Vec<Target> {
+        args.chunks(2)
+            .map(|pair| Target {
+                kind: TargetKind::from_str(&pair[0], true)
+                    .unwrap_or_else(|_| panic!("unknown target kind {}", pair[0])),
+                path: PathBuf::from(&pair[1]),
+            })
+            .collect()
+    }
+
+    pub fn write(&self, records: &[RepoRecord]) {
+        match self.kind {
+            TargetKind::Markdown => write_markdown(&self.path, records),
+            TargetKind::Sqlite => crate::sqlite::write(&self.path, records),
+        }
+    }
+
+    /// Removes what was mirrored of repos that no longer have a page, `present` being the full
+    /// names of those that do.
+    pub fn prune(&self, present: &HashSet<String>) {
+        match self.kind {
+            TargetKind::Markdown => prune_markdown(&self.path, present),
+            // rebuilt from the records on every write
+            TargetKind::Sqlite => {}
+        }
+    }
+}
+
+fn write_markdown(dir: &Path, records: &[RepoRecord]) {
+    for record in records {
+        let owner_dir = dir.join(&record.owner);
+        fs::create_dir_all(&owner_dir).unwrap();
+        // json strings are valid yaml scalars, so they double as safe frontmatter quoting
+        let mut content = format!(
+            "---\nname: {}\nowner: {}\nurl: {}\nlanguage: {}\ntopics: {}\nstars: {}\nlast_release: {}\nlast_commit: {}\n---\n\n# {}\n",
+            json!(record.name),
+            json!(record.owner),
+            json!(record.url),
+            json!(record.language),
+            json!(record.topics),
+            record.stars,
+            json!(record.release.map(|date| date.to_string())),
+            json!(record.commit.map(|date| date.to_string())),
+            record.name,
+        );
+        if let Some(description) = &record.description {
+            content += &format!("\n{}\n", description);
+        }
+        fs::write(owner_dir.join(format!("{}.md", record.name)), content).unwrap();
+    }
+}
+
+fn prune_markdown(dir: &Path, present: &HashSet<String>) {
+    let Ok(owners) = fs::read_dir(dir) else {
+        return;
+    };
+    for owner_dir in owners.flatten().map(|entry| entry.path()) {
+        let (Some(owner), Ok(files)) = (owner_dir.file_name(), fs::read_dir(&owner_dir)) else {
+            continue;
+        };
+        let owner = owner.to_string_lossy().to_string();
+        for file in files.flatten().map(|entry| entry.path()) {
+            if file.extension().and_then(|extension| extension.to_str()) != Some("md") {
+                continue;
+            }
+            let name = file.file_stem().unwrap_or_default().to_string_lossy();
+            if !present.contains(&format!("{}/{}", owner, name)) {
+                fs::remove_file(&file).unwrap();
+            }
+        }
+        // only goes when nothing else is left in it
+        fs::remove_dir(&owner_dir).ok();
+    }
+}
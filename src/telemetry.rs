@@ -0,0 +1,64 @@
+use opentelemetry::{
+    global,
+    trace::{FutureExt, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use opentelemetry_sdk::{runtime, trace, Resource};
+use std::{env, fmt::Display, future::Future};
+
+const TRACER: &str = "notionstar";
+
+/// Whether an OTLP endpoint is configured, the standard way: `OTEL_EXPORTER_OTLP_ENDPOINT` or
+/// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`. Headers and timeout come from the standard variables too.
+pub fn enabled() -> bool {
+    env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_ok()
+        || env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_ok()
+}
+
+/// Starts exporting spans over OTLP/HTTP (protobuf). Until this is called, spans go nowhere and
+/// cost next to nothing.
+pub fn init() -> Result<(), String> {
+    let service = env::var("OTEL_SERVICE_NAME").unwrap_or(TRACER.to_string());
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().http())
+        .with_trace_config(
+            trace::config().with_resource(Resource::new([KeyValue::new("service.name", service)])),
+        )
+        .install_batch(runtime::Tokio)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Exports the spans still buffered. Call it before the process exits.
+pub async fn shutdown() {
+    // blocks until the exporter task, which needs the runtime, is done
+    tokio::task::spawn_blocking(global::shutdown_tracer_provider)
+        .await
+        .ok();
+}
+
+/// Runs `future` in a span called `name`. Spans started while it runs, such as those of the API
+/// calls it makes, become its children.
+pub async fn span<F: Future>(
+    name: &'static str,
+    attributes: Vec<KeyValue>,
+    future: F,
+) -> F::Output {
+    let tracer = global::tracer(TRACER);
+    let span = tracer
+        .span_builder(name)
+        .with_attributes(attributes)
+        .start(&tracer);
+    let cx = Context::current_with_span(span);
+    let output = future.with_context(cx.clone()).await;
+    cx.span().end();
+    output
+}
+
+/// Marks the span `future` runs in as failed.
+pub fn error(err: impl Display) {
+    Context::current()
+        .span()
+        .set_status(Status::error(err.to_string()));
+}
@@ -0,0 +1,42 @@
+use serde_json::{json, Value};
+use std::env;
+
+/// Block types the API returns but won't accept when creating a page.
+const UNCOPYABLE_TYPES: [&str; 5] = [
+    "child_page",
+    "child_database",
+    "link_preview",
+    "synced_block",
+    "unsupported",
+];
+
+/// Id of the `TEMPLATE_PAGE` whose content new pages start with, given as an id or a page URL.
+pub fn page_id() -> Option<String> {
+    let page = env::var("TEMPLATE_PAGE").ok()?;
+    // URLs end in `Title-<32 hex digits>`, possibly followed by a query
+    let page = page.split(['?', '#']).next().unwrap_or_default();
+    let id = page.rsplit(['/', '-']).next().unwrap_or_default();
+    let id = if id.len() == 32 {
+        id
+    } else {
+        page.rsplit('/').next().unwrap_or_default()
+    };
+    Some(id.to_string()).filter(|id| !id.is_empty())
+}
+
+/// Turns blocks read from the template into ones a new page can be created with: only the type
+/// and its content are kept. Nested blocks (a toggle's contents, say) aren't copied, and only the
+/// first 100 blocks are, that's all a create request takes.
+pub fn content(blocks: Vec<Value>) -> Vec<Value> {
+    blocks
+        .into_iter()
+        .filter_map(|block| {
+            let kind = block["type"].as_str()?;
+            if UNCOPYABLE_TYPES.contains(&kind) {
+                return None;
+            }
+            Some(json!({ "object": "block", "type": kind, kind: block[kind] }))
+        })
+        .take(100)
+        .collect()
+}
@@ -0,0 +1,47 @@
+use crate::{error::NotionError, page_full_name, repo::RepoRef, text, text_property, Notion};
+use notion::models::Page;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// The star a page's repo became after a transfer or rename, going by where GitHub redirects
+/// the page's `owner/name` to. `stars` is keyed by repo id.
+pub async fn moved_to<'a>(
+    notion: &Notion,
+    page: &Page,
+    stars: &HashMap<u64, &'a RepoRef>,
+) -> Option<&'a RepoRef> {
+    let (owner, name) = page_full_name(page)
+        .split_once('/')
+        .map(|(owner, name)| (owner.to_string(), name.to_string()))?;
+    if owner.is_empty() {
+        return None;
+    }
+    let repo = notion.github.repository(&owner, &name).await?;
+    stars.get(&repo.id.0).copied()
+}
+
+/// Whether a page matched to `repo` by name has another owner written down: the repo was
+/// transferred with its name kept, or the page is of another repo with the same name.
+pub fn owner_changed(page: &Page, repo: &RepoRef) -> bool {
+    text_property(page, "owner")
+        .is_some_and(|owner| !owner.is_empty() && !owner.eq_ignore_ascii_case(&repo.owner))
+}
+
+/// Whether a page with another owner written down is of `repo` after all, GitHub taking its
+/// `owner/name` to the same repo. `None` when GitHub has nothing under that name, say because the
+/// lookup failed.
+pub async fn same_repo(notion: &Notion, page: &Page, repo: &RepoRef) -> Option<bool> {
+    let owner = text_property(page, "owner")?;
+    let found = notion.github.repository(&owner, &page.title()?).await?;
+    Some(found.id.0 == repo.id)
+}
+
+/// Points a page at where its repo lives now: title, owner and URL.
+pub async fn apply(notion: &Notion, page: &Page, repo: &RepoRef) -> Result<(), NotionError> {
+    let properties = json!({
+        "名称": { "title": text(repo.name.clone()) },
+        "owner": { "rich_text": text(repo.owner.clone()) },
+        "release": { "url": repo.html_url },
+    });
+    notion.patch_properties(page, "transfer", properties).await
+}
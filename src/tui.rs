@@ -0,0 +1,324 @@
+use crate::{
+    date_property, error::task_failure, full_name, kept, manual_row, page_full_name, shutdown,
+    summary::Summary, sync, sync_off, ui::Capture, Notion, SyncArgs,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Gauge, List, ListItem, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+use std::{
+    collections::HashSet,
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+/// What a sync would do to one repo.
+struct Change {
+    action: &'static str,
+    repo: String,
+    detail: String,
+    selected: bool,
+}
+
+/// The changes a full sync would make, the same way `sync` works them out.
+async fn plan(notion: &Notion) -> Vec<Change> {
+    let database = notion.get_database().await;
+    let stars = notion.get_stars().await;
+    let discovered = notion.discover(&stars).await;
+    let stars = [stars, discovered].concat();
+    let starred = stars
+        .iter()
+        .map(|star| star.name.clone())
+        .collect::<HashSet<String>>();
+    let titles = database
+        .iter()
+        .map(|page| page.title().unwrap())
+        .collect::<HashSet<String>>();
+    let mut changes = stars
+        .iter()
+        .filter(|star| !titles.contains(&star.name))
+        .map(|star| Change {
+            action: "add",
+            repo: full_name(star),
+            detail: star.description.clone().unwrap_or_default(),
+            selected: true,
+        })
+        .collect::<Vec<Change>>();
+    for page in database.iter().filter(|page| !sync_off(page)) {
+        let (action, detail) = if starred.contains(&page.title().unwrap()) {
+            let commit = date_property(page, "上次commit");
+            (
+                "refresh",
+                commit.map_or(String::new(), |commit| format!("last commit {}", commit)),
+            )
+        } else if kept(page) || manual_row(page) {
+            continue;
+        } else {
+            ("archive", "unstarred".to_string())
+        };
+        changes.push(Change {
+            action,
+            repo: page_full_name(page),
+            detail,
+            selected: true,
+        });
+    }
+    changes.sort_by(|a, b| a.action.cmp(b.action).then_with(|| a.repo.cmp(&b.repo)));
+    changes
+}
+
+enum Phase {
+    Planning(JoinHandle<Vec<Change>>),
+    Choosing,
+    Running(JoinHandle<Summary>),
+    Done(String),
+}
+
+struct App {
+    notion: Arc<Notion>,
+    capture: Arc<Mutex<Capture>>,
+    phase: Phase,
+    changes: Vec<Change>,
+    table: TableState,
+    show_errors: bool,
+}
+
+/// Shows the plan of a full sync, lets rows be deselected, then runs it with live progress.
+/// `notion` has to be opened with `Ui::captured`, its output is drawn here.
+pub async fn tui(notion: Notion) -> io::Result<()> {
+    let capture = notion
+        .ui
+        .capture()
+        .expect("the TUI draws a captured Ui's output");
+    let notion = Arc::new(notion);
+    let planning = tokio::spawn({
+        let notion = notion.clone();
+        async move { plan(&notion).await }
+    });
+    let mut app = App {
+        notion,
+        capture,
+        phase: Phase::Planning(planning),
+        changes: Vec::new(),
+        table: TableState::default(),
+        show_errors: false,
+    };
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal).await;
+    ratatui::restore();
+    if let Phase::Done(summary) = &app.phase {
+        println!("{}", summary);
+    }
+    result
+}
+
+impl App {
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            match &mut self.phase {
+                Phase::Planning(task) if task.is_finished() => {
+                    match task.await {
+                        Ok(changes) => {
+                            self.changes = changes;
+                            self.table.select((!self.changes.is_empty()).then_some(0));
+                            self.phase = Phase::Choosing;
+                        }
+                        Err(err) => self.phase = Phase::Done(task_failure(err)),
+                    }
+                    continue;
+                }
+                Phase::Running(task) if task.is_finished() => {
+                    self.phase = Phase::Done(match task.await {
+                        Ok(summary) => summary.to_string(),
+                        Err(err) => task_failure(err),
+                    });
+                    // the sync prints its summary straight to stdout
+                    terminal.clear()?;
+                    continue;
+                }
+                _ => {}
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+            // polling blocks, the sync keeps going on the other workers
+            let key = tokio::task::block_in_place(|| -> io::Result<_> {
+                if !event::poll(Duration::from_millis(100))? {
+                    return Ok(None);
+                }
+                Ok(match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => Some(key.code),
+                    _ => None,
+                })
+            })?;
+            let Some(key) = key else { continue };
+            if key == KeyCode::Char('e') {
+                self.show_errors = !self.show_errors;
+                continue;
+            }
+            match (&self.phase, key) {
+                (Phase::Running(_), KeyCode::Char('q') | KeyCode::Esc) => shutdown::request(),
+                (_, KeyCode::Char('q') | KeyCode::Esc) => return Ok(()),
+                (Phase::Choosing, KeyCode::Down | KeyCode::Char('j')) => self.table.select_next(),
+                (Phase::Choosing, KeyCode::Up | KeyCode::Char('k')) => self.table.select_previous(),
+                (Phase::Choosing, KeyCode::Char(' ')) => {
+                    if let Some(change) = self
+                        .table
+                        .selected()
+                        .and_then(|row| self.changes.get_mut(row))
+                    {
+                        change.selected = !change.selected;
+                    }
+                }
+                (Phase::Choosing, KeyCode::Char('a')) => {
+                    let select = self.changes.iter().all(|change| !change.selected);
+                    for change in &mut self.changes {
+                        change.selected = select;
+                    }
+                }
+                (Phase::Choosing, KeyCode::Enter) => self.apply(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Runs a full sync that leaves the deselected repos alone.
+    fn apply(&mut self) {
+        let exclude = self
+            .changes
+            .iter()
+            .filter(|change| !change.selected)
+            .map(|change| change.repo.clone())
+            .collect();
+        let notion = self.notion.clone();
+        self.phase = Phase::Running(tokio::spawn(async move {
+            let args = SyncArgs {
+                full: true,
+                exclude,
+                ..Default::default()
+            };
+            sync(&notion, &args).await
+        }));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let capture = self.capture.clone();
+        let capture = capture.lock().unwrap();
+        let bars = capture.bars.len().min(6) as u16;
+        let [header, main, progress, log] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(5),
+            Constraint::Length(bars),
+            Constraint::Length(8),
+        ])
+        .areas(frame.area());
+
+        let help = match &self.phase {
+            Phase::Planning(_) => "working out the plan…  q quit".to_string(),
+            Phase::Choosing => format!(
+                "{} of {} selected  ↑↓ move  space toggle  a all/none  enter apply  e errors  q quit",
+                self.changes.iter().filter(|change| change.selected).count(),
+                self.changes.len()
+            ),
+            Phase::Running(_) => "syncing…  e errors  q stop after the current page".to_string(),
+            Phase::Done(summary) => format!("done: {}  e errors  q quit", summary),
+        };
+        frame.render_widget(
+            Paragraph::new(help).style(Style::new().add_modifier(Modifier::BOLD)),
+            header,
+        );
+
+        if self.show_errors {
+            let errors = capture
+                .errors
+                .iter()
+                .map(|error| ListItem::new(error.as_str()))
+                .collect::<Vec<ListItem>>();
+            frame.render_widget(
+                List::new(errors).block(Block::bordered().title("errors")),
+                main,
+            );
+        } else {
+            self.draw_plan(frame, main);
+        }
+
+        let shown = &capture.bars[capture.bars.len() - bars as usize..];
+        let rows = Layout::vertical(vec![Constraint::Length(1); shown.len()]).split(progress);
+        for (pb, area) in shown.iter().zip(rows.iter()) {
+            let ratio = match pb.length() {
+                Some(len) if len > 0 => pb.position() as f64 / len as f64,
+                _ => f64::from(u8::from(pb.is_finished())),
+            };
+            let label = match pb.length() {
+                Some(len) => format!("{} {}/{} {}", pb.prefix(), pb.position(), len, pb.message()),
+                None => format!("{} {} {}", pb.prefix(), pb.position(), pb.message()),
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .ratio(ratio.min(1.0))
+                    .label(label)
+                    .gauge_style(Style::new().fg(Color::Cyan)),
+                *area,
+            );
+        }
+
+        let height = log.height.saturating_sub(2) as usize;
+        let lines = capture
+            .lines
+            .iter()
+            .map(|line| Line::raw(line.as_str()))
+            .chain(
+                capture
+                    .errors
+                    .iter()
+                    .map(|error| Line::styled(error.as_str(), Style::new().fg(Color::Red))),
+            )
+            .collect::<Vec<Line>>();
+        let start = lines.len().saturating_sub(height);
+        frame.render_widget(
+            Paragraph::new(lines[start..].to_vec())
+                .block(Block::bordered().title(format!("log ({} errors)", capture.errors.len()))),
+            log,
+        );
+    }
+
+    fn draw_plan(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.changes.iter().map(|change| {
+            let color = match change.action {
+                "add" => Color::Green,
+                "archive" => Color::Red,
+                _ => Color::Reset,
+            };
+            let style = match change.selected {
+                true => Style::new().fg(color),
+                false => Style::new().fg(Color::DarkGray),
+            };
+            Row::new([
+                if change.selected { "[x]" } else { "[ ]" }.to_string(),
+                change.action.to_string(),
+                change.repo.clone(),
+                change.detail.clone(),
+            ])
+            .style(style)
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(3),
+                Constraint::Length(7),
+                Constraint::Length(40),
+                Constraint::Min(10),
+            ],
+        )
+        .header(
+            Row::new(["", "action", "repo", ""]).style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title("plan"));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+}
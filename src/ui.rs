@@ -0,0 +1,119 @@
+use crate::redact::redact;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{
+    env,
+    io::{stdout, IsTerminal},
+    sync::{Arc, Mutex},
+};
+
+pub struct Ui {
+    multi: MultiProgress,
+    hidden: bool,
+    quiet: bool,
+    color: bool,
+    capture: Option<Arc<Mutex<Capture>>>,
+}
+
+/// What a `Ui::captured` would have shown, for a front end to draw itself.
+#[derive(Default)]
+pub struct Capture {
+    pub bars: Vec<ProgressBar>,
+    pub lines: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+impl Ui {
+    pub fn new(quiet: bool) -> Ui {
+        let hidden = quiet || !stdout().is_terminal();
+        let multi = MultiProgress::new();
+        if hidden {
+            multi.set_draw_target(ProgressDrawTarget::hidden());
+        }
+        Ui {
+            multi,
+            hidden,
+            quiet,
+            // https://no-color.org: any non-empty value disables color
+            color: env::var("NO_COLOR").map_or(true, |value| value.is_empty()),
+            capture: None,
+        }
+    }
+
+    /// Prints nothing, bars and messages are collected in `capture` instead.
+    pub fn captured() -> Ui {
+        Ui {
+            capture: Some(Arc::default()),
+            ..Ui::new(true)
+        }
+    }
+
+    pub fn capture(&self) -> Option<Arc<Mutex<Capture>>> {
+        self.capture.clone()
+    }
+
+    /// A bar for a phase with a known amount of work.
+    pub fn bar(&self, prefix: &'static str, len: u64) -> ProgressBar {
+        let pb = self.multi.add(ProgressBar::new(len));
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template(&format!(
+                    "{{prefix:>9}} [{{elapsed_precise}}] {{bar:40{}}} {{pos:>7}}/{{len:7}} {{per_sec:>10}} eta {{eta:<4}} {{msg}}",
+                    if self.color { ".cyan/blue" } else { "" }
+                ))
+                .unwrap(),
+        );
+        pb.set_prefix(prefix);
+        self.track(&pb);
+        pb
+    }
+
+    /// A counter for a paginated phase whose total isn't known upfront.
+    pub fn counter(&self, prefix: &'static str) -> ProgressBar {
+        let pb = self.multi.add(ProgressBar::new_spinner());
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{prefix:>9} [{elapsed_precise}] {spinner} {pos} ({per_sec}) {msg}")
+                .unwrap(),
+        );
+        pb.set_prefix(prefix);
+        self.track(&pb);
+        pb
+    }
+
+    fn track(&self, pb: &ProgressBar) {
+        if let Some(capture) = &self.capture {
+            capture.lock().unwrap().bars.push(pb.clone());
+        }
+    }
+
+    /// Informational output, printed above the bars instead of tearing through them.
+    pub fn println(&self, message: impl AsRef<str>) {
+        let message = redact(message.as_ref());
+        if let Some(capture) = &self.capture {
+            capture.lock().unwrap().lines.push(message);
+            return;
+        }
+        if self.quiet {
+            return;
+        }
+        if self.hidden {
+            println!("{}", message);
+        } else {
+            self.multi.println(message).unwrap();
+        }
+    }
+
+    /// Errors are printed even in quiet mode.
+    pub fn error(&self, message: impl AsRef<str>) {
+        let message = redact(message.as_ref());
+        if let Some(capture) = &self.capture {
+            capture.lock().unwrap().errors.push(message);
+            return;
+        }
+        if self.hidden {
+            eprintln!("{}", message);
+        } else {
+            self.multi.println(message).unwrap();
+        }
+    }
+}
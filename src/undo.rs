@@ -0,0 +1,149 @@
+use crate::{audit, Notion};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+};
+
+/// The operations of the log that make pages.
+const CREATES: [&str; 3] = ["create", "gist create", "recreate"];
+
+/// The value that empties a property of `value`'s kind, for properties that had none before.
+fn cleared(value: &Value) -> Option<Value> {
+    let kind = match value["type"].as_str() {
+        Some(kind) => kind.to_string(),
+        None => value.as_object()?.keys().find(|key| *key != "id")?.clone(),
+    };
+    let empty = match kind.as_str() {
+        "title" | "rich_text" | "multi_select" | "relation" | "people" | "files" => json!([]),
+        "checkbox" => json!(false),
+        "date" | "select" | "status" | "number" | "url" | "email" | "phone_number" => Value::Null,
+        _ => return None,
+    };
+    Some(json!({ kind: empty }))
+}
+
+/// Reverts what run `run` of the `audit_log` wrote: pages it archived are unarchived, properties
+/// get the values they had before it, and pages it created are archived. The undo is logged as a
+/// run of its own, so it can be undone too.
+pub async fn undo(notion: &Notion, run: &str) {
+    let Some(path) = &notion.config.audit_log else {
+        notion
+            .ui
+            .error("undo goes by the config file's audit_log, which isn't set");
+        return;
+    };
+    let entries = fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|entry| entry["run"] == run)
+        .collect::<Vec<Value>>();
+    if entries.is_empty() {
+        notion
+            .ui
+            .error(format!("nothing of run {} in {}", run, path.display()));
+        return;
+    }
+    audit::start_run();
+
+    // walked newest first, so what's left for each property is the value from before the run
+    let mut archived = BTreeMap::new();
+    let mut properties = BTreeMap::<String, Map<String, Value>>::new();
+    // page ids and titles; pages only go by their id, another page may have the title by now
+    let mut created = BTreeMap::new();
+    let mut unknown = BTreeSet::new();
+    for entry in entries.iter().rev() {
+        let property = entry["property"].as_str().unwrap_or_default();
+        let title = entry["new"]["title"][0]["text"]["content"].as_str();
+        let page_id = entry["page_id"].as_str().filter(|id| !id.is_empty());
+        if CREATES.contains(&entry["operation"].as_str().unwrap_or_default()) {
+            match page_id {
+                Some(page_id) => {
+                    let name = created
+                        .entry(page_id.to_string())
+                        .or_insert_with(|| page_id.to_string());
+                    if let Some(title) = title {
+                        *name = title.to_string();
+                    }
+                }
+                // logged before creates had their id
+                None => unknown.extend(title.map(str::to_string)),
+            }
+            continue;
+        }
+        let Some(page_id) = page_id else {
+            continue;
+        };
+        if property == "archived" {
+            let before = entry["old"]
+                .as_bool()
+                .or(entry["new"].as_bool().map(|new| !new));
+            if let Some(before) = before {
+                archived.insert(page_id.to_string(), before);
+            }
+            continue;
+        }
+        let before = match &entry["old"] {
+            Value::Null => cleared(&entry["new"]),
+            old => Some(old.clone()),
+        };
+        if let Some(before) = before {
+            properties
+                .entry(page_id.to_string())
+                .or_default()
+                .insert(property.to_string(), before);
+        }
+    }
+    // pages it created are archived, whatever else it wrote to them
+    archived.retain(|page_id, _| !created.contains_key(page_id));
+    properties.retain(|page_id, _| !created.contains_key(page_id));
+
+    let write = |page_id: String, body: Value| async move {
+        match notion.api.update_page(&page_id, body.clone()).await {
+            Ok(()) => {
+                audit::record(notion, "undo", Some(&page_id), None, &body);
+                true
+            }
+            Err(err) => {
+                notion
+                    .ui
+                    .error(format!("failed to undo on {}: {}", page_id, err));
+                false
+            }
+        }
+    };
+    // archived pages can't be written to, so they come back first and go last
+    for (page_id, _) in archived.iter().filter(|(_, &before)| !before) {
+        if write(page_id.clone(), json!({ "archived": false })).await {
+            notion.ui.println(format!("unarchived {}", page_id));
+        }
+    }
+    for (page_id, before) in properties {
+        let names = before.keys().cloned().collect::<Vec<String>>().join(", ");
+        let body = json!({ "properties": notion.writable(before.into()).await });
+        if write(page_id.clone(), body).await {
+            notion
+                .ui
+                .println(format!("restored {} of {}", names, page_id));
+        }
+    }
+    for (page_id, title) in created {
+        if write(page_id, json!({ "archived": true })).await {
+            notion
+                .ui
+                .println(format!("archived {}, which it created", title));
+        }
+    }
+    for title in unknown {
+        notion.ui.error(format!(
+            "{}, which it created, was logged without its id and is left alone",
+            title
+        ));
+    }
+    for (page_id, _) in archived.iter().filter(|(_, &before)| before) {
+        if write(page_id.clone(), json!({ "archived": true })).await {
+            notion.ui.println(format!("archived {} again", page_id));
+        }
+    }
+}
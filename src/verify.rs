@@ -0,0 +1,97 @@
+use crate::{
+    date_property, full_name, kept, manual_row, same_url, sync_off, text_property, url_property,
+    Notion,
+};
+use std::collections::{BTreeMap, HashSet};
+
+pub async fn verify(notion: &Notion) {
+    let database = notion.get_database().await;
+    let stars = notion.get_stars().await;
+    let mut drift = Vec::new();
+
+    let mut pages = BTreeMap::new();
+    for page in &database {
+        let title = page.title().unwrap();
+        if pages.insert(title.clone(), page).is_some() {
+            drift.push(format!("duplicate page: {}", title));
+        }
+    }
+    let starred = stars
+        .iter()
+        .map(|star| star.name.clone())
+        .collect::<HashSet<String>>();
+    for (title, page) in &pages {
+        // kept pages outliving their star is the point of `Keep`
+        if !starred.contains(title) && !kept(page) && !sync_off(page) {
+            if manual_row(page) {
+                drift.push(format!("unmatched manual row: {}", title));
+            } else {
+                drift.push(format!("not starred: {}", title));
+            }
+        }
+    }
+
+    let pb = notion.ui.bar("verifying", stars.len() as u64);
+    for star in &stars {
+        pb.set_message(full_name(star));
+        pb.inc(1);
+        let page = match pages.get(&star.name) {
+            Some(page) => page,
+            None => {
+                drift.push(format!("missing in notion: {}", full_name(star)));
+                continue;
+            }
+        };
+        if sync_off(page) {
+            continue;
+        }
+        let owner = star.owner.clone();
+        let url = star.html_url.clone();
+        let notion_url = url_property(page, "release");
+        if !notion_url
+            .as_ref()
+            .is_some_and(|stored| same_url(stored, &url))
+        {
+            drift.push(format!(
+                "url drift {}: notion {:?}, github {}",
+                star.name, notion_url, url
+            ));
+        }
+        let notion_owner = text_property(page, "owner");
+        if notion_owner.as_deref() != Some(owner.as_str()) {
+            drift.push(format!(
+                "owner drift {}: notion {:?}, github {}",
+                star.name, notion_owner, owner
+            ));
+        }
+        let (release, commit) = notion.fetch_activity(star).await;
+        let notion_release = date_property(page, "上次release");
+        if notion_release != release {
+            drift.push(format!(
+                "release drift {}: notion {:?}, github {:?}",
+                star.name, notion_release, release
+            ));
+        }
+        let notion_commit = date_property(page, "上次commit");
+        if notion_commit != commit {
+            drift.push(format!(
+                "commit drift {}: notion {:?}, github {:?}",
+                star.name, notion_commit, commit
+            ));
+        }
+    }
+    pb.finish_with_message("done");
+
+    for line in &drift {
+        println!("{}", line);
+    }
+    println!(
+        "{} stars, {} pages, {} drifted",
+        stars.len(),
+        database.len(),
+        drift.len()
+    );
+    if !drift.is_empty() {
+        std::process::exit(1);
+    }
+}
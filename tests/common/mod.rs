@@ -0,0 +1,222 @@
+#![allow(dead_code)]
+
+use notionstar::{client::ReleaseInfo, summary::Summary, sync, ui::Ui, Notion, SyncArgs};
+use serde_json::{json, Value};
+use std::env;
+use tempfile::TempDir;
+use tokio::sync::{Mutex, MutexGuard};
+
+static ENV: Mutex<()> = Mutex::const_new(());
+
+/// Points the state file and archive backups at a throwaway directory. Hold on to the result
+/// for the whole test, the environment is shared by every test in the binary.
+pub async fn isolate() -> (TempDir, MutexGuard<'static, ()>) {
+    let guard = ENV.lock().await;
+    let dir = tempfile::tempdir().unwrap();
+    env::set_var("STATE_FILE", dir.path().join("state.json"));
+    env::set_var("BACKUP_DIR", dir.path().join("backups"));
+    env::set_var("CONFIG_FILE", dir.path().join("notionstar.toml"));
+    env::remove_var("WEBHOOK_URL");
+    env::remove_var("TEMPLATE_PAGE");
+    env::remove_var("DOCKER_HUB_URL");
+    env::remove_var("HOMEBREW_API_URL");
+    env::remove_var("AUR_URL");
+    env::remove_var("REPOLOGY_URL");
+    env::remove_var("LLM_API_KEY");
+    env::remove_var("DASHBOARD_PAGE");
+    env::remove_var("OTEL_EXPORTER_OTLP_ENDPOINT");
+    env::remove_var("CONTRIBUTORS_CHECK_DAYS");
+    env::remove_var("TRACK_OPEN_PRS");
+    env::remove_var("TRACK_STATUS");
+    env::remove_var("GITHUB_WEBHOOK_SECRET");
+    env::remove_var("LANGUAGE_ICONS");
+    env::remove_var("CACHE_TTL_RELEASES");
+    env::remove_var("CACHE_TTL_EMPTY");
+    env::remove_var("CACHE_TTL_REPOLOGY");
+    env::remove_var("NOTION_API");
+    env::remove_var("NOTION_API_FILE");
+    env::remove_var("GITHUB_API");
+    env::remove_var("GITHUB_API_FILE");
+    env::remove_var("NOTION_API_URL");
+    env::remove_var("NOTION_CLIENT_ID");
+    env::remove_var("NOTION_CLIENT_SECRET");
+    env::remove_var("NOTIONSTAR_RELEASES_URL");
+    env::remove_var("GITHUB_STEP_SUMMARY");
+    env::remove_var("GITHUB_OUTPUT");
+    env::set_var("LLM_CACHE", dir.path().join("llm.json"));
+    (dir, guard)
+}
+
+pub async fn full_sync(notion: &Notion) -> Summary {
+    sync(
+        notion,
+        &SyncArgs {
+            full: true,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+pub fn quiet() -> Ui {
+    Ui::new(true)
+}
+
+pub fn user(login: &str) -> Value {
+    let url = format!("https://api.github.com/users/{}", login);
+    json!({
+        "login": login,
+        "id": 1,
+        "node_id": "MDQ6VXNlcjE=",
+        "avatar_url": format!("https://avatars.githubusercontent.com/{}", login),
+        "gravatar_id": "",
+        "url": url,
+        "html_url": format!("https://github.com/{}", login),
+        "followers_url": format!("{}/followers", url),
+        "following_url": format!("{}/following{{/other_user}}", url),
+        "gists_url": format!("{}/gists{{/gist_id}}", url),
+        "starred_url": format!("{}/starred{{/owner}}{{/repo}}", url),
+        "subscriptions_url": format!("{}/subscriptions", url),
+        "organizations_url": format!("{}/orgs", url),
+        "repos_url": format!("{}/repos", url),
+        "events_url": format!("{}/events{{/privacy}}", url),
+        "received_events_url": format!("{}/received_events", url),
+        "type": "User",
+        "site_admin": false
+    })
+}
+
+pub fn repo(owner: &str, name: &str) -> Value {
+    json!({
+        "id": 1000 + name.len(),
+        "node_id": "R_kgDOAAAAAQ",
+        "name": name,
+        "full_name": format!("{}/{}", owner, name),
+        "private": false,
+        "owner": user(owner),
+        "html_url": format!("https://github.com/{}/{}", owner, name),
+        "description": format!("{} does things", name),
+        "fork": false,
+        "url": format!("https://api.github.com/repos/{}/{}", owner, name),
+        "language": "Rust",
+        "topics": ["cli"],
+        "license": {
+            "key": "mit",
+            "name": "MIT License",
+            "node_id": "MDc6TGljZW5zZTEz",
+            "spdx_id": "MIT",
+            "url": "https://api.github.com/licenses/mit",
+            "html_url": null
+        },
+        "stargazers_count": 42,
+        "open_issues_count": 9,
+        "default_branch": "main",
+        "created_at": "2019-06-01T00:00:00Z",
+        "pushed_at": "2024-03-10T09:30:00Z",
+        "archived": false
+    })
+}
+
+pub fn release(owner: &str, name: &str, tag: &str, published_at: &str) -> Value {
+    let url = format!("https://api.github.com/repos/{}/{}/releases/1", owner, name);
+    json!({
+        "url": url,
+        "html_url": format!("https://github.com/{}/{}/releases/tag/{}", owner, name, tag),
+        "assets_url": format!("{}/assets", url),
+        "upload_url": format!("https://uploads.github.com/repos/{}/{}/releases/1/assets{{?name,label}}", owner, name),
+        "tarball_url": null,
+        "zipball_url": null,
+        "id": 1,
+        "node_id": "RE_kwDOAAAAAc4AAAAB",
+        "tag_name": tag,
+        "target_commitish": "main",
+        "name": tag,
+        "body": "bug fixes",
+        "draft": false,
+        "prerelease": false,
+        "created_at": published_at,
+        "published_at": published_at,
+        "author": user(owner),
+        "assets": []
+    })
+}
+
+pub fn commit(owner: &str, name: &str, date: &str) -> Value {
+    let sha = "6dcb09b5b57875f334f61aebed695e2e4193db5e";
+    let url = format!(
+        "https://api.github.com/repos/{}/{}/commits/{}",
+        owner, name, sha
+    );
+    let person = json!({ "name": owner, "email": format!("{}@example.com", owner), "date": date });
+    json!({
+        "url": url,
+        "sha": sha,
+        "node_id": "C_kwDOAAAAAdoAKDZkY2IwOWI1",
+        "html_url": format!("https://github.com/{}/{}/commit/{}", owner, name, sha),
+        "comments_url": format!("{}/comments", url),
+        "commit": {
+            "url": format!("https://api.github.com/repos/{}/{}/git/commits/{}", owner, name, sha),
+            "author": person,
+            "committer": person,
+            "message": "fix things",
+            "comment_count": 0,
+            "tree": { "sha": sha, "url": format!("https://api.github.com/repos/{}/{}/tree/{}", owner, name, sha) }
+        },
+        "author": null,
+        "committer": null,
+        "parents": []
+    })
+}
+
+pub fn rich_text(content: &str) -> Value {
+    json!([{
+        "type": "text",
+        "text": { "content": content, "link": null },
+        "annotations": {
+            "bold": false,
+            "italic": false,
+            "strikethrough": false,
+            "underline": false,
+            "code": false,
+            "color": "default"
+        },
+        "plain_text": content,
+        "href": null
+    }])
+}
+
+/// A database row the way the Notion API returns it.
+pub fn page(id: &str, owner: &str, name: &str, release: Option<&str>) -> Value {
+    json!({
+        "object": "page",
+        "id": id,
+        "created_time": "2024-01-01T00:00:00.000Z",
+        "last_edited_time": "2024-01-01T00:00:00.000Z",
+        "parent": { "type": "database_id", "database_id": "db" },
+        "archived": false,
+        "properties": {
+            "名称": { "id": "title", "type": "title", "title": rich_text(name) },
+            "release": {
+                "id": "pr%7Cj",
+                "type": "url",
+                "url": format!("https://github.com/{}/{}", owner, name)
+            },
+            "owner": { "id": "OHG%3B", "type": "rich_text", "rich_text": rich_text(owner) },
+            "上次release": {
+                "id": "pkvi",
+                "type": "date",
+                "date": release.map(|start| json!({ "start": start, "end": null, "time_zone": null }))
+            }
+        },
+        "url": format!("https://www.notion.so/{}", id.replace('-', ""))
+    })
+}
+
+pub fn release_info(tag: &str, published_at: &str) -> ReleaseInfo {
+    ReleaseInfo {
+        tag_name: tag.to_string(),
+        published_at: Some(published_at.parse().unwrap()),
+        body: None,
+        prerelease: false,
+    }
+}
@@ -0,0 +1,75 @@
+//! A stop request can't be taken back, so it gets a test binary of its own rather than ending
+//! the runs of every test after it.
+
+mod common;
+
+use common::{full_sync, isolate, quiet, repo, rich_text};
+use notionstar::{
+    client::{MockGithubClient, MockNotionClient, NotionClient},
+    daemon::daemon,
+    shutdown, Notion,
+};
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+
+#[tokio::test]
+async fn a_stop_request_ends_the_daemon_and_cuts_syncs_short() {
+    let (dir, _env) = isolate().await;
+    std::fs::write(
+        dir.path().join("notionstar.toml"),
+        r#"
+[[daemon.schedules]]
+cron = "* * * * * *"
+phases = ["add"]
+"#,
+    )
+    .unwrap();
+    let api = MockNotionClient::default();
+    let github = || MockGithubClient {
+        stars: vec![(
+            "2024-02-01T00:00:00Z".parse().unwrap(),
+            serde_json::from_value(repo("octo", "alpha")).unwrap(),
+        )],
+        ..Default::default()
+    };
+    let running = tokio::spawn(daemon(Arc::new(Notion::with_clients(
+        Box::new(github()),
+        Box::new(api.clone()),
+        quiet(),
+    ))));
+
+    // the schedule fires every second
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while api.pages().is_empty() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+    shutdown::request();
+    tokio::time::timeout(Duration::from_secs(10), running)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(api.pages().len(), 1);
+
+    // both look unstarred, but the run was told to stop before it got to archiving
+    api.create_page(json!({
+        "properties": {
+            "名称": { "title": rich_text("beta") },
+            "release": { "url": "https://github.com/octo/beta" },
+            "owner": { "rich_text": rich_text("octo") },
+        }
+    }))
+    .await
+    .unwrap();
+    let summary = full_sync(&Notion::with_clients(
+        Box::new(MockGithubClient::default()),
+        Box::new(api.clone()),
+        quiet(),
+    ))
+    .await;
+    assert!(summary.interrupted);
+    assert!(summary.removed.is_empty());
+    assert!(api.pages().iter().all(|page| !page.archived));
+}
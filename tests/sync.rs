@@ -0,0 +1,576 @@
+mod common;
+
+use common::{commit, full_sync, isolate, page, quiet, release, repo};
+use notion::chrono::NaiveDate;
+use notionstar::{
+    bench,
+    client::{
+        self, GithubClient, HttpGithubClient, HttpNotionClient, NotionClient, DEFAULT_VERSION,
+    },
+    picker, Notion,
+};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::{
+    matchers::{body_partial_json, header, method, path, query_param},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+
+const ALPHA: &str = "11111111-1111-1111-1111-111111111111";
+const GAMMA: &str = "33333333-3333-3333-3333-333333333333";
+
+async fn github() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/user/starred"))
+        .and(query_param("page", "1"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!([repo("octo", "alpha"), repo("octo", "beta")])),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/user/starred"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octo/alpha/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(release(
+            "octo",
+            "alpha",
+            "v1.2.0",
+            "2024-03-01T12:00:00Z",
+        )))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octo/alpha/commits"))
+        .and(query_param("sha", "main"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([commit(
+            "octo",
+            "alpha",
+            "2024-03-05T08:00:00Z"
+        )])))
+        .mount(&server)
+        .await;
+    // gamma was unstarred rather than gone
+    let mut gamma = repo("octo", "gamma");
+    gamma["id"] = json!(3000);
+    Mock::given(method("GET"))
+        .and(path("/repos/octo/gamma"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(gamma))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn notion() -> MockServer {
+    let server = MockServer::start().await;
+    // newer property types the notion crate can't parse on its own
+    let mut alpha = page(ALPHA, "octo", "alpha", Some("2024-01-01"));
+    alpha["properties"]["ID"] = json!({
+        "id": "%3DbBn",
+        "type": "unique_id",
+        "unique_id": { "prefix": "STAR", "number": 7 }
+    });
+    alpha["properties"]["Open"] = json!({ "id": "k%5Bq%3F", "type": "button", "button": {} });
+    Mock::given(method("GET"))
+        .and(path("/v1/databases/db"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "database",
+            "id": "db",
+            "properties": {
+                "名称": { "id": "title", "name": "名称", "type": "title", "title": {} },
+                "release": { "id": "pr%7Cj", "name": "release", "type": "url", "url": {} },
+                "Created": { "id": "c%40t", "name": "Created", "type": "created_time", "created_time": {} },
+                "Language": { "id": "l%3Ang", "name": "Language", "type": "select", "select": { "options": [] } },
+                "Topics": { "id": "t%3Apc", "name": "Topics", "type": "multi_select", "multi_select": { "options": [] } },
+                "License": { "id": "l%3Acs", "name": "License", "type": "select", "select": { "options": [] } }
+            }
+        })))
+        .mount(&server)
+        .await;
+    // select options for language, topics and license
+    Mock::given(method("PATCH"))
+        .and(path("/v1/databases/db"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "database",
+            "id": "db"
+        })))
+        .expect(1..)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/databases/db/query"))
+        .and(header("Notion-Version", "2022-06-28"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "results": [
+                alpha,
+                page(GAMMA, "octo", "gamma", None),
+            ],
+            "next_cursor": null,
+            "has_more": false
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn client(github: &MockServer, notion: &MockServer) -> Notion {
+    let timeout = Duration::from_secs(5);
+    Notion::with_clients(
+        Box::new(HttpGithubClient::new(
+            "ghp_test".to_string(),
+            &github.uri(),
+            timeout,
+        )),
+        Box::new(HttpNotionClient::new(
+            "secret_test".to_string(),
+            "db".to_string(),
+            &format!("{}/v1", notion.uri()),
+            DEFAULT_VERSION.to_string(),
+            timeout,
+        )),
+        quiet(),
+    )
+}
+
+#[tokio::test]
+async fn full_sync_adds_archives_and_updates() {
+    let _env = isolate().await;
+    let github = github().await;
+    let notion = notion().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/pages"))
+        .and(body_partial_json(json!({
+            "parent": { "database_id": "db" },
+            "properties": { "release": { "url": "https://github.com/octo/beta" } }
+        })))
+        // Created is a created_time column here, so it must not be written
+        .and(|request: &Request| {
+            let body: serde_json::Value = request.body_json().unwrap();
+            body["properties"].get("Created").is_none()
+        })
+        .respond_with(ResponseTemplate::new(200).set_body_json(page(
+            "22222222-2222-2222-2222-222222222222",
+            "octo",
+            "beta",
+            None,
+        )))
+        .expect(1)
+        .mount(&notion)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("/v1/pages/{}", GAMMA)))
+        .and(body_partial_json(json!({ "archived": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&notion)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("/v1/pages/{}", ALPHA)))
+        .and(body_partial_json(json!({
+            "properties": { "上次release": { "date": { "start": "2024-03-01" } } }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&notion)
+        .await;
+
+    let summary = full_sync(&client(&github, &notion)).await;
+
+    assert_eq!(summary.added, ["beta"]);
+    assert_eq!(summary.removed, ["gamma"]);
+    assert_eq!(summary.updated.len(), 1);
+    assert_eq!(summary.updated[0].name, "alpha");
+    assert!(summary.errors.is_empty());
+}
+
+#[tokio::test]
+async fn failed_archive_is_reported_with_request_id() {
+    let (dir, _env) = isolate().await;
+    let github = github().await;
+    let notion = notion().await;
+    // failures skip the digest and go out right away
+    std::fs::write(
+        dir.path().join("notionstar.toml"),
+        format!(
+            "[notifiers.ops]\nkind = \"webhook\"\nurl = \"{}/hooks/ops\"\n\n[[routes]]\nnotifier = \"ops\"\nevery = \"7d\"\n",
+            notion.uri()
+        ),
+    )
+    .unwrap();
+    Mock::given(method("POST"))
+        .and(path("/hooks/ops"))
+        .and(body_partial_json(
+            json!({ "events": [{ "kind": "failure" }] }),
+        ))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&notion)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/pages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&notion)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("/v1/pages/{}", GAMMA)))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "object": "error",
+            "status": 409,
+            "code": "conflict_error",
+            "message": "Conflict occurred while saving. Please try again.",
+            "request_id": "4e1f6ac2-0d3a-4f8c-9d0e-5b8c1f2a7e11"
+        })))
+        .mount(&notion)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("/v1/pages/{}", ALPHA)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&notion)
+        .await;
+
+    let summary = full_sync(&client(&github, &notion)).await;
+
+    assert!(summary.removed.is_empty());
+    assert_eq!(summary.errors.len(), 1);
+    let err = &summary.errors[0];
+    assert_eq!(err.operation, "archive");
+    assert_eq!(err.repo, "gamma");
+    assert_eq!(err.page_id.as_deref(), Some(GAMMA));
+    assert_eq!(err.error.status, 409);
+    assert_eq!(
+        err.error.request_id.as_deref(),
+        Some("4e1f6ac2-0d3a-4f8c-9d0e-5b8c1f2a7e11")
+    );
+}
+
+#[tokio::test]
+async fn replay_reproduces_a_recorded_run() {
+    let env = isolate().await;
+    let fixtures = tempfile::tempdir().unwrap();
+    let github = github().await;
+    let notion = notion().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/pages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&notion)
+        .await;
+    Mock::given(method("PATCH"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&notion)
+        .await;
+    let recorded = full_sync(&client(&github, &notion).record(fixtures.path())).await;
+    drop((github, notion, env));
+
+    let _env = isolate().await;
+    let replayed = full_sync(&Notion::replay(fixtures.path(), quiet())).await;
+
+    assert_eq!(replayed.added, recorded.added);
+    assert_eq!(replayed.removed, recorded.removed);
+    assert_eq!(replayed.updated.len(), recorded.updated.len());
+    assert_eq!(replayed.updated[0].release, recorded.updated[0].release);
+}
+
+#[tokio::test]
+async fn bench_times_syncs_over_recorded_fixtures() {
+    let env = isolate().await;
+    let fixtures = tempfile::tempdir().unwrap();
+    let github = github().await;
+    let notion = notion().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/pages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&notion)
+        .await;
+    Mock::given(method("PATCH"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&notion)
+        .await;
+    full_sync(&client(&github, &notion).record(fixtures.path())).await;
+    drop((github, notion, env));
+
+    let (dir, _env) = isolate().await;
+    let state = dir.path().join("state.json");
+    let bench = bench::bench(fixtures.path(), 3).await;
+    assert_eq!(bench.runs.len(), 3);
+    assert_eq!((bench.stars, bench.pages), (2, 2));
+    assert!(bench
+        .to_string()
+        .starts_with("3 runs over 2 stars and 2 pages: mean "));
+    // the real state is left alone
+    assert!(!state.exists());
+}
+
+#[tokio::test]
+async fn release_feed_is_read_before_the_api() {
+    let github = github().await;
+    Mock::given(method("GET"))
+        .and(path("/octo/beta/releases.atom"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>tag:github.com,2008:https://github.com/octo/beta/releases</id>
+  <entry>
+    <id>tag:github.com,2008:Repository/2/pkg/v2.2.0-rc.1</id>
+    <updated>2024-04-09T09:30:00Z</updated>
+  </entry>
+  <entry>
+    <id>tag:github.com,2008:Repository/2/pkg/v2.1.0</id>
+    <updated>2024-04-02T09:30:00Z</updated>
+    <title>v2.1.0</title>
+    <content type="html">&lt;h2&gt;Security&lt;/h2&gt;&lt;p&gt;Fixes CVE-2024-1 &amp;amp; more&lt;/p&gt;</content>
+  </entry>
+  <entry>
+    <id>tag:github.com,2008:Repository/2/pkg/v2.0.0</id>
+    <updated>2024-01-02T09:30:00Z</updated>
+  </entry>
+</feed>"#,
+        ))
+        .mount(&github)
+        .await;
+    let client = HttpGithubClient::new(
+        "ghp_test".to_string(),
+        &github.uri(),
+        Duration::from_secs(5),
+    )
+    .with_release_feed(&github.uri());
+
+    // the release candidate on top is passed over
+    let release = client.feed_release("octo", "beta").await.unwrap().unwrap();
+    assert_eq!(release.tag_name, "pkg/v2.1.0");
+    assert!(!release.prerelease);
+    assert_eq!(
+        release.body.as_deref(),
+        Some("Security\nFixes CVE-2024-1 & more")
+    );
+    // `updated` is an edit time, the date is left to the API
+    assert_eq!(release.published_at, None);
+    // alpha has no feed, the API answers for it
+    assert!(client.feed_release("octo", "alpha").await.is_none());
+    let release = client.latest_release("octo", "alpha").await.unwrap();
+    assert_eq!(release.tag_name, "v1.2.0");
+}
+
+#[tokio::test]
+async fn latest_discussion_comes_from_graphql() {
+    let github = github().await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_partial_json(
+            json!({ "variables": { "owner": "octo", "name": "alpha" } }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "repository": { "discussions": {
+                "nodes": [{ "createdAt": "2024-05-06T07:08:09Z" }]
+            } } }
+        })))
+        .mount(&github)
+        .await;
+    let client = HttpGithubClient::new(
+        "ghp_test".to_string(),
+        &github.uri(),
+        Duration::from_secs(5),
+    );
+
+    assert_eq!(
+        client.latest_discussion("octo", "alpha").await,
+        NaiveDate::from_ymd_opt(2024, 5, 6)
+    );
+    // no mock for beta's query, like a repo with discussions turned off
+    assert_eq!(client.latest_discussion("octo", "beta").await, None);
+}
+
+#[tokio::test]
+async fn failed_searches_say_why() {
+    let github = github().await;
+    Mock::given(method("GET"))
+        .and(path("/search/repositories"))
+        .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+            "message": "Validation Failed"
+        })))
+        .mount(&github)
+        .await;
+    let client = HttpGithubClient::new(
+        "ghp_test".to_string(),
+        &github.uri(),
+        Duration::from_secs(5),
+    );
+
+    let err = client.search_repositories("topic:").await.unwrap_err();
+    assert!(err.contains("422"), "{}", err);
+}
+
+#[tokio::test]
+async fn picked_database_is_saved_to_the_config() {
+    let (dir, _env) = isolate().await;
+    let notion = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/search"))
+        .and(body_partial_json(
+            json!({ "filter": { "property": "object", "value": "database" } }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [
+                { "object": "database", "id": ALPHA, "title": [{ "plain_text": "GitHub " }, { "plain_text": "Stars" }] },
+                { "object": "database", "id": GAMMA, "title": [] },
+            ],
+            "next_cursor": null,
+        })))
+        .mount(&notion)
+        .await;
+    let client = HttpNotionClient::new(
+        "secret_test".to_string(),
+        String::new(),
+        &format!("{}/v1", notion.uri()),
+        DEFAULT_VERSION.to_string(),
+        Duration::from_secs(5),
+    );
+
+    let databases = client.search_databases().await.unwrap();
+    assert_eq!(
+        databases,
+        [
+            (ALPHA.to_string(), "GitHub Stars".to_string()),
+            (GAMMA.to_string(), String::new()),
+        ]
+    );
+
+    let config = dir.path().join("notionstar.toml");
+    std::fs::write(&config, "[defaults]\nbranch = \"dev\"\n").unwrap();
+    picker::save_database(&config, ALPHA).unwrap();
+    let config: toml::Value = toml::from_str(&std::fs::read_to_string(config).unwrap()).unwrap();
+    assert_eq!(config["database"].as_str(), Some(ALPHA));
+    assert_eq!(config["defaults"]["branch"].as_str(), Some("dev"));
+
+    // an answer that isn't a search result is reported, not a panic
+    notion.reset().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/search"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html></html>"))
+        .mount(&notion)
+        .await;
+    assert!(client.search_databases().await.is_err());
+}
+
+#[tokio::test]
+async fn preflight_explains_what_is_wrong() {
+    let _env = isolate().await;
+    assert_eq!(
+        client::database_id(
+            "https://www.notion.so/team/Stars-0123456789abcdef0123456789ABCDEF?v=42"
+        ),
+        Some("0123456789abcdef0123456789abcdef".to_string())
+    );
+    assert_eq!(client::database_id(ALPHA), Some(ALPHA.replace('-', "")));
+    assert_eq!(client::database_id("stars"), None);
+
+    let github = MockServer::start().await;
+    let notion = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/databases/db"))
+        .and(header("authorization", "Bearer secret_test"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "object": "error", "status": 404, "code": "object_not_found",
+            "message": "Could not find database with ID: db.",
+        })))
+        .mount(&notion)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/databases/db"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "object": "error", "status": 401, "code": "unauthorized",
+            "message": "API token is invalid.",
+        })))
+        .mount(&notion)
+        .await;
+
+    let err = client(&github, &notion).preflight("db").await.unwrap_err();
+    assert!(err.contains("isn't shared with the integration"), "{}", err);
+
+    let revoked = Notion::with_clients(
+        Box::new(HttpGithubClient::new(
+            "ghp_test".to_string(),
+            &github.uri(),
+            Duration::from_secs(5),
+        )),
+        Box::new(HttpNotionClient::new(
+            "secret_revoked".to_string(),
+            "db".to_string(),
+            &format!("{}/v1", notion.uri()),
+            DEFAULT_VERSION.to_string(),
+            Duration::from_secs(5),
+        )),
+        quiet(),
+    );
+    let err = revoked.preflight("db").await.unwrap_err();
+    assert!(err.starts_with("NOTION_API was rejected"), "{}", err);
+}
+
+#[tokio::test]
+async fn retries_only_repeat_what_is_safe_to_repeat() {
+    let notion = MockServer::start().await;
+    let busy = || {
+        ResponseTemplate::new(503)
+            .insert_header("retry-after", "0")
+            .set_body_json(json!({ "object": "error", "status": 503, "code": "service_unavailable", "message": "busy" }))
+    };
+    // an update is sent again until it goes through
+    Mock::given(method("PATCH"))
+        .and(path(format!("/v1/pages/{}", ALPHA)))
+        .respond_with(busy())
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&notion)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!("/v1/pages/{}", ALPHA)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&notion)
+        .await;
+    // the create failed on the way back, but the page is there
+    Mock::given(method("POST"))
+        .and(path("/v1/pages"))
+        .respond_with(busy())
+        .expect(1)
+        .mount(&notion)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/databases/db/query"))
+        .and(body_partial_json(json!({
+            "filter": { "property": "release", "url": { "equals": "https://github.com/octo/alpha" } }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [page(ALPHA, "octo", "alpha", Some("2024-01-01"))],
+            "next_cursor": null,
+        })))
+        .expect(1)
+        .mount(&notion)
+        .await;
+    let client = HttpNotionClient::new(
+        "secret_test".to_string(),
+        "db".to_string(),
+        &format!("{}/v1", notion.uri()),
+        DEFAULT_VERSION.to_string(),
+        Duration::from_secs(5),
+    )
+    .with_retries(2);
+
+    client
+        .update_page(ALPHA, json!({ "properties": {} }))
+        .await
+        .unwrap();
+    client
+        .create_page(json!({ "properties": {
+            "Name": { "title": [{ "text": { "content": "alpha" } }] },
+            "release": { "url": "https://github.com/octo/alpha" },
+        } }))
+        .await
+        .unwrap();
+}